@@ -30,13 +30,19 @@ fn create_bindings(
         .header(apr_path.join("apr_version.h").to_str().unwrap())
         .header(apu_path.join("apu_version.h").to_str().unwrap())
         .header(apr_path.join("apr_strings.h").to_str().unwrap())
+        .header(apr_path.join("apr_escape.h").to_str().unwrap())
         .header(apr_path.join("apr_thread_proc.h").to_str().unwrap())
         .header(apr_path.join("apr_thread_mutex.h").to_str().unwrap())
         .header(apr_path.join("apr_thread_cond.h").to_str().unwrap())
+        .header(apr_path.join("apr_thread_rwlock.h").to_str().unwrap())
+        .header(apr_path.join("apr_proc_mutex.h").to_str().unwrap())
+        .header(apr_path.join("apr_global_mutex.h").to_str().unwrap())
         .header(apr_path.join("apr_dso.h").to_str().unwrap())
         .header(apr_path.join("apr_env.h").to_str().unwrap())
         .header(apr_path.join("apr_network_io.h").to_str().unwrap())
+        .header(apr_path.join("apr_poll.h").to_str().unwrap())
         .header(apr_path.join("apr_mmap.h").to_str().unwrap())
+        .header(apr_path.join("apr_shm.h").to_str().unwrap())
         .header(apr_path.join("apr_user.h").to_str().unwrap())
         .header(apu_path.join("apr_md5.h").to_str().unwrap())
         .header(apu_path.join("apr_sha1.h").to_str().unwrap())
@@ -47,6 +53,8 @@ fn create_bindings(
         .header(apu_path.join("apr_xml.h").to_str().unwrap())
         .header(apu_path.join("apr_crypto.h").to_str().unwrap())
         .header(apu_path.join("apr_queue.h").to_str().unwrap())
+        .header(apu_path.join("apr_rmm.h").to_str().unwrap())
+        .header(apu_path.join("apr_buckets.h").to_str().unwrap())
         .header_contents(
             "platform_headers.h",
             if cfg!(windows) {
@@ -70,15 +78,21 @@ fn create_bindings(
         .allowlist_file(".*[/\\\\]apr_time.h")
         .allowlist_file(".*[/\\\\]apr_date.h")
         .allowlist_file(".*[/\\\\]apr_strings.h")
+        .allowlist_file(".*[/\\\\]apr_escape.h")
         .allowlist_file(".*[/\\\\]apr_version.h")
         .allowlist_file(".*[/\\\\]apu_version.h")
         .allowlist_file(".*[/\\\\]apr_thread_proc.h")
         .allowlist_file(".*[/\\\\]apr_thread_mutex.h")
         .allowlist_file(".*[/\\\\]apr_thread_cond.h")
+        .allowlist_file(".*[/\\\\]apr_thread_rwlock.h")
+        .allowlist_file(".*[/\\\\]apr_proc_mutex.h")
+        .allowlist_file(".*[/\\\\]apr_global_mutex.h")
         .allowlist_file(".*[/\\\\]apr_dso.h")
         .allowlist_file(".*[/\\\\]apr_env.h")
         .allowlist_file(".*[/\\\\]apr_network_io.h")
+        .allowlist_file(".*[/\\\\]apr_poll.h")
         .allowlist_file(".*[/\\\\]apr_mmap.h")
+        .allowlist_file(".*[/\\\\]apr_shm.h")
         .allowlist_file(".*[/\\\\]apr_user.h")
         .allowlist_file(".*[/\\\\]apr_md5.h")
         .allowlist_file(".*[/\\\\]apr_sha1.h")
@@ -89,6 +103,8 @@ fn create_bindings(
         .allowlist_file(".*[/\\\\]apr_xml.h")
         .allowlist_file(".*[/\\\\]apr_crypto.h")
         .allowlist_file(".*[/\\\\]apr_queue.h")
+        .allowlist_file(".*[/\\\\]apr_rmm.h")
+        .allowlist_file(".*[/\\\\]apr_buckets.h")
         .allowlist_file(".*[/\\\\]apr_portable.h")
         .allowlist_file(".*[/\\\\]apr_support.h")
         // Explicitly allowlist fundamental APR types that may be defined via
@@ -0,0 +1,748 @@
+//! Bucket brigades
+//!
+//! Bucket brigades are APR-util's mechanism for passing streams of data between
+//! layers without copying: a brigade is a ring of buckets, each of which refers
+//! to a chunk of data (in memory, in a file, in a memory map) or carries
+//! metadata such as flush and end-of-stream markers.
+//!
+//! Most of the C bucket API consists of macros that bindgen can't see, so the
+//! ring manipulation is reimplemented here on top of the bound structures.
+
+use crate::file::File;
+use crate::mmap::Mmap;
+use crate::pool::Pool;
+use crate::{Error, Result, Status};
+use core::ffi::{c_char, c_void, CStr};
+use core::marker::PhantomData;
+use core::ptr;
+use std::io::{Read, Write};
+
+/// Sentinel "bucket" of a brigade's ring, as computed by `APR_BRIGADE_SENTINEL`.
+unsafe fn sentinel(bb: *mut apr_sys::apr_bucket_brigade) -> *mut apr_sys::apr_bucket {
+    let list = ptr::addr_of_mut!((*bb).list) as *mut u8;
+    list.sub(core::mem::offset_of!(apr_sys::apr_bucket, link)) as *mut apr_sys::apr_bucket
+}
+
+unsafe fn first(bb: *mut apr_sys::apr_bucket_brigade) -> *mut apr_sys::apr_bucket {
+    (*bb).list.next
+}
+
+unsafe fn is_empty(bb: *mut apr_sys::apr_bucket_brigade) -> bool {
+    first(bb) == sentinel(bb)
+}
+
+/// Equivalent of `APR_BUCKET_INIT`: make the bucket a ring of one.
+unsafe fn bucket_init(e: *mut apr_sys::apr_bucket) {
+    (*e).link.next = e;
+    (*e).link.prev = e;
+}
+
+/// Equivalent of `APR_RING_INSERT_BEFORE`.
+unsafe fn insert_before(lep: *mut apr_sys::apr_bucket, nep: *mut apr_sys::apr_bucket) {
+    let prev = (*lep).link.prev;
+    (*nep).link.next = lep;
+    (*nep).link.prev = prev;
+    (*prev).link.next = nep;
+    (*lep).link.prev = nep;
+}
+
+/// Equivalent of `APR_BUCKET_REMOVE`.
+unsafe fn bucket_remove(e: *mut apr_sys::apr_bucket) {
+    let next = (*e).link.next;
+    let prev = (*e).link.prev;
+    (*prev).link.next = next;
+    (*next).link.prev = prev;
+    bucket_init(e);
+}
+
+/// Equivalent of `apr_bucket_destroy`.
+unsafe fn bucket_destroy(e: *mut apr_sys::apr_bucket) {
+    if let Some(destroy) = (*(*e).type_).destroy {
+        destroy((*e).data);
+    }
+    if let Some(free) = (*e).free {
+        free(e as *mut c_void);
+    }
+}
+
+/// Equivalent of `apr_bucket_read`.
+unsafe fn bucket_read<'a>(e: *mut apr_sys::apr_bucket) -> Result<&'a [u8]> {
+    let read = match (*(*e).type_).read {
+        Some(read) => read,
+        None => return Err(Error::from_status(Status::NotImplemented)),
+    };
+    let mut data: *const c_char = ptr::null();
+    let mut len: apr_sys::apr_size_t = 0;
+    let status = read(
+        e,
+        &mut data,
+        &mut len,
+        apr_sys::apr_read_type_e_APR_BLOCK_READ,
+    );
+    if status != apr_sys::APR_SUCCESS as i32 {
+        return Err(Error::from_status(status.into()));
+    }
+    if data.is_null() || len == 0 {
+        Ok(&[])
+    } else {
+        Ok(core::slice::from_raw_parts(data as *const u8, len))
+    }
+}
+
+/// Equivalent of `apr_bucket_split`.
+unsafe fn bucket_split(e: *mut apr_sys::apr_bucket, point: usize) -> Result<()> {
+    let split = match (*(*e).type_).split {
+        Some(split) => split,
+        None => return Err(Error::from_status(Status::NotImplemented)),
+    };
+    let status = split(e, point);
+    if status != apr_sys::APR_SUCCESS as i32 {
+        return Err(Error::from_status(status.into()));
+    }
+    Ok(())
+}
+
+/// Allocator used for buckets and their data.
+///
+/// The allocator is created in a pool and destroyed together with it, so this
+/// is a cheap handle that can be cloned and shared between brigades.
+#[derive(Clone)]
+pub struct BucketAlloc<'pool> {
+    raw: *mut apr_sys::apr_bucket_alloc_t,
+    _phantom: PhantomData<&'pool Pool<'pool>>,
+}
+
+impl<'pool> BucketAlloc<'pool> {
+    /// Create a new bucket allocator that lives as long as `pool`
+    pub fn new(pool: &'pool Pool<'pool>) -> Self {
+        let raw = unsafe { apr_sys::apr_bucket_alloc_create(pool.as_mut_ptr()) };
+        BucketAlloc {
+            raw,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Get a raw pointer to the underlying bucket allocator
+    pub fn as_ptr(&self) -> *mut apr_sys::apr_bucket_alloc_t {
+        self.raw
+    }
+}
+
+/// A bucket that is not (yet) part of a brigade.
+///
+/// Buckets are inserted into a brigade with [`Brigade::push_back`] or
+/// [`Brigade::push_front`]; a bucket that is dropped without being inserted
+/// is destroyed.
+pub struct Bucket<'a> {
+    raw: *mut apr_sys::apr_bucket,
+    _phantom: PhantomData<&'a [u8]>,
+}
+
+impl<'a> Bucket<'a> {
+    fn from_created(raw: *mut apr_sys::apr_bucket) -> Result<Self> {
+        if raw.is_null() {
+            return Err(Error::from_status(Status::from(apr_sys::APR_ENOMEM as i32)));
+        }
+        Ok(Bucket {
+            raw,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Create a heap bucket holding a copy of `data`
+    pub fn heap(data: &[u8], alloc: &BucketAlloc<'a>) -> Result<Self> {
+        // Without a free function, APR copies the data into its own buffer.
+        Self::from_created(unsafe {
+            apr_sys::apr_bucket_heap_create(
+                data.as_ptr() as *const c_char,
+                data.len(),
+                None,
+                alloc.raw,
+            )
+        })
+    }
+
+    /// Create a bucket referring to data allocated in `pool`
+    pub fn pool(data: &'a [u8], pool: &'a Pool<'a>, alloc: &BucketAlloc<'a>) -> Result<Self> {
+        Self::from_created(unsafe {
+            apr_sys::apr_bucket_pool_create(
+                data.as_ptr() as *const c_char,
+                data.len(),
+                pool.as_mut_ptr(),
+                alloc.raw,
+            )
+        })
+    }
+
+    /// Create a bucket referring to short-lived data
+    ///
+    /// APR copies the data if the bucket is set aside, but otherwise reads it
+    /// in place, so it must outlive the brigade the bucket is inserted into.
+    pub fn transient(data: &'a [u8], alloc: &BucketAlloc<'a>) -> Result<Self> {
+        Self::from_created(unsafe {
+            apr_sys::apr_bucket_transient_create(
+                data.as_ptr() as *const c_char,
+                data.len(),
+                alloc.raw,
+            )
+        })
+    }
+
+    /// Create a bucket referring to data that is never freed
+    pub fn immortal(data: &'static [u8], alloc: &BucketAlloc<'a>) -> Result<Self> {
+        Self::from_created(unsafe {
+            apr_sys::apr_bucket_immortal_create(
+                data.as_ptr() as *const c_char,
+                data.len(),
+                alloc.raw,
+            )
+        })
+    }
+
+    /// Create a bucket reading `len` bytes from `file`, starting at `offset`
+    pub fn file(
+        file: &'a File,
+        offset: i64,
+        len: usize,
+        pool: &'a Pool<'a>,
+        alloc: &BucketAlloc<'a>,
+    ) -> Result<Self> {
+        Self::from_created(unsafe {
+            apr_sys::apr_bucket_file_create(
+                file.as_mut_ptr(),
+                offset as apr_sys::apr_off_t,
+                len,
+                pool.as_mut_ptr(),
+                alloc.raw,
+            )
+        })
+    }
+
+    /// Create a bucket referring to `len` bytes of `mmap`, starting at `start`
+    ///
+    /// The bucket takes over the memory map and deletes it once the last
+    /// bucket referring to it is destroyed.
+    pub fn mmap(mmap: Mmap<'a>, start: i64, len: usize, alloc: &BucketAlloc<'a>) -> Result<Self> {
+        let mut mmap = mmap;
+        let raw = unsafe {
+            apr_sys::apr_bucket_mmap_create(
+                mmap.as_mut_ptr(),
+                start as apr_sys::apr_off_t,
+                len,
+                alloc.raw,
+            )
+        };
+        if !raw.is_null() {
+            core::mem::forget(mmap);
+        }
+        Self::from_created(raw)
+    }
+
+    /// Create a flush metadata bucket
+    pub fn flush(alloc: &BucketAlloc<'a>) -> Result<Self> {
+        Self::from_created(unsafe { apr_sys::apr_bucket_flush_create(alloc.raw) })
+    }
+
+    /// Create an end-of-stream metadata bucket
+    pub fn eos(alloc: &BucketAlloc<'a>) -> Result<Self> {
+        Self::from_created(unsafe { apr_sys::apr_bucket_eos_create(alloc.raw) })
+    }
+
+    /// Borrow this bucket
+    pub fn as_ref(&self) -> BucketRef<'_> {
+        BucketRef {
+            raw: self.raw,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Get a raw pointer to the underlying bucket
+    pub fn as_ptr(&self) -> *mut apr_sys::apr_bucket {
+        self.raw
+    }
+
+    fn into_raw(self) -> *mut apr_sys::apr_bucket {
+        let raw = self.raw;
+        core::mem::forget(self);
+        raw
+    }
+}
+
+impl<'a> Drop for Bucket<'a> {
+    fn drop(&mut self) {
+        unsafe { bucket_destroy(self.raw) }
+    }
+}
+
+/// A borrowed bucket, for example one inside a brigade.
+#[derive(Clone, Copy)]
+pub struct BucketRef<'b> {
+    raw: *mut apr_sys::apr_bucket,
+    _phantom: PhantomData<&'b apr_sys::apr_bucket>,
+}
+
+impl<'b> BucketRef<'b> {
+    /// Read the data in this bucket, blocking if necessary
+    ///
+    /// Reading a bucket whose length is not yet known (such as a file bucket)
+    /// may replace it with an in-memory bucket holding the data read so far.
+    pub fn read(&self) -> Result<&'b [u8]> {
+        unsafe { bucket_read(self.raw) }
+    }
+
+    /// Length of the data in this bucket, or `None` if it is not known until read
+    pub fn len(&self) -> Option<usize> {
+        let len = unsafe { (*self.raw).length };
+        if len == usize::MAX {
+            None
+        } else {
+            Some(len)
+        }
+    }
+
+    /// Whether this bucket is known to contain no data
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+
+    /// Name of the bucket type, e.g. `"HEAP"` or `"EOS"`
+    pub fn type_name(&self) -> &'static str {
+        unsafe {
+            let name = (*(*self.raw).type_).name;
+            if name.is_null() {
+                return "";
+            }
+            CStr::from_ptr(name).to_str().unwrap_or("")
+        }
+    }
+
+    /// Whether this is a metadata bucket rather than a data bucket
+    pub fn is_metadata(&self) -> bool {
+        unsafe { (*(*self.raw).type_).is_metadata != 0 }
+    }
+
+    /// Whether this is an end-of-stream bucket
+    pub fn is_eos(&self) -> bool {
+        unsafe { (*self.raw).type_ == ptr::addr_of!(apr_sys::apr_bucket_type_eos) }
+    }
+
+    /// Whether this is a flush bucket
+    pub fn is_flush(&self) -> bool {
+        unsafe { (*self.raw).type_ == ptr::addr_of!(apr_sys::apr_bucket_type_flush) }
+    }
+
+    /// Get a raw pointer to the underlying bucket
+    pub fn as_ptr(&self) -> *mut apr_sys::apr_bucket {
+        self.raw
+    }
+}
+
+/// A bucket brigade: an ordered ring of buckets.
+pub struct Brigade<'pool> {
+    raw: *mut apr_sys::apr_bucket_brigade,
+    alloc: BucketAlloc<'pool>,
+    _phantom: PhantomData<&'pool Pool<'pool>>,
+}
+
+impl<'pool> Brigade<'pool> {
+    /// Create an empty brigade with its own bucket allocator
+    pub fn new(pool: &'pool Pool<'pool>) -> Self {
+        Self::with_alloc(pool, BucketAlloc::new(pool))
+    }
+
+    /// Create an empty brigade using an existing bucket allocator
+    pub fn with_alloc(pool: &'pool Pool<'pool>, alloc: BucketAlloc<'pool>) -> Self {
+        let raw = unsafe { apr_sys::apr_brigade_create(pool.as_mut_ptr(), alloc.raw) };
+        Brigade {
+            raw,
+            alloc,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The bucket allocator used by this brigade
+    pub fn bucket_alloc(&self) -> &BucketAlloc<'pool> {
+        &self.alloc
+    }
+
+    /// Whether the brigade contains no buckets
+    pub fn is_empty(&self) -> bool {
+        unsafe { is_empty(self.raw) }
+    }
+
+    /// Append a bucket to the end of the brigade
+    pub fn push_back(&mut self, bucket: Bucket<'pool>) {
+        unsafe { insert_before(sentinel(self.raw), bucket.into_raw()) }
+    }
+
+    /// Insert a bucket at the start of the brigade
+    pub fn push_front(&mut self, bucket: Bucket<'pool>) {
+        unsafe { insert_before(first(self.raw), bucket.into_raw()) }
+    }
+
+    /// Remove the first bucket from the brigade
+    pub fn pop_front(&mut self) -> Option<Bucket<'pool>> {
+        if self.is_empty() {
+            return None;
+        }
+        unsafe {
+            let e = first(self.raw);
+            bucket_remove(e);
+            Some(Bucket {
+                raw: e,
+                _phantom: PhantomData,
+            })
+        }
+    }
+
+    /// Iterate over the buckets in the brigade
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            brigade: self.raw,
+            cursor: ptr::null_mut(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Total length of the data in the brigade
+    ///
+    /// Buckets of unknown length are read to determine their size.
+    pub fn length(&self) -> Result<usize> {
+        let mut length: apr_sys::apr_off_t = 0;
+        let status = unsafe { apr_sys::apr_brigade_length(self.raw, 1, &mut length) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(Error::from_status(status.into()));
+        }
+        Ok(length as usize)
+    }
+
+    /// Copy all data in the brigade into a contiguous buffer
+    pub fn flatten(&self) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; self.length()?];
+        let mut len: apr_sys::apr_size_t = buf.len();
+        let status = unsafe {
+            apr_sys::apr_brigade_flatten(self.raw, buf.as_mut_ptr() as *mut c_char, &mut len)
+        };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(Error::from_status(status.into()));
+        }
+        buf.truncate(len);
+        Ok(buf)
+    }
+
+    /// Split the brigade before the bucket at `index`
+    ///
+    /// The bucket at `index` and everything after it are moved into the
+    /// returned brigade. If `index` is past the end, the returned brigade is
+    /// empty.
+    pub fn split(&mut self, index: usize) -> Brigade<'pool> {
+        let mut e = unsafe { first(self.raw) };
+        let end = unsafe { sentinel(self.raw) };
+        for _ in 0..index {
+            if e == end {
+                break;
+            }
+            e = unsafe { (*e).link.next };
+        }
+        self.split_at_bucket(e)
+    }
+
+    /// Split the brigade at byte offset `point`
+    ///
+    /// Buckets are split as needed so that the first `point` bytes remain in
+    /// this brigade and the rest are moved into the returned brigade.
+    pub fn partition(&mut self, point: usize) -> Result<Brigade<'pool>> {
+        let mut after: *mut apr_sys::apr_bucket = ptr::null_mut();
+        let status = unsafe {
+            apr_sys::apr_brigade_partition(self.raw, point as apr_sys::apr_off_t, &mut after)
+        };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(Error::from_status(status.into()));
+        }
+        Ok(self.split_at_bucket(after))
+    }
+
+    fn split_at_bucket(&mut self, e: *mut apr_sys::apr_bucket) -> Brigade<'pool> {
+        let raw = unsafe {
+            if e == sentinel(self.raw) {
+                apr_sys::apr_brigade_create((*self.raw).p, self.alloc.raw)
+            } else {
+                apr_sys::apr_brigade_split_ex(self.raw, e, ptr::null_mut())
+            }
+        };
+        Brigade {
+            raw,
+            alloc: self.alloc.clone(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Move all buckets of `other` to the end of this brigade
+    pub fn append(&mut self, other: &mut Brigade<'pool>) {
+        while let Some(bucket) = other.pop_front() {
+            self.push_back(bucket);
+        }
+    }
+
+    /// Destroy all buckets in the brigade
+    pub fn clear(&mut self) {
+        unsafe {
+            apr_sys::apr_brigade_cleanup(self.raw as *mut c_void);
+        }
+    }
+
+    /// Get a raw pointer to the underlying brigade
+    pub fn as_ptr(&self) -> *const apr_sys::apr_bucket_brigade {
+        self.raw
+    }
+
+    /// Get a mutable raw pointer to the underlying brigade
+    pub fn as_mut_ptr(&mut self) -> *mut apr_sys::apr_bucket_brigade {
+        self.raw
+    }
+}
+
+impl<'pool> Drop for Brigade<'pool> {
+    fn drop(&mut self) {
+        unsafe {
+            apr_sys::apr_brigade_destroy(self.raw);
+        }
+    }
+}
+
+impl<'b, 'pool> IntoIterator for &'b Brigade<'pool> {
+    type Item = BucketRef<'b>;
+    type IntoIter = Iter<'b>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the buckets of a brigade
+pub struct Iter<'b> {
+    brigade: *mut apr_sys::apr_bucket_brigade,
+    // The last bucket returned; the next one is looked up lazily because
+    // reading a bucket may insert new buckets after it.
+    cursor: *mut apr_sys::apr_bucket,
+    _phantom: PhantomData<&'b Brigade<'b>>,
+}
+
+impl<'b> Iterator for Iter<'b> {
+    type Item = BucketRef<'b>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = unsafe {
+            if self.cursor.is_null() {
+                first(self.brigade)
+            } else {
+                (*self.cursor).link.next
+            }
+        };
+        if next == unsafe { sentinel(self.brigade) } {
+            return None;
+        }
+        self.cursor = next;
+        Some(BucketRef {
+            raw: next,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl<'pool> Read for Brigade<'pool> {
+    /// Consume data from the front of the brigade.
+    ///
+    /// Metadata buckets are discarded; reading stops at an end-of-stream bucket.
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        unsafe {
+            while !is_empty(self.raw) {
+                let e = first(self.raw);
+                let bucket = BucketRef {
+                    raw: e,
+                    _phantom: PhantomData,
+                };
+                if bucket.is_eos() {
+                    return Ok(0);
+                }
+                if bucket.is_metadata() {
+                    bucket_remove(e);
+                    bucket_destroy(e);
+                    continue;
+                }
+
+                let data = bucket_read(e).map_err(std::io::Error::other)?;
+                if data.is_empty() {
+                    bucket_remove(e);
+                    bucket_destroy(e);
+                    continue;
+                }
+
+                let n = data.len().min(buf.len());
+                buf[..n].copy_from_slice(&data[..n]);
+                if n < data.len() {
+                    bucket_split(e, n).map_err(std::io::Error::other)?;
+                }
+                bucket_remove(e);
+                bucket_destroy(e);
+                return Ok(n);
+            }
+        }
+
+        Ok(0)
+    }
+}
+
+impl<'pool> Write for Brigade<'pool> {
+    /// Append a copy of `buf` to the end of the brigade.
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let status = unsafe {
+            apr_sys::apr_brigade_write(
+                self.raw,
+                None,
+                ptr::null_mut(),
+                buf.as_ptr() as *const c_char,
+                buf.len(),
+            )
+        };
+
+        if status == apr_sys::APR_SUCCESS as i32 {
+            Ok(buf.len())
+        } else {
+            Err(std::io::Error::other(Status::from(status)))
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::OpenFlags;
+    use crate::mmap::MmapFlag;
+
+    #[test]
+    fn test_brigade_push_and_flatten() {
+        let pool = Pool::new();
+        let mut bb = Brigade::new(&pool);
+        assert!(bb.is_empty());
+
+        let alloc = bb.bucket_alloc().clone();
+        bb.push_back(Bucket::heap(b"Hello, ", &alloc).unwrap());
+        bb.push_back(Bucket::immortal(b"world", &alloc).unwrap());
+        bb.push_front(Bucket::transient(b">> ", &alloc).unwrap());
+        bb.push_back(Bucket::eos(&alloc).unwrap());
+
+        assert!(!bb.is_empty());
+        assert_eq!(bb.length().unwrap(), 15);
+        assert_eq!(bb.flatten().unwrap(), b">> Hello, world");
+
+        let names: Vec<_> = bb.iter().map(|b| b.type_name()).collect();
+        assert_eq!(names, vec!["TRANSIENT", "HEAP", "IMMORTAL", "EOS"]);
+        assert!(bb.iter().last().unwrap().is_eos());
+        assert!(bb.iter().last().unwrap().is_metadata());
+    }
+
+    #[test]
+    fn test_brigade_split_and_partition() {
+        let pool = Pool::new();
+        let mut bb = Brigade::new(&pool);
+        let alloc = bb.bucket_alloc().clone();
+        bb.push_back(Bucket::heap(b"abc", &alloc).unwrap());
+        bb.push_back(Bucket::heap(b"def", &alloc).unwrap());
+
+        let mut tail = bb.split(1);
+        assert_eq!(bb.flatten().unwrap(), b"abc");
+        assert_eq!(tail.flatten().unwrap(), b"def");
+
+        let rest = tail.partition(1).unwrap();
+        assert_eq!(tail.flatten().unwrap(), b"d");
+        assert_eq!(rest.flatten().unwrap(), b"ef");
+
+        let empty = bb.split(10);
+        assert!(empty.is_empty());
+
+        bb.append(&mut tail);
+        assert!(tail.is_empty());
+        assert_eq!(bb.flatten().unwrap(), b"abcd");
+    }
+
+    #[test]
+    fn test_brigade_read_write() {
+        let pool = Pool::new();
+        let mut bb = Brigade::new(&pool);
+        let alloc = bb.bucket_alloc().clone();
+
+        bb.write_all(b"first ").unwrap();
+        bb.push_back(Bucket::flush(&alloc).unwrap());
+        bb.write_all(b"second").unwrap();
+        bb.push_back(Bucket::eos(&alloc).unwrap());
+
+        let mut small = [0u8; 4];
+        assert_eq!(bb.read(&mut small).unwrap(), 4);
+        assert_eq!(&small, b"firs");
+
+        let mut rest = String::new();
+        bb.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "t second");
+
+        // The end-of-stream bucket stays in place
+        assert!(bb.iter().next().unwrap().is_eos());
+    }
+
+    #[test]
+    fn test_pool_bucket_and_pop() {
+        let pool = Pool::new();
+        let data = b"pool data";
+        let mut bb = Brigade::new(&pool);
+        let alloc = bb.bucket_alloc().clone();
+        bb.push_back(Bucket::pool(data, &pool, &alloc).unwrap());
+
+        let bucket = bb.pop_front().unwrap();
+        assert!(bb.is_empty());
+        assert_eq!(bucket.as_ref().len(), Some(data.len()));
+        assert_eq!(bucket.as_ref().read().unwrap(), data);
+    }
+
+    #[test]
+    fn test_file_and_mmap_buckets() {
+        let pool = Pool::new();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("buckets.txt");
+        std::fs::write(&path, b"file contents").unwrap();
+
+        let file = File::open(&path, OpenFlags::READ, 0, &pool).unwrap();
+        let mmap =
+            unsafe { Mmap::create(file.as_mut_ptr(), 0, 13, MmapFlag::Read, &pool) }.unwrap();
+
+        let mut bb = Brigade::new(&pool);
+        let alloc = bb.bucket_alloc().clone();
+        bb.push_back(Bucket::file(&file, 5, 8, &pool, &alloc).unwrap());
+        bb.push_back(Bucket::mmap(mmap, 0, 4, &alloc).unwrap());
+        assert_eq!(bb.flatten().unwrap(), b"contentsfile");
+    }
+
+    #[test]
+    fn test_copy_file_into_brigade() {
+        let pool = Pool::new();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("copy.txt");
+        std::fs::write(&path, b"copied through io::copy").unwrap();
+
+        let mut file = File::open(&path, OpenFlags::READ, 0, &pool).unwrap();
+        let mut bb = Brigade::new(&pool);
+        std::io::copy(&mut file, &mut bb).unwrap();
+        assert_eq!(bb.flatten().unwrap(), b"copied through io::copy");
+    }
+}
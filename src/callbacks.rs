@@ -25,6 +25,19 @@ impl<F> CallbackHandle<F> {
     pub fn baton(&self) -> *mut c_void {
         &*self.boxed as *const F as *mut c_void
     }
+
+    /// Get a baton pointer through which the callback may be mutated
+    ///
+    /// Use this rather than [`CallbackHandle::baton`] when the trampoline
+    /// calls an `FnMut`.
+    pub fn baton_mut(&mut self) -> *mut c_void {
+        &mut *self.boxed as *mut F as *mut c_void
+    }
+
+    /// Consume the handle, returning the closure
+    pub fn into_inner(self) -> F {
+        *self.boxed
+    }
 }
 
 // Example of how to create extern "C" trampolines for callbacks:
@@ -1,7 +1,26 @@
-//! Date parsing functions
-use crate::time::Time;
+//! Date parsing and formatting
+//!
+//! APR itself only parses the HTTP date forms; the formatting functions and
+//! the ISO 8601 parser here produce and accept the same [`Time`] values.
+use crate::time::{ExplodedTime, Time};
+use alloc::format;
+use alloc::string::String;
 use apr_sys::apr_date_checkmask;
 
+const DAY_NAMES: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+const MONTH_NAMES: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
 /// Check if the given data matches the mask.
 pub fn checkmask(data: &str, mask: &str) -> bool {
     let (data, mask) = (
@@ -38,6 +57,284 @@ pub fn parse_rfc(data: &str) -> Option<Time> {
     }
 }
 
+fn explode(time: Time, offset: i32) -> ExplodedTime {
+    // apr_time_exp_tz has no failure cases.
+    ExplodedTime::with_offset(time, offset).expect("apr_time_exp_tz failed")
+}
+
+fn day_name(exploded: &ExplodedTime) -> &'static str {
+    DAY_NAMES[exploded.weekday() as usize]
+}
+
+fn month_name(exploded: &ExplodedTime) -> &'static str {
+    MONTH_NAMES[exploded.month() as usize - 1]
+}
+
+/// Format the time as an RFC 1123 date, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+pub fn format_rfc1123(time: Time) -> String {
+    time.rfc822_date()
+}
+
+/// Format the time as an RFC 850 date, e.g. `Sunday, 06-Nov-94 08:49:37 GMT`.
+pub fn format_rfc850(time: Time) -> String {
+    let t = explode(time, 0);
+    format!(
+        "{}, {:02}-{}-{:02} {:02}:{:02}:{:02} GMT",
+        day_name(&t),
+        t.day(),
+        month_name(&t),
+        t.year().rem_euclid(100),
+        t.hour(),
+        t.minute(),
+        t.second()
+    )
+}
+
+/// Format the time in UTC as ANSI C's `asctime()` does, e.g. `Sun Nov  6 08:49:37 1994`.
+pub fn format_asctime(time: Time) -> String {
+    let t = explode(time, 0);
+    format!(
+        "{} {} {:2} {:02}:{:02}:{:02} {}",
+        &day_name(&t)[..3],
+        month_name(&t),
+        t.day(),
+        t.hour(),
+        t.minute(),
+        t.second(),
+        t.year()
+    )
+}
+
+/// Format the time as an ISO 8601 / RFC 3339 timestamp in a timezone
+/// `offset` seconds east of UTC.
+///
+/// Microseconds are included as a six digit fraction when non-zero, and a
+/// zero offset is written as `Z`, e.g. `1994-11-06T08:49:37.000123Z` or
+/// `1994-11-06T10:49:37+02:00`.
+pub fn format_iso8601(time: Time, offset: i32) -> String {
+    let t = explode(time, offset);
+    let mut formatted = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        t.year(),
+        t.month(),
+        t.day(),
+        t.hour(),
+        t.minute(),
+        t.second()
+    );
+    if t.microsecond() != 0 {
+        formatted.push_str(&format!(".{:06}", t.microsecond()));
+    }
+    if offset == 0 {
+        formatted.push('Z');
+    } else {
+        let sign = if offset < 0 { '-' } else { '+' };
+        let offset = offset.unsigned_abs();
+        formatted.push_str(&format!(
+            "{}{:02}:{:02}",
+            sign,
+            offset / 3600,
+            offset % 3600 / 60
+        ));
+    }
+    formatted
+}
+
+fn take_digits(data: &mut &[u8], n: usize) -> Option<u32> {
+    let digits = data.get(..n)?;
+    if !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    *data = &data[n..];
+    Some(
+        digits
+            .iter()
+            .fold(0, |value, digit| value * 10 + (digit - b'0') as u32),
+    )
+}
+
+fn take_byte(data: &mut &[u8], accepted: &[u8]) -> Option<u8> {
+    let (&first, rest) = data.split_first()?;
+    if !accepted.contains(&first) {
+        return None;
+    }
+    *data = rest;
+    Some(first)
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days from 1970-01-01 to the given date in the proleptic Gregorian calendar.
+///
+/// Unlike `apr_time_exp_gmt_get`, this also works for dates before 1970.
+fn days_from_civil(year: u32, month: u32, day: u32) -> i64 {
+    // Count years from March, so the leap day comes last.
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Parse an ISO 8601 / RFC 3339 timestamp, e.g. `1994-11-06T08:49:37.5+02:00`.
+///
+/// The date may be followed by a time, separated by `T` or a space, with
+/// optional seconds and fraction of a second. The timezone is `Z` or an
+/// offset like `+02:00`, `+0200` or `+02`; timestamps without one are taken
+/// to be in UTC. Dates before 1970 give a negative [`Time`].
+pub fn parse_iso8601(data: &str) -> Option<Time> {
+    let mut data = data.as_bytes();
+    let year = take_digits(&mut data, 4)?;
+    take_byte(&mut data, b"-")?;
+    let month = take_digits(&mut data, 2)?;
+    take_byte(&mut data, b"-")?;
+    let day = take_digits(&mut data, 2)?;
+
+    let (mut hour, mut minute, mut second, mut microsecond) = (0, 0, 0, 0);
+    let mut offset = 0;
+    if !data.is_empty() {
+        take_byte(&mut data, b"Tt ")?;
+        hour = take_digits(&mut data, 2)?;
+        take_byte(&mut data, b":")?;
+        minute = take_digits(&mut data, 2)?;
+        if take_byte(&mut data, b":").is_some() {
+            second = take_digits(&mut data, 2)?;
+            if take_byte(&mut data, b".,").is_some() {
+                let len = data.iter().take_while(|b| b.is_ascii_digit()).count();
+                if len == 0 {
+                    return None;
+                }
+                // Anything past microseconds is truncated.
+                let digits = len.min(6);
+                let fraction = take_digits(&mut &data[..digits], digits)?;
+                microsecond = fraction * 10u32.pow(6 - digits as u32);
+                data = &data[len..];
+            }
+        }
+
+        match take_byte(&mut data, b"Zz+-") {
+            None | Some(b'Z' | b'z') => {}
+            Some(sign) => {
+                let hours = take_digits(&mut data, 2)?;
+                take_byte(&mut data, b":");
+                let minutes = if data.is_empty() {
+                    0
+                } else {
+                    take_digits(&mut data, 2)?
+                };
+                if hours > 23 || minutes > 59 {
+                    return None;
+                }
+                offset = (hours * 3600 + minutes * 60) as i32;
+                if sign == b'-' {
+                    offset = -offset;
+                }
+            }
+        }
+        if !data.is_empty() {
+            return None;
+        }
+    }
+
+    if !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    let seconds = days_from_civil(year, month, day) * 86400
+        + i64::from(hour * 3600 + minute * 60 + second)
+        - i64::from(offset);
+    Some(Time::from(seconds * 1_000_000 + i64::from(microsecond)))
+}
+
+/// A textual date format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DateFormat {
+    /// RFC 1123, the preferred HTTP format: `Sun, 06 Nov 1994 08:49:37 GMT`.
+    Rfc1123,
+    /// RFC 850, obsoleted by RFC 1036: `Sunday, 06-Nov-94 08:49:37 GMT`.
+    Rfc850,
+    /// ANSI C's `asctime()` format: `Sun Nov  6 08:49:37 1994`.
+    Asctime,
+    /// ISO 8601 / RFC 3339: `1994-11-06T08:49:37Z`.
+    Iso8601,
+}
+
+impl DateFormat {
+    /// Every format, in the order [`DateFormat::detect`] tries them.
+    pub const ALL: [DateFormat; 4] = [
+        DateFormat::Iso8601,
+        DateFormat::Rfc1123,
+        DateFormat::Rfc850,
+        DateFormat::Asctime,
+    ];
+
+    fn matches(self, data: &str) -> bool {
+        match self {
+            DateFormat::Rfc1123 => data
+                .split_once(", ")
+                .is_some_and(|(_, date)| checkmask(date, "## @$$ #### ##:##:## *")),
+            DateFormat::Rfc850 => data
+                .split_once(", ")
+                .is_some_and(|(_, date)| checkmask(date, "##-@$$-## ##:##:## *")),
+            DateFormat::Asctime => checkmask(data, "@$$ @$$ ~# ##:##:## ####*"),
+            DateFormat::Iso8601 => checkmask(data, "####-##-##*"),
+        }
+    }
+
+    /// Parse `data`, which must be in this format.
+    pub fn parse(self, data: &str) -> Option<Time> {
+        if data.contains('\0') || !self.matches(data) {
+            return None;
+        }
+        match self {
+            DateFormat::Iso8601 => parse_iso8601(data),
+            _ => parse_http(data),
+        }
+    }
+
+    /// Format `time` in this format, in UTC.
+    pub fn format(self, time: Time) -> String {
+        match self {
+            DateFormat::Rfc1123 => format_rfc1123(time),
+            DateFormat::Rfc850 => format_rfc850(time),
+            DateFormat::Asctime => format_asctime(time),
+            DateFormat::Iso8601 => format_iso8601(time, 0),
+        }
+    }
+
+    /// Guess the format `data` is in from its shape.
+    pub fn detect(data: &str) -> Option<Self> {
+        if data.contains('\0') {
+            return None;
+        }
+        Self::ALL.into_iter().find(|format| format.matches(data))
+    }
+}
+
+/// Parse a date in any [`DateFormat`], falling back to the looser forms
+/// accepted by [`parse_rfc`].
+pub fn parse(data: &str) -> Option<Time> {
+    DateFormat::detect(data)
+        .and_then(|format| format.parse(data))
+        .or_else(|| parse_rfc(data))
+}
+
 #[test]
 fn test_parse_http() {
     let expected = Time::from(784111777000000);
@@ -72,3 +369,90 @@ fn test_parse_rfc() {
     assert_eq!(parse_rfc("Sun, 06 Nov 94 8:49:37 GMT"), Some(expected)); // Unknown [Elm 70.85]
     assert_eq!(parse_rfc("Sun, 6 Nov 94 8:49:37 GMT"), Some(expected)); // Unknown [Elm 70.85]
 }
+
+#[test]
+fn test_format() {
+    let time = Time::from(784111777000000);
+    assert_eq!(format_rfc1123(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+    assert_eq!(format_rfc850(time), "Sunday, 06-Nov-94 08:49:37 GMT");
+    assert_eq!(format_asctime(time), "Sun Nov  6 08:49:37 1994");
+    assert_eq!(format_iso8601(time, 0), "1994-11-06T08:49:37Z");
+    assert_eq!(
+        format_iso8601(Time::from(784111777000123), 2 * 3600),
+        "1994-11-06T10:49:37.000123+02:00"
+    );
+    assert_eq!(
+        format_iso8601(time, -(9 * 3600 + 30 * 60)),
+        "1994-11-05T23:19:37-09:30"
+    );
+}
+
+#[test]
+fn test_parse_iso8601() {
+    let expected = Time::from(784111777000000);
+    assert_eq!(parse_iso8601("1994-11-06T08:49:37Z"), Some(expected));
+    assert_eq!(parse_iso8601("1994-11-06t08:49:37z"), Some(expected));
+    assert_eq!(parse_iso8601("1994-11-06 08:49:37"), Some(expected));
+    assert_eq!(parse_iso8601("1994-11-06T10:49:37+02:00"), Some(expected));
+    assert_eq!(parse_iso8601("1994-11-06T03:49:37-0500"), Some(expected));
+    assert_eq!(parse_iso8601("1994-11-06T09:49:37+01"), Some(expected));
+    assert_eq!(
+        parse_iso8601("1994-11-06T08:49:37.123456789Z"),
+        Some(Time::from(784111777123456))
+    );
+    assert_eq!(
+        parse_iso8601("1994-11-06T08:49:37,5Z"),
+        Some(Time::from(784111777500000))
+    );
+    assert_eq!(
+        parse_iso8601("1994-11-06T08:49Z"),
+        Some(Time::from(784111740000000))
+    );
+    assert_eq!(
+        parse_iso8601("1994-11-06"),
+        Some(Time::from(784080000000000))
+    );
+
+    assert_eq!(parse_iso8601("1994-11-06T08:49:37.Z"), None);
+    assert_eq!(parse_iso8601("1994-02-29T08:49:37Z"), None);
+    assert_eq!(parse_iso8601("1994-13-06T08:49:37Z"), None);
+    assert_eq!(parse_iso8601("1994-11-06T24:00:00Z"), None);
+    assert_eq!(parse_iso8601("1994-11-06T08:49:37Z junk"), None);
+    assert_eq!(parse_iso8601("Sun, 06 Nov 1994 08:49:37 GMT"), None);
+}
+
+#[test]
+fn test_parse_iso8601_before_epoch() {
+    let expected = Time::from(-14182940000000);
+    assert_eq!(parse_iso8601("1969-07-20T20:17:40Z"), Some(expected));
+    assert_eq!(parse_iso8601("1969-07-20T22:17:40+02:00"), Some(expected));
+    assert_eq!(format_iso8601(expected, 0), "1969-07-20T20:17:40Z");
+    assert_eq!(
+        parse_iso8601("1900-01-01"),
+        Some(Time::from(-2208988800000000))
+    );
+    assert_eq!(parse_iso8601("1970-01-01"), Some(Time::from(0)));
+
+    let fractional = parse_iso8601("1969-12-31T23:59:59.5Z").unwrap();
+    assert_eq!(fractional, Time::from(-500_000));
+    assert_eq!(format_iso8601(fractional, 0), "1969-12-31T23:59:59.500000Z");
+}
+
+#[test]
+fn test_date_format() {
+    let time = Time::from(784111777000000);
+    for format in DateFormat::ALL {
+        let formatted = format.format(time);
+        assert_eq!(DateFormat::detect(&formatted), Some(format));
+        assert_eq!(format.parse(&formatted), Some(time));
+        assert_eq!(parse(&formatted), Some(time));
+    }
+
+    assert_eq!(
+        DateFormat::Rfc850.parse("Sun, 06 Nov 1994 08:49:37 GMT"),
+        None
+    );
+    assert_eq!(DateFormat::detect("WTAF"), None);
+    assert_eq!(parse("Sun, 6 Nov 94 8:49:37 GMT"), Some(time));
+    assert_eq!(parse("WTAF"), None);
+}
@@ -0,0 +1,174 @@
+//! Dynamic loading of shared objects
+//!
+//! [`Dso::load`] opens a shared library and [`Dso::sym`] looks up symbols in
+//! it. Symbols are returned as [`Symbol`]s that borrow the [`Dso`], so the
+//! library cannot be unloaded while they are still in use.
+
+use crate::{pool::Pool, Result};
+use alloc::ffi::CString;
+use alloc::string::String;
+use core::ffi::{c_char, CStr};
+use core::marker::PhantomData;
+use core::ptr;
+use std::path::Path;
+
+/// Loaded shared object
+pub struct Dso<'pool> {
+    raw: *mut apr_sys::apr_dso_handle_t,
+    _phantom: PhantomData<&'pool Pool<'pool>>,
+}
+
+/// Read APR's description of the last error on `handle`.
+fn error_string(handle: *mut apr_sys::apr_dso_handle_t) -> String {
+    let mut buf = [0 as c_char; 256];
+    unsafe {
+        let msg = apr_sys::apr_dso_error(handle, buf.as_mut_ptr(), buf.len());
+        if msg.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(msg).to_string_lossy().into_owned()
+        }
+    }
+}
+
+impl<'pool> Dso<'pool> {
+    /// Load the shared object at `path`
+    ///
+    /// On failure the error carries the loader's message, e.g. from `dlerror()`.
+    pub fn load<P: AsRef<Path>>(path: P, pool: &'pool Pool<'pool>) -> Result<Self> {
+        let c_path = CString::new(path.as_ref().to_string_lossy().as_ref())
+            .map_err(|_| crate::Error::from_status((apr_sys::APR_EINVAL as i32).into()))?;
+        let mut handle: *mut apr_sys::apr_dso_handle_t = ptr::null_mut();
+
+        let status =
+            unsafe { apr_sys::apr_dso_load(&mut handle, c_path.as_ptr(), pool.as_mut_ptr()) };
+
+        if status != apr_sys::APR_SUCCESS as i32 {
+            let err = crate::Error::from_status(status.into());
+            // APR hands back a handle even on failure so the message can be read.
+            return Err(if handle.is_null() {
+                err
+            } else {
+                err.context(error_string(handle))
+            });
+        }
+
+        Ok(Dso {
+            raw: handle,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Look up the symbol `name`
+    ///
+    /// # Safety
+    ///
+    /// `T` must be a pointer type (typically an `extern "C" fn`) that
+    /// matches the actual type of the symbol.
+    pub unsafe fn sym<T: Copy>(&self, name: &str) -> Result<Symbol<'_, T>> {
+        assert_eq!(
+            core::mem::size_of::<T>(),
+            core::mem::size_of::<apr_sys::apr_dso_handle_sym_t>(),
+            "symbol type must be pointer-sized"
+        );
+        let c_name = CString::new(name)
+            .map_err(|_| crate::Error::from_status((apr_sys::APR_EINVAL as i32).into()))?;
+        let mut sym: apr_sys::apr_dso_handle_sym_t = ptr::null_mut();
+
+        let status = apr_sys::apr_dso_sym(&mut sym, self.raw, c_name.as_ptr());
+
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()).context(self.dso_error()));
+        }
+
+        Ok(Symbol {
+            value: core::mem::transmute_copy(&sym),
+            _phantom: PhantomData,
+        })
+    }
+
+    /// The loader's description of the most recent error on this object
+    pub fn dso_error(&self) -> String {
+        error_string(self.raw)
+    }
+
+    /// Unload the shared object, reporting any error
+    ///
+    /// This happens automatically on drop.
+    pub fn unload(mut self) -> Result<()> {
+        let raw = core::mem::replace(&mut self.raw, ptr::null_mut());
+        let status = unsafe { apr_sys::apr_dso_unload(raw) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+        Ok(())
+    }
+
+    /// Raw pointer to the underlying APR handle
+    pub fn as_ptr(&self) -> *const apr_sys::apr_dso_handle_t {
+        self.raw
+    }
+
+    /// Mutable raw pointer to the underlying APR handle
+    pub fn as_mut_ptr(&mut self) -> *mut apr_sys::apr_dso_handle_t {
+        self.raw
+    }
+}
+
+impl<'pool> Drop for Dso<'pool> {
+    fn drop(&mut self) {
+        if !self.raw.is_null() {
+            unsafe {
+                apr_sys::apr_dso_unload(self.raw);
+            }
+        }
+    }
+}
+
+/// Symbol looked up in a [`Dso`], valid while the object stays loaded
+pub struct Symbol<'dso, T> {
+    value: T,
+    _phantom: PhantomData<&'dso Dso<'dso>>,
+}
+
+impl<'dso, T> core::ops::Deref for Symbol<'dso, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_missing() {
+        let pool = Pool::new();
+        let err = Dso::load("/nonexistent/libapr-rs-missing.so", &pool)
+            .err()
+            .unwrap();
+        // The loader's message, e.g. from dlerror(), names the missing file.
+        #[cfg(target_os = "linux")]
+        assert!(err.to_string().contains("libapr-rs-missing.so"), "{}", err);
+        #[cfg(not(target_os = "linux"))]
+        assert_ne!(err.to_string(), err.status().to_string());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_load_and_call() {
+        let pool = Pool::new();
+        let libm = Dso::load("libm.so.6", &pool).unwrap();
+
+        let cos = unsafe { libm.sym::<extern "C" fn(f64) -> f64>("cos") }.unwrap();
+        assert_eq!(cos(0.0), 1.0);
+
+        let err = unsafe { libm.sym::<extern "C" fn()>("no_such_symbol") }
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("no_such_symbol"), "{}", err);
+        libm.unload().unwrap();
+    }
+}
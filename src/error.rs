@@ -72,7 +72,7 @@ impl From<Status> for Error {
 #[cfg(feature = "std")]
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
-        Error::from_status(Status::General).with_source(err)
+        Error::from_status(Status::from(&err)).with_source(err)
     }
 }
 
@@ -0,0 +1,369 @@
+//! Escaping and unescaping for URLs, HTML, shells and LDAP
+//!
+//! These wrap APR's `apr_escape.h`. Each function returns the input unchanged
+//! (and without allocating) when there is nothing to escape. As in C, input
+//! is only processed up to the first NUL byte.
+//!
+//! ```no_run
+//! use apr::escape::{escape_urlencoded, unescape_urlencoded};
+//!
+//! assert_eq!(escape_urlencoded("a b&c"), "a+b%26c");
+//! assert_eq!(unescape_urlencoded("a+b%26c").unwrap(), "a b&c");
+//! ```
+
+use crate::pool::Pool;
+use crate::status::Status;
+use crate::tables::StringTable;
+use crate::{Error, Result};
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ffi::{c_char, c_int, c_void};
+
+/// Which characters [`escape_ldap`] escapes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LdapEscape {
+    /// Characters special in a distinguished name
+    Dn,
+    /// Characters special in a search filter
+    Filter,
+    /// Characters special in either
+    All,
+}
+
+impl From<LdapEscape> for c_int {
+    fn from(mode: LdapEscape) -> c_int {
+        (match mode {
+            LdapEscape::Dn => apr_sys::APR_ESCAPE_LDAP_DN,
+            LdapEscape::Filter => apr_sys::APR_ESCAPE_LDAP_FILTER,
+            LdapEscape::All => apr_sys::APR_ESCAPE_LDAP_ALL,
+        }) as c_int
+    }
+}
+
+/// The part of `s` APR will look at.
+fn until_nul(s: &str) -> &str {
+    s.find('\0').map_or(s, |end| &s[..end])
+}
+
+/// Run an `apr_escape_*` style function twice: once to measure the output
+/// and once to fill it.
+///
+/// Returns `None` if the input needs no changes, otherwise the output and
+/// the length APR reported for it.
+fn transform<F>(f: F) -> Result<Option<(Vec<u8>, usize)>>
+where
+    F: Fn(*mut c_char, *mut apr_sys::apr_size_t) -> apr_sys::apr_status_t,
+{
+    let mut len: apr_sys::apr_size_t = 0;
+    let status = f(core::ptr::null_mut(), &mut len);
+    if status == apr_sys::APR_NOTFOUND as i32 {
+        return Ok(None);
+    }
+    if status != apr_sys::APR_SUCCESS as i32 {
+        return Err(Error::from_status(status.into()));
+    }
+
+    let mut buf = vec![0u8; len];
+    let status = f(buf.as_mut_ptr() as *mut c_char, &mut len);
+    if status != apr_sys::APR_SUCCESS as i32 {
+        return Err(Error::from_status(status.into()));
+    }
+    Ok(Some((buf, len)))
+}
+
+/// Run a string-producing `apr_escape_*` function on `s`.
+fn transform_str<'a, F>(s: &'a str, f: F) -> Result<Cow<'a, str>>
+where
+    F: Fn(
+        *mut c_char,
+        *const c_char,
+        apr_sys::apr_ssize_t,
+        *mut apr_sys::apr_size_t,
+    ) -> apr_sys::apr_status_t,
+{
+    let s = until_nul(s);
+    let result = transform(|dest, len| {
+        f(
+            dest,
+            s.as_ptr() as *const c_char,
+            s.len() as apr_sys::apr_ssize_t,
+            len,
+        )
+    })?;
+    match result {
+        None => Ok(Cow::Borrowed(s)),
+        Some((mut buf, len)) => {
+            // The reported length includes the NUL terminator.
+            buf.truncate(len.saturating_sub(1));
+            String::from_utf8(buf)
+                .map(Cow::Owned)
+                .map_err(|_| Error::from_status(Status::BadCh).context("result is not valid UTF-8"))
+        }
+    }
+}
+
+/// Escaping only ever replaces whole ASCII characters or every high byte,
+/// so it cannot fail or produce invalid UTF-8.
+fn escape_str<'a, F>(s: &'a str, f: F) -> Cow<'a, str>
+where
+    F: Fn(
+        *mut c_char,
+        *const c_char,
+        apr_sys::apr_ssize_t,
+        *mut apr_sys::apr_size_t,
+    ) -> apr_sys::apr_status_t,
+{
+    transform_str(s, f).expect("escaping failed")
+}
+
+/// Percent-encode `s` for use as a single path segment, escaping `/` too
+pub fn escape_path_segment(s: &str) -> Cow<'_, str> {
+    escape_str(s, |dest, src, slen, len| unsafe {
+        apr_sys::apr_escape_path_segment(dest, src, slen, len)
+    })
+}
+
+/// Percent-encode `path` for use as the path of a URL
+///
+/// Unless `partial` is set, `./` is prepended if the first segment contains a
+/// `:`, so that it can't be mistaken for a scheme.
+pub fn escape_path(path: &str, partial: bool) -> Cow<'_, str> {
+    escape_str(path, |dest, src, slen, len| unsafe {
+        apr_sys::apr_escape_path(dest, src, slen, partial as c_int, len)
+    })
+}
+
+/// Encode `s` as `application/x-www-form-urlencoded`, turning spaces into `+`
+pub fn escape_urlencoded(s: &str) -> Cow<'_, str> {
+    escape_str(s, |dest, src, slen, len| unsafe {
+        apr_sys::apr_escape_urlencoded(dest, src, slen, len)
+    })
+}
+
+/// Decode `application/x-www-form-urlencoded` data, turning `+` into spaces
+///
+/// Fails with an `EINVAL` status (see [`Status::is_einval`]) on a malformed
+/// `%` escape, or [`Status::BadCh`] if the decoded data is not valid UTF-8.
+pub fn unescape_urlencoded(s: &str) -> Result<Cow<'_, str>> {
+    transform_str(s, |dest, src, slen, len| unsafe {
+        apr_sys::apr_unescape_url(
+            dest,
+            src,
+            slen,
+            core::ptr::null(),
+            core::ptr::null(),
+            1,
+            len,
+        )
+    })
+}
+
+/// Escape `&`, `<`, `>` and `"` as HTML/XML entities
+///
+/// With `to_ascii`, non-ASCII characters are escaped as numeric entities too.
+pub fn escape_entity(s: &str, to_ascii: bool) -> Cow<'_, str> {
+    escape_str(s, |dest, src, slen, len| unsafe {
+        apr_sys::apr_escape_entity(dest, src, slen, to_ascii as c_int, len)
+    })
+}
+
+/// Replace HTML/XML entities with the characters they stand for
+///
+/// Fails with [`Status::BadCh`] if the result is not valid UTF-8, as happens
+/// for numeric entities between 128 and 255.
+pub fn unescape_entity(s: &str) -> Result<Cow<'_, str>> {
+    transform_str(s, |dest, src, slen, len| unsafe {
+        apr_sys::apr_unescape_entity(dest, src, slen, len)
+    })
+}
+
+/// Escape control characters for echoing to a terminal or log
+///
+/// With `quote`, `"` is escaped as well.
+pub fn escape_echo(s: &str, quote: bool) -> Cow<'_, str> {
+    escape_str(s, |dest, src, slen, len| unsafe {
+        apr_sys::apr_escape_echo(dest, src, slen, quote as c_int, len)
+    })
+}
+
+/// Encode `data` as lowercase hex, optionally separating bytes with `:`
+///
+/// Unlike the other escaping functions this always allocates, since the
+/// input is binary.
+pub fn escape_hex(data: &[u8], colon: bool) -> String {
+    if data.is_empty() {
+        return String::new();
+    }
+    let result = transform(|dest, len| unsafe {
+        apr_sys::apr_escape_hex(
+            dest,
+            data.as_ptr() as *const c_void,
+            data.len(),
+            colon as c_int,
+            len,
+        )
+    })
+    .expect("hex encoding failed");
+    let (mut buf, len) = result.expect("hex encoding of non-empty input");
+    buf.truncate(len.saturating_sub(1));
+    String::from_utf8(buf).expect("hex output is ASCII")
+}
+
+/// Decode hex produced by [`escape_hex`]
+///
+/// Fails with [`Status::BadCh`] on anything other than hex digits, and `:`
+/// separators when `colon` is set.
+pub fn unescape_hex(s: &str, colon: bool) -> Result<Vec<u8>> {
+    let s = until_nul(s);
+    if s.is_empty() {
+        return Ok(Vec::new());
+    }
+    let result = transform(|dest, len| unsafe {
+        apr_sys::apr_unescape_hex(
+            dest as *mut c_void,
+            s.as_ptr() as *const c_char,
+            s.len() as apr_sys::apr_ssize_t,
+            colon as c_int,
+            len,
+        )
+    })?;
+    Ok(result.map_or_else(Vec::new, |(mut buf, len)| {
+        buf.truncate(len);
+        buf
+    }))
+}
+
+/// Escape the characters of `s` that are special in LDAP, as selected by `mode`
+pub fn escape_ldap(s: &str, mode: LdapEscape) -> Cow<'_, str> {
+    escape_str(s, |dest, src, slen, len| unsafe {
+        apr_sys::apr_escape_ldap(dest, src as *const c_void, slen, mode.into(), len)
+    })
+}
+
+/// Backslash-escape the characters of `s` that are special to a shell
+pub fn escape_shell(s: &str) -> Cow<'_, str> {
+    escape_str(s, |dest, src, slen, len| unsafe {
+        apr_sys::apr_escape_shell(dest, src, slen, len)
+    })
+}
+
+/// Parse a URL query string such as `a=1&b=two+words` into a table
+///
+/// A leading `?` is ignored. Keys and values are decoded with
+/// [`unescape_urlencoded`]; keys without `=` get an empty value. Repeated
+/// keys are all kept, in order.
+pub fn parse_query<'pool>(query: &str, pool: &'pool Pool) -> Result<StringTable<'pool>> {
+    let query = query.strip_prefix('?').unwrap_or(query);
+    let mut table = StringTable::new(pool, 8);
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let key = unescape_urlencoded(key)?;
+        let value = unescape_urlencoded(value)?;
+        if key.contains('\0') || value.contains('\0') {
+            return Err(Error::from_status(Status::BadCh).context("query contains a NUL byte"));
+        }
+        table.add(&key, &value);
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_path() {
+        assert!(matches!(
+            escape_path_segment("plain"),
+            Cow::Borrowed("plain")
+        ));
+        assert_eq!(escape_path_segment("a b/c"), "a%20b%2fc");
+        assert_eq!(escape_path("/a b/c", false), "/a%20b/c");
+        assert_eq!(escape_path("a:b", false), "./a:b");
+        assert_eq!(escape_path("a:b", true), "a:b");
+    }
+
+    #[test]
+    fn test_urlencoded() {
+        assert_eq!(escape_urlencoded("a b&c=d"), "a+b%26c%3dd");
+        assert!(matches!(escape_urlencoded("abc"), Cow::Borrowed("abc")));
+        assert_eq!(unescape_urlencoded("a+b%26c%3Dd").unwrap(), "a b&c=d");
+        assert_eq!(unescape_urlencoded("caf%C3%A9").unwrap(), "café");
+        assert!(matches!(
+            unescape_urlencoded("abc").unwrap(),
+            Cow::Borrowed("abc")
+        ));
+        assert!(unescape_urlencoded("bad%zz").is_err());
+        assert!(unescape_urlencoded("%ff").is_err());
+    }
+
+    #[test]
+    fn test_entity() {
+        assert_eq!(
+            escape_entity("<a href=\"x\">&</a>", false),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+        assert_eq!(escape_entity("café", false), "café");
+        assert_eq!(escape_entity("café", true), "caf&#195;&#169;");
+        assert_eq!(unescape_entity("&lt;b&gt; &amp; &#65;").unwrap(), "<b> & A");
+        assert!(matches!(
+            unescape_entity("plain").unwrap(),
+            Cow::Borrowed("plain")
+        ));
+    }
+
+    #[test]
+    fn test_echo_shell_ldap() {
+        assert_eq!(escape_echo("a\tb\"c", false), "a\\tb\"c");
+        assert_eq!(escape_echo("a\tb\"c", true), "a\\tb\\\"c");
+        assert_eq!(escape_shell("rm -rf $HOME; ls"), "rm -rf \\$HOME\\; ls");
+        assert_eq!(escape_ldap("a*(b)", LdapEscape::Filter), "a\\2a\\28b\\29");
+        assert_eq!(escape_ldap("a,b", LdapEscape::Dn), "a\\2cb");
+        assert!(matches!(
+            escape_ldap("a,b", LdapEscape::Filter),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(escape_hex(&[0xde, 0xad, 0x01], false), "dead01");
+        assert_eq!(escape_hex(&[0xde, 0xad, 0x01], true), "de:ad:01");
+        assert_eq!(escape_hex(&[], true), "");
+        assert_eq!(
+            unescape_hex("dead01", false).unwrap(),
+            vec![0xde, 0xad, 0x01]
+        );
+        assert_eq!(
+            unescape_hex("DE:AD:01", true).unwrap(),
+            vec![0xde, 0xad, 0x01]
+        );
+        assert!(unescape_hex("xyz", false).is_err());
+    }
+
+    #[test]
+    fn test_nul_terminates_input() {
+        assert_eq!(escape_urlencoded("a\0b c"), "a");
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let pool = Pool::new();
+        let table = parse_query("?q=two+words&lang=en&flag&lang=fr&&x=%3D", &pool).unwrap();
+        let entries: Vec<_> = table.iter().collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("q", "two words"),
+                ("lang", "en"),
+                ("flag", ""),
+                ("lang", "fr"),
+                ("x", "=")
+            ]
+        );
+        assert!(parse_query("a=%00", &pool).is_err());
+        assert!(parse_query("a=%zz", &pool).is_err());
+    }
+}
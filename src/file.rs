@@ -1,8 +1,8 @@
 //! File handling
-use crate::{pool::Pool, status::Status};
+use crate::{pool::Pool, status::Status, time::Time};
 use apr_sys;
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 pub use apr_sys::apr_file_t;
 
@@ -162,6 +162,19 @@ impl File {
         }
     }
 
+    /// Wrap a raw APR file, taking ownership of it
+    ///
+    /// # Safety
+    ///
+    /// `raw` must be a valid, open file that is not closed elsewhere; it will
+    /// be closed when the returned `File` is dropped.
+    pub unsafe fn from_raw(raw: *mut apr_sys::apr_file_t) -> Self {
+        File {
+            raw,
+            _no_send: std::marker::PhantomData,
+        }
+    }
+
     /// Get the raw file pointer
     pub fn as_ptr(&self) -> *const apr_sys::apr_file_t {
         self.raw
@@ -350,6 +363,578 @@ impl File {
     }
 }
 
+/// Fields of a [`FileInfo`] to retrieve
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InfoWanted(i32);
+
+impl InfoWanted {
+    /// Stat the link rather than the file it points to
+    pub const LINK: InfoWanted = InfoWanted(apr_sys::APR_FINFO_LINK as i32);
+    /// Modification time
+    pub const MTIME: InfoWanted = InfoWanted(apr_sys::APR_FINFO_MTIME as i32);
+    /// Creation or inode-changed time
+    pub const CTIME: InfoWanted = InfoWanted(apr_sys::APR_FINFO_CTIME as i32);
+    /// Access time
+    pub const ATIME: InfoWanted = InfoWanted(apr_sys::APR_FINFO_ATIME as i32);
+    /// Size of the file
+    pub const SIZE: InfoWanted = InfoWanted(apr_sys::APR_FINFO_SIZE as i32);
+    /// Storage size consumed by the file
+    pub const CSIZE: InfoWanted = InfoWanted(apr_sys::APR_FINFO_CSIZE as i32);
+    /// Device
+    pub const DEV: InfoWanted = InfoWanted(apr_sys::APR_FINFO_DEV as i32);
+    /// Inode
+    pub const INODE: InfoWanted = InfoWanted(apr_sys::APR_FINFO_INODE as i32);
+    /// Number of hard links
+    pub const NLINK: InfoWanted = InfoWanted(apr_sys::APR_FINFO_NLINK as i32);
+    /// File type
+    pub const TYPE: InfoWanted = InfoWanted(apr_sys::APR_FINFO_TYPE as i32);
+    /// User id of the owner
+    pub const USER: InfoWanted = InfoWanted(apr_sys::APR_FINFO_USER as i32);
+    /// Group id of the owner
+    pub const GROUP: InfoWanted = InfoWanted(apr_sys::APR_FINFO_GROUP as i32);
+    /// User protection bits
+    pub const UPROT: InfoWanted = InfoWanted(apr_sys::APR_FINFO_UPROT as i32);
+    /// Group protection bits
+    pub const GPROT: InfoWanted = InfoWanted(apr_sys::APR_FINFO_GPROT as i32);
+    /// World protection bits
+    pub const WPROT: InfoWanted = InfoWanted(apr_sys::APR_FINFO_WPROT as i32);
+    /// Whether the file system is case insensitive
+    pub const ICASE: InfoWanted = InfoWanted(apr_sys::APR_FINFO_ICASE as i32);
+    /// Name of the file in its proper case
+    pub const NAME: InfoWanted = InfoWanted(apr_sys::APR_FINFO_NAME as i32);
+    /// Type, size and times
+    pub const MIN: InfoWanted = InfoWanted(apr_sys::APR_FINFO_MIN as i32);
+    /// Device and inode
+    pub const IDENT: InfoWanted = InfoWanted(apr_sys::APR_FINFO_IDENT as i32);
+    /// User and group
+    pub const OWNER: InfoWanted = InfoWanted(apr_sys::APR_FINFO_OWNER as i32);
+    /// All protection bits
+    pub const PROT: InfoWanted = InfoWanted(apr_sys::APR_FINFO_PROT as i32);
+    /// All fields that are cheap to retrieve on this platform
+    pub const NORM: InfoWanted = InfoWanted(apr_sys::APR_FINFO_NORM as i32);
+    /// Fields available from a directory entry without an extra stat
+    pub const DIRENT: InfoWanted = InfoWanted(apr_sys::APR_FINFO_DIRENT as i32);
+
+    /// Combine multiple flags
+    pub fn combine(flags: &[InfoWanted]) -> Self {
+        let combined = flags.iter().fold(0, |acc, flag| acc | flag.0);
+        InfoWanted(combined)
+    }
+
+    /// Whether all fields in `other` are included
+    pub fn contains(&self, other: InfoWanted) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for InfoWanted {
+    type Output = InfoWanted;
+
+    fn bitor(self, rhs: InfoWanted) -> InfoWanted {
+        InfoWanted(self.0 | rhs.0)
+    }
+}
+
+/// Type of a file system entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    /// No file type determined
+    NoFile,
+    /// Regular file
+    Regular,
+    /// Directory
+    Directory,
+    /// Character device
+    CharDevice,
+    /// Block device
+    BlockDevice,
+    /// FIFO / pipe
+    Pipe,
+    /// Symbolic link
+    Symlink,
+    /// Socket
+    Socket,
+    /// File of some other, unknown type
+    Unknown,
+}
+
+impl From<apr_sys::apr_filetype_e> for FileType {
+    fn from(filetype: apr_sys::apr_filetype_e) -> Self {
+        match filetype {
+            apr_sys::apr_filetype_e_APR_NOFILE => FileType::NoFile,
+            apr_sys::apr_filetype_e_APR_REG => FileType::Regular,
+            apr_sys::apr_filetype_e_APR_DIR => FileType::Directory,
+            apr_sys::apr_filetype_e_APR_CHR => FileType::CharDevice,
+            apr_sys::apr_filetype_e_APR_BLK => FileType::BlockDevice,
+            apr_sys::apr_filetype_e_APR_PIPE => FileType::Pipe,
+            apr_sys::apr_filetype_e_APR_LNK => FileType::Symlink,
+            apr_sys::apr_filetype_e_APR_SOCK => FileType::Socket,
+            _ => FileType::Unknown,
+        }
+    }
+}
+
+/// Information about a file, as returned by [`stat`], [`File::info`] or [`Dir`]
+///
+/// Only the fields that were requested (and that the platform could provide)
+/// are set; the accessors return `None` for the others.
+#[derive(Debug, Clone)]
+pub struct FileInfo {
+    valid: i32,
+    filetype: apr_sys::apr_filetype_e,
+    protection: FilePerms,
+    user: apr_sys::apr_uid_t,
+    group: apr_sys::apr_gid_t,
+    inode: apr_sys::apr_ino_t,
+    device: apr_sys::apr_dev_t,
+    nlink: i32,
+    size: apr_sys::apr_off_t,
+    csize: apr_sys::apr_off_t,
+    atime: apr_sys::apr_time_t,
+    mtime: apr_sys::apr_time_t,
+    ctime: apr_sys::apr_time_t,
+    path: Option<PathBuf>,
+    name: Option<String>,
+}
+
+impl FileInfo {
+    /// Copy the information out of a raw `apr_finfo_t`
+    ///
+    /// # Safety
+    ///
+    /// The string fields of `finfo` must be null or valid C strings.
+    pub unsafe fn from_raw(finfo: &apr_sys::apr_finfo_t) -> Self {
+        let path = if finfo.fname.is_null() {
+            None
+        } else {
+            Some(crate::paths::cstring_to_pathbuf(finfo.fname))
+        };
+        let name = if finfo.name.is_null() {
+            None
+        } else {
+            Some(
+                std::ffi::CStr::from_ptr(finfo.name)
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        };
+        FileInfo {
+            valid: finfo.valid,
+            filetype: finfo.filetype,
+            protection: finfo.protection,
+            user: finfo.user,
+            group: finfo.group,
+            inode: finfo.inode,
+            device: finfo.device,
+            nlink: finfo.nlink,
+            size: finfo.size,
+            csize: finfo.csize,
+            atime: finfo.atime,
+            mtime: finfo.mtime,
+            ctime: finfo.ctime,
+            path,
+            name,
+        }
+    }
+
+    fn has(&self, field: InfoWanted) -> bool {
+        self.valid & field.0 == field.0
+    }
+
+    /// Fields that are valid in this structure
+    pub fn valid(&self) -> InfoWanted {
+        InfoWanted(self.valid)
+    }
+
+    /// Type of the file
+    pub fn file_type(&self) -> Option<FileType> {
+        self.has(InfoWanted::TYPE)
+            .then(|| FileType::from(self.filetype))
+    }
+
+    /// Whether this is a directory
+    pub fn is_dir(&self) -> bool {
+        self.file_type() == Some(FileType::Directory)
+    }
+
+    /// Whether this is a regular file
+    pub fn is_file(&self) -> bool {
+        self.file_type() == Some(FileType::Regular)
+    }
+
+    /// Whether this is a symbolic link (only reported when [`InfoWanted::LINK`] was requested)
+    pub fn is_symlink(&self) -> bool {
+        self.file_type() == Some(FileType::Symlink)
+    }
+
+    /// Access permissions, in APR's `APR_FPROT_*` representation
+    pub fn permissions(&self) -> Option<FilePerms> {
+        (self.valid & InfoWanted::PROT.0 != 0).then_some(self.protection)
+    }
+
+    /// User id of the owner
+    pub fn user(&self) -> Option<apr_sys::apr_uid_t> {
+        self.has(InfoWanted::USER).then_some(self.user)
+    }
+
+    /// Group id of the owner
+    pub fn group(&self) -> Option<apr_sys::apr_gid_t> {
+        self.has(InfoWanted::GROUP).then_some(self.group)
+    }
+
+    /// Inode number
+    pub fn inode(&self) -> Option<apr_sys::apr_ino_t> {
+        self.has(InfoWanted::INODE).then_some(self.inode)
+    }
+
+    /// Device the file resides on
+    pub fn device(&self) -> Option<apr_sys::apr_dev_t> {
+        self.has(InfoWanted::DEV).then_some(self.device)
+    }
+
+    /// Number of hard links to the file
+    pub fn nlink(&self) -> Option<u32> {
+        self.has(InfoWanted::NLINK).then_some(self.nlink as u32)
+    }
+
+    /// Size of the file in bytes
+    pub fn size(&self) -> Option<u64> {
+        self.has(InfoWanted::SIZE).then_some(self.size as u64)
+    }
+
+    /// Storage size consumed by the file
+    pub fn storage_size(&self) -> Option<u64> {
+        self.has(InfoWanted::CSIZE).then_some(self.csize as u64)
+    }
+
+    /// Time the file was last accessed
+    pub fn atime(&self) -> Option<Time> {
+        self.has(InfoWanted::ATIME).then(|| Time::from(self.atime))
+    }
+
+    /// Time the file was last modified
+    pub fn mtime(&self) -> Option<Time> {
+        self.has(InfoWanted::MTIME).then(|| Time::from(self.mtime))
+    }
+
+    /// Time the file was created or its inode last changed
+    pub fn ctime(&self) -> Option<Time> {
+        self.has(InfoWanted::CTIME).then(|| Time::from(self.ctime))
+    }
+
+    /// Path of the file, if known
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Base name of the file, if requested with [`InfoWanted::NAME`]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+/// Retrieve information about the file at `path`
+///
+/// Only the fields selected by `wanted` are guaranteed to be filled in; APR may
+/// return more, and on some platforms fewer.
+pub fn stat<P: AsRef<Path>>(
+    path: P,
+    wanted: InfoWanted,
+    pool: &Pool<'_>,
+) -> Result<FileInfo, Status> {
+    let path_str = path.as_ref().to_string_lossy();
+    let path_cstr = alloc::ffi::CString::new(path_str.as_ref())
+        .map_err(|_| Status::from(apr_sys::APR_EINVAL as i32))?;
+
+    let mut finfo = std::mem::MaybeUninit::<apr_sys::apr_finfo_t>::zeroed();
+    let status = unsafe {
+        apr_sys::apr_stat(
+            finfo.as_mut_ptr(),
+            path_cstr.as_ptr(),
+            wanted.0,
+            pool.as_mut_ptr(),
+        )
+    };
+
+    // APR_INCOMPLETE means some of the wanted fields are unavailable; the
+    // valid mask records which ones were filled in.
+    if status == apr_sys::APR_SUCCESS as i32 || status == apr_sys::APR_INCOMPLETE as i32 {
+        Ok(unsafe { FileInfo::from_raw(finfo.assume_init_ref()) })
+    } else {
+        Err(Status::from(status))
+    }
+}
+
+impl File {
+    /// Retrieve information about this open file
+    pub fn info(&self, wanted: InfoWanted) -> Result<FileInfo, Status> {
+        let mut finfo = std::mem::MaybeUninit::<apr_sys::apr_finfo_t>::zeroed();
+        let status = unsafe { apr_sys::apr_file_info_get(finfo.as_mut_ptr(), wanted.0, self.raw) };
+
+        if status == apr_sys::APR_SUCCESS as i32 || status == apr_sys::APR_INCOMPLETE as i32 {
+            Ok(unsafe { FileInfo::from_raw(finfo.assume_init_ref()) })
+        } else {
+            Err(Status::from(status))
+        }
+    }
+}
+
+/// An open directory, yielding a [`FileInfo`] for each entry
+///
+/// The `.` and `..` entries are skipped. The path of each entry is the
+/// directory path joined with the entry name.
+pub struct Dir<'pool> {
+    raw: *mut apr_sys::apr_dir_t,
+    path: PathBuf,
+    wanted: InfoWanted,
+    _phantom: std::marker::PhantomData<&'pool Pool<'pool>>,
+}
+
+impl<'pool> Dir<'pool> {
+    /// Open a directory for reading
+    pub fn open<P: AsRef<Path>>(path: P, pool: &'pool Pool<'pool>) -> Result<Self, Status> {
+        Self::open_in(path.as_ref(), pool.as_mut_ptr())
+    }
+
+    /// Open a directory in a raw pool that must outlive the `Dir`
+    fn open_in(path: &Path, pool: *mut apr_sys::apr_pool_t) -> Result<Self, Status> {
+        let path_str = path.to_string_lossy();
+        let path_cstr = alloc::ffi::CString::new(path_str.as_ref())
+            .map_err(|_| Status::from(apr_sys::APR_EINVAL as i32))?;
+
+        let mut dir_ptr: *mut apr_sys::apr_dir_t = std::ptr::null_mut();
+        let status = unsafe { apr_sys::apr_dir_open(&mut dir_ptr, path_cstr.as_ptr(), pool) };
+
+        if status == apr_sys::APR_SUCCESS as i32 {
+            Ok(Dir {
+                raw: dir_ptr,
+                path: path.to_path_buf(),
+                wanted: InfoWanted::DIRENT | InfoWanted::TYPE,
+                _phantom: std::marker::PhantomData,
+            })
+        } else {
+            Err(Status::from(status))
+        }
+    }
+
+    /// Set the fields retrieved for each entry (defaults to name and type)
+    pub fn wanted(mut self, wanted: InfoWanted) -> Self {
+        self.wanted = wanted;
+        self
+    }
+
+    /// Path of the directory
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Restart reading from the first entry
+    pub fn rewind(&mut self) -> Result<(), Status> {
+        let status = unsafe { apr_sys::apr_dir_rewind(self.raw) };
+
+        if status == apr_sys::APR_SUCCESS as i32 {
+            Ok(())
+        } else {
+            Err(Status::from(status))
+        }
+    }
+
+    /// Get the raw directory pointer
+    pub fn as_ptr(&self) -> *const apr_sys::apr_dir_t {
+        self.raw
+    }
+
+    /// Get the mutable raw directory pointer
+    pub fn as_mut_ptr(&mut self) -> *mut apr_sys::apr_dir_t {
+        self.raw
+    }
+}
+
+impl<'pool> Iterator for Dir<'pool> {
+    type Item = Result<FileInfo, Status>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut finfo = std::mem::MaybeUninit::<apr_sys::apr_finfo_t>::zeroed();
+            let status = unsafe {
+                apr_sys::apr_dir_read(
+                    finfo.as_mut_ptr(),
+                    (self.wanted | InfoWanted::NAME).0,
+                    self.raw,
+                )
+            };
+
+            // APR signals the end of the directory with ENOENT.
+            if status == apr_sys::APR_ENOENT as i32 {
+                return None;
+            }
+            if status != apr_sys::APR_SUCCESS as i32 && status != apr_sys::APR_INCOMPLETE as i32 {
+                return Some(Err(Status::from(status)));
+            }
+
+            let mut info = unsafe { FileInfo::from_raw(finfo.assume_init_ref()) };
+            match info.name.as_deref() {
+                Some(".") | Some("..") => continue,
+                Some(name) => info.path = Some(self.path.join(name)),
+                None => {}
+            }
+            return Some(Ok(info));
+        }
+    }
+}
+
+impl<'pool> Drop for Dir<'pool> {
+    fn drop(&mut self) {
+        unsafe {
+            apr_sys::apr_dir_close(self.raw);
+        }
+    }
+}
+
+/// Open a directory for reading
+pub fn read_dir<'pool, P: AsRef<Path>>(
+    path: P,
+    pool: &'pool Pool<'pool>,
+) -> Result<Dir<'pool>, Status> {
+    Dir::open(path, pool)
+}
+
+type WalkFilter<'a> = Box<dyn FnMut(&FileInfo) -> bool + 'a>;
+
+/// A directory being walked, opened in its own subpool so that its memory
+/// is released as soon as the walk leaves it
+struct WalkLevel<'pool> {
+    path: PathBuf,
+    // Declared before the pool so the directory is closed first.
+    dir: Dir<'pool>,
+    _pool: Pool<'pool>,
+}
+
+impl<'pool> WalkLevel<'pool> {
+    fn open(path: &Path, wanted: InfoWanted, parent: &'pool Pool<'pool>) -> crate::Result<Self> {
+        let pool = parent.subpool();
+        let dir = Dir::open_in(path, pool.as_mut_ptr())
+            .map_err(|e| {
+                crate::Error::from_status(e)
+                    .context(format!("Failed to open directory: {:?}", path))
+            })?
+            .wanted(wanted);
+        Ok(WalkLevel {
+            path: path.to_path_buf(),
+            dir,
+            _pool: pool,
+        })
+    }
+}
+
+/// Recursive directory walker, created by [`walk`]
+///
+/// Directories are yielded before their contents. Symbolic links to
+/// directories are not followed. A directory that can't be opened is still
+/// yielded, followed by the error for opening it.
+pub struct Walk<'pool> {
+    pool: &'pool Pool<'pool>,
+    root: Option<PathBuf>,
+    stack: Vec<WalkLevel<'pool>>,
+    pending: Option<crate::Error>,
+    wanted: InfoWanted,
+    max_depth: Option<usize>,
+    filter: Option<WalkFilter<'pool>>,
+    descend: Option<WalkFilter<'pool>>,
+}
+
+/// Walk the directory tree rooted at `root`
+pub fn walk<'pool, P: AsRef<Path>>(root: P, pool: &'pool Pool<'pool>) -> Walk<'pool> {
+    Walk {
+        pool,
+        root: Some(root.as_ref().to_path_buf()),
+        stack: Vec::new(),
+        pending: None,
+        wanted: InfoWanted::DIRENT | InfoWanted::TYPE,
+        max_depth: None,
+        filter: None,
+        descend: None,
+    }
+}
+
+impl<'pool> Walk<'pool> {
+    /// Set the fields retrieved for each entry (name and type are always included)
+    pub fn wanted(mut self, wanted: InfoWanted) -> Self {
+        self.wanted = wanted | InfoWanted::DIRENT | InfoWanted::TYPE;
+        self
+    }
+
+    /// Don't descend more than `depth` levels below the root
+    ///
+    /// A depth of 1 only yields the entries of the root directory itself.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Only yield entries for which `filter` returns true
+    ///
+    /// Directories that are filtered out are still descended into.
+    pub fn filter_entry<F: FnMut(&FileInfo) -> bool + 'pool>(mut self, filter: F) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Only descend into directories for which `descend` returns true
+    pub fn descend<F: FnMut(&FileInfo) -> bool + 'pool>(mut self, descend: F) -> Self {
+        self.descend = Some(Box::new(descend));
+        self
+    }
+}
+
+impl<'pool> Iterator for Walk<'pool> {
+    type Item = crate::Result<FileInfo>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(root) = self.root.take() {
+            match WalkLevel::open(&root, self.wanted, self.pool) {
+                Ok(level) => self.stack.push(level),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        loop {
+            if let Some(e) = self.pending.take() {
+                return Some(Err(e));
+            }
+
+            let depth = self.stack.len();
+            let level = self.stack.last_mut()?;
+            let entry = match level.dir.next() {
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+                Some(Err(e)) => {
+                    return Some(Err(crate::Error::from_status(e)
+                        .context(format!("Failed to read directory: {:?}", level.path))))
+                }
+                Some(Ok(entry)) => entry,
+            };
+
+            let below_max = self.max_depth.is_none_or(|max| depth < max);
+            if entry.is_dir() && below_max && self.descend.as_mut().is_none_or(|f| f(&entry)) {
+                if let Some(path) = entry.path() {
+                    // The entry itself is still yielded; the error comes next.
+                    match WalkLevel::open(path, self.wanted, self.pool) {
+                        Ok(level) => self.stack.push(level),
+                        Err(e) => self.pending = Some(e),
+                    }
+                }
+            }
+
+            if self.filter.as_mut().is_none_or(|f| f(&entry)) {
+                return Some(Ok(entry));
+            }
+        }
+    }
+}
+
 /// High-level convenience functions for common file operations
 pub mod io {
     use super::*;
@@ -505,4 +1090,149 @@ mod tests {
             (apr_sys::APR_FOPEN_READ | apr_sys::APR_FOPEN_WRITE | apr_sys::APR_FOPEN_CREATE) as i32;
         assert_eq!(flags.0, expected);
     }
+
+    #[test]
+    fn test_stat() {
+        let pool = Pool::new();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stat.txt");
+        std::fs::write(&path, b"twelve bytes").unwrap();
+
+        let info = stat(
+            &path,
+            InfoWanted::combine(&[InfoWanted::MIN, InfoWanted::IDENT]),
+            &pool,
+        )
+        .unwrap();
+        assert_eq!(info.file_type(), Some(FileType::Regular));
+        assert!(info.is_file());
+        assert_eq!(info.size(), Some(12));
+        assert!(info.mtime().is_some());
+        assert!(info.inode().is_some());
+        assert_eq!(info.path(), Some(path.as_path()));
+
+        let info = stat(dir.path(), InfoWanted::TYPE, &pool).unwrap();
+        assert!(info.is_dir());
+        assert_eq!(info.size(), None);
+
+        assert!(stat(dir.path().join("missing"), InfoWanted::TYPE, &pool).is_err());
+    }
+
+    #[test]
+    fn test_file_info() {
+        let pool = Pool::new();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("info.txt");
+        std::fs::write(&path, b"abc").unwrap();
+
+        let file = File::open(&path, OpenFlags::READ, 0, &pool).unwrap();
+        let info = file.info(InfoWanted::NORM).unwrap();
+        assert!(info.is_file());
+        assert_eq!(info.size(), Some(3));
+        assert_eq!(info.nlink(), Some(1));
+    }
+
+    #[test]
+    fn test_read_dir() {
+        let pool = Pool::new();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), b"a").unwrap();
+        std::fs::write(dir.path().join("b.txt"), b"b").unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+
+        let mut entries: Vec<_> = read_dir(dir.path(), &pool)
+            .unwrap()
+            .map(|e| e.unwrap())
+            .map(|e| (e.name().unwrap().to_string(), e.is_dir()))
+            .collect();
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![
+                ("a.txt".to_string(), false),
+                ("b.txt".to_string(), false),
+                ("sub".to_string(), true),
+            ]
+        );
+
+        let mut d = Dir::open(dir.path(), &pool).unwrap();
+        let first = d.next().unwrap().unwrap();
+        assert_eq!(first.path().unwrap().parent(), Some(dir.path()));
+        d.rewind().unwrap();
+        assert_eq!(d.count(), 3);
+    }
+
+    #[test]
+    fn test_walk() {
+        let pool = Pool::new();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("x/y")).unwrap();
+        std::fs::create_dir(dir.path().join("skip")).unwrap();
+        std::fs::write(dir.path().join("top.rs"), b"").unwrap();
+        std::fs::write(dir.path().join("x/mid.rs"), b"").unwrap();
+        std::fs::write(dir.path().join("x/y/deep.rs"), b"").unwrap();
+        std::fs::write(dir.path().join("x/y/deep.txt"), b"").unwrap();
+        std::fs::write(dir.path().join("skip/hidden.rs"), b"").unwrap();
+
+        let rel = |info: FileInfo| {
+            info.path()
+                .unwrap()
+                .strip_prefix(dir.path())
+                .unwrap()
+                .to_path_buf()
+        };
+
+        let mut all: Vec<_> = walk(dir.path(), &pool).map(|e| rel(e.unwrap())).collect();
+        all.sort();
+        assert_eq!(all.len(), 9);
+
+        let mut rust_files: Vec<_> = walk(dir.path(), &pool)
+            .filter_entry(|info| info.is_file() && info.name().unwrap().ends_with(".rs"))
+            .descend(|info| info.name() != Some("skip"))
+            .map(|e| rel(e.unwrap()))
+            .collect();
+        rust_files.sort();
+        assert_eq!(
+            rust_files,
+            vec![
+                PathBuf::from("top.rs"),
+                PathBuf::from("x/mid.rs"),
+                PathBuf::from("x/y/deep.rs"),
+            ]
+        );
+
+        let shallow = walk(dir.path(), &pool).max_depth(1).count();
+        assert_eq!(shallow, 3);
+
+        assert!(walk(dir.path().join("missing"), &pool)
+            .next()
+            .unwrap()
+            .is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_walk_unreadable_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let pool = Pool::new();
+        let dir = tempfile::tempdir().unwrap();
+        let locked = dir.path().join("locked");
+        std::fs::create_dir(&locked).unwrap();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+        if std::fs::read_dir(&locked).is_ok() {
+            // Running as root, so the directory can be read anyway.
+            std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+            return;
+        }
+
+        let mut entries = walk(dir.path(), &pool);
+        let entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.name(), Some("locked"));
+        let err = entries.next().unwrap().unwrap_err();
+        assert!(err.to_string().contains("locked"), "{}", err);
+        assert!(entries.next().is_none());
+
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
 }
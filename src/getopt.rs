@@ -1,9 +1,11 @@
 //! Command line option parsing.
 use crate::pool::Pool;
 use alloc::borrow::ToOwned;
+use alloc::collections::BTreeMap;
 use alloc::string::String;
 use alloc::vec::Vec;
 use core::marker::PhantomData;
+use core::str::FromStr;
 
 /// A trait for types that can be converted into a sequence of allowed option characters.
 pub trait IntoAllowedOptionChars {
@@ -71,18 +73,17 @@ impl<'pool> Option<'pool> {
         name: &str,
         has_arg: bool,
         indicator: Indicator,
-        description: core::option::Option<&'pool str>,
+        description: core::option::Option<&str>,
     ) -> Self {
-        let name = alloc::ffi::CString::new(name).unwrap();
-        let description = description.map(|s| alloc::ffi::CString::new(s).unwrap());
-
+        // The strings are copied into the pool, since APR keeps pointing at
+        // them for as long as the option is in use.
         let option = pool.calloc::<apr_sys::apr_getopt_option_t>();
         unsafe {
-            (*option).name = name.as_ptr() as *mut _;
+            (*option).name = pool.pstrdup(name);
             (*option).has_arg = if has_arg { 1 } else { 0 };
             (*option).optch = indicator.into();
             if let Some(description) = description {
-                (*option).description = description.as_ptr() as *mut _;
+                (*option).description = pool.pstrdup(description);
             }
         }
 
@@ -117,10 +118,13 @@ impl<'pool> Option<'pool> {
         }
     }
 
-    /// Returns the description of the option.
+    /// Returns the description of the option, or an empty string if it has none.
     pub fn description(&self) -> &str {
         unsafe {
             let description = (*self.ptr).description;
+            if description.is_null() {
+                return "";
+            }
             core::ffi::CStr::from_ptr(description).to_str().unwrap()
         }
     }
@@ -309,6 +313,49 @@ impl Getopt<'_> {
         }
     }
 
+    /// The argument at `index` in the (possibly permuted) argument vector.
+    fn arg(&self, index: usize) -> String {
+        unsafe {
+            let arg = *(*self.ptr).argv.add(index);
+            core::ffi::CStr::from_ptr(arg)
+                .to_string_lossy()
+                .into_owned()
+        }
+    }
+
+    /// The arguments apr_getopt_long stopped at, once it has returned `APR_EOF`.
+    fn remaining(&self) -> Vec<String> {
+        let (ind, argc) = unsafe { ((*self.ptr).ind as usize, (*self.ptr).argc as usize) };
+        (ind..argc).map(|i| self.arg(i)).collect()
+    }
+
+    /// The token apr_getopt_long rejected with `status`.
+    ///
+    /// `place` is the value of `apr_getopt_t::place` before the call: when it
+    /// is non-empty APR was in the middle of a run of short options like
+    /// `-vx`, otherwise it started on the argument just before `ind`.
+    fn rejected_token(&self, place: *const core::ffi::c_char, status: u32, optch: i32) -> String {
+        let in_run = unsafe { *place != 0 };
+        if in_run {
+            let ch = if status == apr_sys::APR_BADCH {
+                unsafe { *place as u8 as char }
+            } else {
+                optch as u8 as char
+            };
+            return alloc::format!("-{}", ch);
+        }
+
+        let ind = unsafe { (*self.ptr).ind as usize };
+        let token = self.arg(ind.max(1) - 1);
+        if token.starts_with("--") || token.len() < 2 {
+            token
+        } else if status == apr_sys::APR_BADCH {
+            alloc::format!("-{}", token[1..].chars().next().unwrap())
+        } else {
+            alloc::format!("-{}", optch as u8 as char)
+        }
+    }
+
     /// Return ptr to the underlying `apr_getopt_t` structure.
     pub fn as_ptr(&self) -> *const apr_sys::apr_getopt_t {
         self.ptr
@@ -320,11 +367,430 @@ impl Getopt<'_> {
     }
 }
 
+/// Description of an option accepted by an [`OptionParser`].
+///
+/// Every option has a long name, used both as `--name` on the command line
+/// and as its key in [`Matches`].
+#[derive(Debug, Clone)]
+pub struct OptionSpec {
+    long: String,
+    short: core::option::Option<char>,
+    value_name: core::option::Option<String>,
+    repeatable: bool,
+    help: core::option::Option<String>,
+    check: core::option::Option<fn(&str) -> bool>,
+}
+
+fn parses_as<T: FromStr>(value: &str) -> bool {
+    value.parse::<T>().is_ok()
+}
+
+impl OptionSpec {
+    /// An option that takes no value, given as `--long`.
+    pub fn flag(long: &str) -> Self {
+        Self {
+            long: long.to_owned(),
+            short: None,
+            value_name: None,
+            repeatable: false,
+            help: None,
+            check: None,
+        }
+    }
+
+    /// An option that takes a value, given as `--long VALUE` or `--long=VALUE`.
+    ///
+    /// `value_name` is the placeholder shown for the value in the help text.
+    pub fn value(long: &str, value_name: &str) -> Self {
+        Self {
+            value_name: Some(value_name.to_owned()),
+            ..Self::flag(long)
+        }
+    }
+
+    /// Also accept the option as `-c`.
+    pub fn short(mut self, c: char) -> Self {
+        assert!(
+            c.is_ascii_graphic() && c != '-',
+            "invalid short option: {:?}",
+            c
+        );
+        self.short = Some(c);
+        self
+    }
+
+    /// Allow the option to be given more than once.
+    pub fn repeatable(mut self) -> Self {
+        self.repeatable = true;
+        self
+    }
+
+    /// Reject values that do not parse as `T`.
+    pub fn parse_as<T: FromStr>(mut self) -> Self {
+        self.check = Some(parses_as::<T>);
+        self
+    }
+
+    /// Set the description shown in the help text.
+    pub fn help(mut self, help: &str) -> Self {
+        self.help = Some(help.to_owned());
+        self
+    }
+
+    fn takes_value(&self) -> bool {
+        self.value_name.is_some()
+    }
+}
+
+/// An error from [`OptionParser::parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// `--help` was given; show [`OptionParser::help`] to the user.
+    Help,
+
+    /// An option that was not defined, such as `--frobnicate` or `-x`.
+    UnknownOption(String),
+
+    /// An option that takes a value was given without one.
+    MissingArgument(String),
+
+    /// A flag was given a value, as in `--verbose=yes`.
+    UnexpectedArgument(String),
+
+    /// A value did not parse as the type the option expects.
+    InvalidValue {
+        /// The option, such as `--jobs`.
+        option: String,
+        /// The value it was given.
+        value: String,
+    },
+
+    /// An option that is not repeatable was given more than once.
+    Repeated(String),
+}
+
+impl core::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            ParseError::Help => write!(f, "help requested"),
+            ParseError::UnknownOption(token) => write!(f, "invalid option: {}", token),
+            ParseError::MissingArgument(token) => write!(f, "missing argument: {}", token),
+            ParseError::UnexpectedArgument(token) => write!(f, "erroneous argument: {}", token),
+            ParseError::InvalidValue { option, value } => {
+                write!(f, "invalid value for {}: {}", option, value)
+            }
+            ParseError::Repeated(option) => write!(f, "option given more than once: {}", option),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
+/// The options found by [`OptionParser::parse`], keyed by long name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Matches {
+    occurrences: BTreeMap<String, Vec<core::option::Option<String>>>,
+    free: Vec<String>,
+}
+
+impl Matches {
+    /// Whether the option `long` was given.
+    pub fn contains(&self, long: &str) -> bool {
+        self.occurrences.contains_key(long)
+    }
+
+    /// How many times the option `long` was given, e.g. 3 for `-vvv`.
+    pub fn count(&self, long: &str) -> usize {
+        self.occurrences.get(long).map_or(0, Vec::len)
+    }
+
+    /// The value the option `long` was last given with.
+    pub fn value(&self, long: &str) -> core::option::Option<&str> {
+        self.values(long).pop()
+    }
+
+    /// Every value the option `long` was given with, in command line order.
+    pub fn values(&self, long: &str) -> Vec<&str> {
+        self.occurrences
+            .get(long)
+            .into_iter()
+            .flatten()
+            .filter_map(|value| value.as_deref())
+            .collect()
+    }
+
+    /// The value the option `long` was last given with, parsed as `T`.
+    pub fn get<T: FromStr>(&self, long: &str) -> Result<core::option::Option<T>, ParseError> {
+        self.value(long)
+            .map(|value| Self::parse_value(long, value))
+            .transpose()
+    }
+
+    /// Every value the option `long` was given with, parsed as `T`.
+    pub fn get_all<T: FromStr>(&self, long: &str) -> Result<Vec<T>, ParseError> {
+        self.values(long)
+            .into_iter()
+            .map(|value| Self::parse_value(long, value))
+            .collect()
+    }
+
+    /// The arguments following the options.
+    pub fn free(&self) -> &[String] {
+        &self.free
+    }
+
+    fn parse_value<T: FromStr>(long: &str, value: &str) -> Result<T, ParseError> {
+        value.parse().map_err(|_| ParseError::InvalidValue {
+            option: alloc::format!("--{}", long),
+            value: value.to_owned(),
+        })
+    }
+}
+
+/// Types that can be built from the options an [`OptionParser`] found.
+pub trait FromMatches: Sized {
+    /// Build `Self` from `matches`.
+    fn from_matches(matches: &Matches) -> Result<Self, ParseError>;
+}
+
+/// Declarative command line parser driven by `apr_getopt_long`.
+///
+/// Options are described once with [`OptionSpec`]s, from which both the
+/// APR option table and the `--help` text are generated. A `--help` option
+/// (and `-h`, unless another option claims it) is always accepted.
+///
+/// ```no_run
+/// # use apr::getopt::{OptionParser, OptionSpec};
+/// let pool = apr::Pool::new();
+/// let parser = OptionParser::new(&pool, "frob")
+///     .usage("[FILE]...")
+///     .option(OptionSpec::flag("verbose").short('v').repeatable().help("Say more"))
+///     .option(OptionSpec::value("jobs", "N").short('j').parse_as::<u32>().help("Run N jobs"));
+/// let matches = parser.parse(&["frob", "-vv", "--jobs=4", "input"]).unwrap();
+/// assert_eq!(matches.count("verbose"), 2);
+/// assert_eq!(matches.get::<u32>("jobs").unwrap(), Some(4));
+/// ```
+pub struct OptionParser<'pool> {
+    pool: &'pool Pool<'pool>,
+    program: String,
+    usage: core::option::Option<String>,
+    specs: Vec<OptionSpec>,
+    options: Vec<Option<'pool>>,
+    help: Option<'pool>,
+    interleave: bool,
+}
+
+impl<'pool> OptionParser<'pool> {
+    /// Create a parser for `program`, allocating the option table in `pool`.
+    pub fn new(pool: &'pool Pool<'pool>, program: &str) -> Self {
+        Self {
+            pool,
+            program: program.to_owned(),
+            usage: None,
+            specs: Vec::new(),
+            options: Vec::new(),
+            help: Self::help_option(pool, Indicator::Letter('h')),
+            interleave: false,
+        }
+    }
+
+    /// Set the synopsis shown after the program name, e.g. `[FILE]...`.
+    pub fn usage(mut self, usage: &str) -> Self {
+        self.usage = Some(usage.to_owned());
+        self
+    }
+
+    /// Accept options after the first non-option argument.
+    pub fn allow_interleaving(mut self, allow: bool) -> Self {
+        self.interleave = allow;
+        self
+    }
+
+    /// Add an option.
+    ///
+    /// # Panics
+    ///
+    /// If its long or short name is already taken.
+    pub fn option(mut self, spec: OptionSpec) -> Self {
+        assert!(
+            spec.long != "help" && !self.specs.iter().any(|s| s.long == spec.long),
+            "duplicate option: --{}",
+            spec.long
+        );
+        assert!(
+            spec.short.is_none() || !self.specs.iter().any(|s| s.short == spec.short),
+            "duplicate option: -{}",
+            spec.short.unwrap_or_default()
+        );
+
+        // Long-only options are told apart by their position.
+        let indicator = match spec.short {
+            Some(c) => Indicator::Letter(c),
+            None => Indicator::Identifier(self.specs.len() as i32 + 1),
+        };
+        self.options.push(Option::new(
+            self.pool,
+            &spec.long,
+            spec.takes_value(),
+            indicator,
+            spec.help.as_deref(),
+        ));
+        if spec.short == Some('h') {
+            // Out of reach of the identifiers given to long-only options.
+            self.help = Self::help_option(self.pool, Indicator::Identifier(i32::MAX - 255));
+        }
+        self.specs.push(spec);
+        self
+    }
+
+    fn help_option(pool: &'pool Pool<'pool>, indicator: Indicator) -> Option<'pool> {
+        Option::new(
+            pool,
+            "help",
+            false,
+            indicator,
+            Some("Show this help and exit"),
+        )
+    }
+
+    /// The `--help` text: a usage line followed by one line per option.
+    pub fn help(&self) -> String {
+        let rows = self
+            .options
+            .iter()
+            .zip(self.specs.iter().map(|s| s.value_name.as_deref()))
+            .chain(core::iter::once((&self.help, None)))
+            .map(|(option, value_name)| {
+                let mut synopsis = match option.optch() {
+                    Some(c) => alloc::format!("-{}, --{}", c as char, option.name()),
+                    None => alloc::format!("    --{}", option.name()),
+                };
+                if let Some(value_name) = value_name {
+                    synopsis.push(' ');
+                    synopsis.push_str(value_name);
+                }
+                (synopsis, option.description())
+            })
+            .collect::<Vec<_>>();
+        let width = rows.iter().map(|(synopsis, _)| synopsis.len()).max();
+
+        let mut text = alloc::format!("Usage: {} [OPTIONS]", self.program);
+        if let Some(usage) = &self.usage {
+            text.push(' ');
+            text.push_str(usage);
+        }
+        text.push_str("\n\nOptions:\n");
+        for (synopsis, description) in &rows {
+            let line = alloc::format!(
+                "  {:width$}  {}",
+                synopsis,
+                description,
+                width = width.unwrap_or(0)
+            );
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Parse `args`, whose first element is the program name.
+    pub fn parse(&self, args: &[&str]) -> Result<Matches, ParseError> {
+        let mut getopt = Getopt::new(args).expect("apr_getopt_init failed");
+        getopt.allow_interleaving(self.interleave);
+        // Errors are reported through ParseError rather than on stderr.
+        unsafe { (*getopt.ptr).errfn = None };
+
+        let help_optch = unsafe { (*self.help.as_ptr()).optch };
+        let mut table = self
+            .options
+            .iter()
+            .chain(core::iter::once(&self.help))
+            .map(|option| unsafe { *option.as_ptr() })
+            .collect::<Vec<_>>();
+        table.push(apr_sys::apr_getopt_option_t {
+            name: core::ptr::null(),
+            has_arg: 0,
+            optch: 0,
+            description: core::ptr::null(),
+        });
+
+        let mut matches = Matches::default();
+        loop {
+            let place = unsafe { (*getopt.ptr).place };
+            let mut optch: i32 = 0;
+            let mut optarg: *const core::ffi::c_char = core::ptr::null();
+            let rv = unsafe {
+                apr_sys::apr_getopt_long(getopt.ptr, table.as_ptr(), &mut optch, &mut optarg)
+            };
+
+            match rv as u32 {
+                apr_sys::APR_SUCCESS => {}
+                apr_sys::APR_EOF => break,
+                apr_sys::APR_BADCH => {
+                    let token = getopt.rejected_token(place, apr_sys::APR_BADCH, optch);
+                    return Err(ParseError::UnknownOption(token));
+                }
+                apr_sys::APR_BADARG => {
+                    let token = getopt.rejected_token(place, apr_sys::APR_BADARG, optch);
+                    return Err(if token.starts_with("--") && token.contains('=') {
+                        ParseError::UnexpectedArgument(token)
+                    } else {
+                        ParseError::MissingArgument(token)
+                    });
+                }
+                _ => panic!("unexpected status: {}", rv),
+            }
+
+            if optch == help_optch {
+                return Err(ParseError::Help);
+            }
+            let index = match Indicator::from(optch) {
+                Indicator::Letter(c) => self.specs.iter().position(|s| s.short == Some(c)),
+                Indicator::Identifier(i) => Some(i as usize - 1),
+                Indicator::Sentinel => None,
+            }
+            .expect("apr_getopt_long returned an unknown option");
+            let spec = &self.specs[index];
+
+            let value = (!optarg.is_null()).then(|| {
+                unsafe { core::ffi::CStr::from_ptr(optarg) }
+                    .to_string_lossy()
+                    .into_owned()
+            });
+            if let (Some(check), Some(value)) = (spec.check, &value) {
+                if !check(value) {
+                    return Err(ParseError::InvalidValue {
+                        option: alloc::format!("--{}", spec.long),
+                        value: value.clone(),
+                    });
+                }
+            }
+
+            let occurrences = matches.occurrences.entry(spec.long.clone()).or_default();
+            if !spec.repeatable && !occurrences.is_empty() {
+                return Err(ParseError::Repeated(alloc::format!("--{}", spec.long)));
+            }
+            occurrences.push(value);
+        }
+
+        matches.free = getopt.remaining();
+        Ok(matches)
+    }
+
+    /// Parse `args` into a `T`.
+    pub fn parse_into<T: FromMatches>(&self, args: &[&str]) -> Result<T, ParseError> {
+        T::from_matches(&self.parse(args)?)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use alloc::borrow::ToOwned;
+    use alloc::string::String;
     use alloc::string::ToString;
     use alloc::vec;
+    use alloc::vec::Vec;
 
     #[test]
     fn test_getopt_long() {
@@ -382,4 +848,137 @@ mod tests {
             ]
         );
     }
+
+    fn frob_parser<'pool>(pool: &'pool crate::pool::Pool<'pool>) -> super::OptionParser<'pool> {
+        use super::{OptionParser, OptionSpec};
+        OptionParser::new(pool, "frob")
+            .usage("[FILE]...")
+            .option(
+                OptionSpec::flag("verbose")
+                    .short('v')
+                    .repeatable()
+                    .help("Say more"),
+            )
+            .option(
+                OptionSpec::value("jobs", "N")
+                    .short('j')
+                    .parse_as::<u32>()
+                    .help("Run N jobs"),
+            )
+            .option(OptionSpec::value("output", "FILE").help("Write to FILE"))
+    }
+
+    #[test]
+    fn test_option_parser() {
+        let pool = crate::pool::Pool::new();
+        let parser = frob_parser(&pool);
+
+        let matches = parser
+            .parse(&[
+                "frob", "-vv", "--jobs=4", "--output", "out", "input", "rest",
+            ])
+            .unwrap();
+        assert_eq!(matches.count("verbose"), 2);
+        assert_eq!(matches.get::<u32>("jobs").unwrap(), Some(4));
+        assert_eq!(matches.value("output"), Some("out"));
+        assert!(!matches.contains("missing"));
+        assert_eq!(matches.free(), &["input".to_owned(), "rest".to_owned()]);
+
+        let matches = parser.parse(&["frob", "input", "-v"]).unwrap();
+        assert!(!matches.contains("verbose"));
+        assert_eq!(matches.free(), &["input".to_owned(), "-v".to_owned()]);
+
+        let parser = frob_parser(&pool).allow_interleaving(true);
+        let matches = parser.parse(&["frob", "input", "-v", "rest"]).unwrap();
+        assert_eq!(matches.count("verbose"), 1);
+        assert_eq!(matches.free(), &["input".to_owned(), "rest".to_owned()]);
+    }
+
+    #[test]
+    fn test_option_parser_errors() {
+        use super::ParseError;
+        let pool = crate::pool::Pool::new();
+        let parser = frob_parser(&pool);
+
+        assert_eq!(
+            parser.parse(&["frob", "--bogus"]),
+            Err(ParseError::UnknownOption("--bogus".to_owned()))
+        );
+        assert_eq!(
+            parser.parse(&["frob", "-vx"]),
+            Err(ParseError::UnknownOption("-x".to_owned()))
+        );
+        assert_eq!(
+            parser.parse(&["frob", "--output"]),
+            Err(ParseError::MissingArgument("--output".to_owned()))
+        );
+        assert_eq!(
+            parser.parse(&["frob", "-j"]),
+            Err(ParseError::MissingArgument("-j".to_owned()))
+        );
+        assert_eq!(
+            parser.parse(&["frob", "--verbose=yes"]),
+            Err(ParseError::UnexpectedArgument("--verbose=yes".to_owned()))
+        );
+        assert_eq!(
+            parser.parse(&["frob", "-j", "many"]),
+            Err(ParseError::InvalidValue {
+                option: "--jobs".to_owned(),
+                value: "many".to_owned()
+            })
+        );
+        assert_eq!(
+            parser.parse(&["frob", "--output=a", "--output=b"]),
+            Err(ParseError::Repeated("--output".to_owned()))
+        );
+        assert_eq!(parser.parse(&["frob", "-v", "-h"]), Err(ParseError::Help));
+        assert_eq!(
+            ParseError::UnknownOption("-x".to_owned()).to_string(),
+            "invalid option: -x"
+        );
+    }
+
+    #[test]
+    fn test_option_parser_help() {
+        let pool = crate::pool::Pool::new();
+        assert_eq!(
+            frob_parser(&pool).help(),
+            "Usage: frob [OPTIONS] [FILE]...\n\
+             \n\
+             Options:\n\
+             \x20 -v, --verbose      Say more\n\
+             \x20 -j, --jobs N       Run N jobs\n\
+             \x20     --output FILE  Write to FILE\n\
+             \x20 -h, --help         Show this help and exit\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_into() {
+        use super::{FromMatches, Matches, ParseError};
+
+        struct Frob {
+            verbosity: usize,
+            jobs: u32,
+            files: Vec<String>,
+        }
+
+        impl FromMatches for Frob {
+            fn from_matches(matches: &Matches) -> Result<Self, ParseError> {
+                Ok(Frob {
+                    verbosity: matches.count("verbose"),
+                    jobs: matches.get("jobs")?.unwrap_or(1),
+                    files: matches.free().to_vec(),
+                })
+            }
+        }
+
+        let pool = crate::pool::Pool::new();
+        let frob: Frob = frob_parser(&pool)
+            .parse_into(&["frob", "-v", "-j8", "a", "b"])
+            .unwrap();
+        assert_eq!(frob.verbosity, 1);
+        assert_eq!(frob.jobs, 8);
+        assert_eq!(frob.files, vec!["a".to_owned(), "b".to_owned()]);
+    }
 }
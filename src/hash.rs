@@ -1,9 +1,71 @@
 //! Hash table support.
 
+use crate::callbacks::CallbackHandle;
 use crate::pool::Pool;
+use alloc::vec::Vec;
 pub use apr_sys::apr_hash_t;
-use core::ffi::c_void;
+use core::alloc::Layout;
+use core::ffi::{c_char, c_uint, c_void, CStr};
+use core::fmt;
 use core::marker::PhantomData;
+use core::ops::Index;
+use core::ptr::NonNull;
+
+/// View a key passed back by APR as a byte slice.
+unsafe fn key_slice<'a>(key: *const c_void, klen: apr_sys::apr_ssize_t) -> &'a [u8] {
+    if key.is_null() || klen <= 0 {
+        &[]
+    } else {
+        core::slice::from_raw_parts(key as *const u8, klen as usize)
+    }
+}
+
+unsafe extern "C" fn hash_fn_trampoline<F: Fn(&[u8]) -> u32>(
+    key: *const c_char,
+    klen: *mut apr_sys::apr_ssize_t,
+) -> c_uint {
+    // APR_HASH_KEY_STRING keys are NUL-terminated; like the default hash
+    // function, report the real length back to APR.
+    if *klen < 0 {
+        *klen = CStr::from_ptr(key).to_bytes().len() as apr_sys::apr_ssize_t;
+    }
+    // `F` is zero-sized (checked when the hash was created), so any pointer
+    // to it is a valid instance.
+    let f = &*NonNull::<F>::dangling().as_ptr();
+    f(key_slice(key as *const c_void, *klen))
+}
+
+unsafe extern "C" fn merge_trampoline<F>(
+    _pool: *mut apr_sys::apr_pool_t,
+    key: *const c_void,
+    klen: apr_sys::apr_ssize_t,
+    h1_val: *const c_void,
+    h2_val: *const c_void,
+    data: *const c_void,
+) -> *mut c_void
+where
+    F: FnMut(&[u8], *mut c_void, *mut c_void) -> *mut c_void,
+{
+    let merger = &mut *(data as *mut F);
+    merger(
+        key_slice(key, klen),
+        h1_val as *mut c_void,
+        h2_val as *mut c_void,
+    )
+}
+
+unsafe extern "C" fn do_trampoline<F>(
+    rec: *mut c_void,
+    key: *const c_void,
+    klen: apr_sys::apr_ssize_t,
+    value: *const c_void,
+) -> core::ffi::c_int
+where
+    F: FnMut(&[u8], *mut c_void) -> bool,
+{
+    let f = &mut *(rec as *mut F);
+    f(key_slice(key, klen), value as *mut c_void) as core::ffi::c_int
+}
 
 /// A hash table that stores byte slices as keys and raw pointers as values.
 ///
@@ -24,6 +86,31 @@ impl<'pool> Hash<'pool> {
         }
     }
 
+    /// Create a new hash table that hashes keys with `hash_fn`.
+    ///
+    /// APR passes no context to hash functions, so `hash_fn` must be a
+    /// closure that captures nothing (or a plain function item).
+    ///
+    /// # Panics
+    /// Panics if `hash_fn` captures any state.
+    pub fn with_hash_function<F>(pool: &'pool Pool<'pool>, hash_fn: F) -> Self
+    where
+        F: Fn(&[u8]) -> u32 + Copy + 'static,
+    {
+        assert_eq!(
+            core::mem::size_of::<F>(),
+            0,
+            "hash functions must not capture any state"
+        );
+        let _ = hash_fn;
+        Self {
+            ptr: unsafe {
+                apr_sys::apr_hash_make_custom(pool.as_mut_ptr(), Some(hash_fn_trampoline::<F>))
+            },
+            _phantom: PhantomData,
+        }
+    }
+
     /// Create a hash table from a raw pointer.
     ///
     /// # Safety
@@ -96,6 +183,77 @@ impl<'pool> Hash<'pool> {
         }
     }
 
+    /// Copy the hash table into `pool`, sharing the keys and values.
+    pub fn copy<'a>(&self, pool: &'a Pool<'a>) -> Hash<'a>
+    where
+        'pool: 'a,
+    {
+        unsafe { Hash::from_ptr(apr_sys::apr_hash_copy(pool.as_mut_ptr(), self.ptr)) }
+    }
+
+    /// Create a new hash table in `pool` with the entries of this table and
+    /// `base`, preferring this table's values for keys present in both.
+    ///
+    /// Both tables must use the same hash function.
+    pub fn overlay(&self, base: &Hash<'pool>, pool: &'pool Pool<'pool>) -> Hash<'pool> {
+        unsafe {
+            Hash::from_ptr(apr_sys::apr_hash_overlay(
+                pool.as_mut_ptr(),
+                self.ptr,
+                base.ptr,
+            ))
+        }
+    }
+
+    /// Create a new hash table in `pool` with the entries of this table and
+    /// `other`.
+    ///
+    /// For keys present in both, `merger` is called with the key, this
+    /// table's value and `other`'s value, and returns the value to store.
+    /// Both tables must use the same hash function.
+    pub fn merge<F>(&self, other: &Hash<'pool>, pool: &'pool Pool<'pool>, merger: F) -> Hash<'pool>
+    where
+        F: FnMut(&[u8], *mut c_void, *mut c_void) -> *mut c_void,
+    {
+        let mut merger = CallbackHandle::new(merger);
+        unsafe {
+            Hash::from_ptr(apr_sys::apr_hash_merge(
+                pool.as_mut_ptr(),
+                self.ptr,
+                other.ptr,
+                Some(merge_trampoline::<F>),
+                merger.baton_mut(),
+            ))
+        }
+    }
+
+    /// Create a copy of the hash table in `pool` holding only the entries for
+    /// which `keep` returns `true`.
+    pub fn filter<'a, F>(&self, pool: &'a Pool<'a>, mut keep: F) -> Hash<'a>
+    where
+        F: FnMut(&[u8], *mut c_void) -> bool,
+        'pool: 'a,
+    {
+        let mut filtered = self.copy(pool);
+        for (key, value) in self.iter() {
+            if !keep(key, value) {
+                filtered.remove(key);
+            }
+        }
+        filtered
+    }
+
+    /// Call `f` with each key and value, stopping early if it returns `false`.
+    ///
+    /// Returns `false` if iteration was stopped by `f`.
+    pub fn do_each<F>(&self, f: F) -> bool
+    where
+        F: FnMut(&[u8], *mut c_void) -> bool,
+    {
+        let mut f = CallbackHandle::new(f);
+        unsafe { apr_sys::apr_hash_do(Some(do_trampoline::<F>), f.baton_mut(), self.ptr) != 0 }
+    }
+
     /// Create an iterator over the hash table entries.
     pub fn iter(&self) -> HashIter<'pool> {
         HashIter {
@@ -346,6 +504,88 @@ impl<'pool, 'a> Extend<(&'a [u8], *mut c_void)> for Hash<'pool> {
     }
 }
 
+impl<'pool, V: 'pool> TypedHash<'pool, V> {
+    /// Create a new typed hash table that hashes keys with `hash_fn`.
+    ///
+    /// See [`Hash::with_hash_function`].
+    pub fn with_hash_function<F>(pool: &'pool Pool<'pool>, hash_fn: F) -> Self
+    where
+        F: Fn(&[u8]) -> u32 + Copy + 'static,
+    {
+        Self {
+            inner: Hash::with_hash_function(pool, hash_fn),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Copy the hash table into `pool`, sharing the keys and values.
+    pub fn copy<'a>(&self, pool: &'a Pool<'a>) -> TypedHash<'a, V>
+    where
+        'pool: 'a,
+    {
+        TypedHash {
+            inner: self.inner.copy(pool),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Create a new hash table in `pool` with the entries of this table and
+    /// `base`, preferring this table's values for keys present in both.
+    pub fn overlay(&self, base: &TypedHash<'pool, V>, pool: &'pool Pool<'pool>) -> Self {
+        Self {
+            inner: self.inner.overlay(&base.inner, pool),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Create a new hash table in `pool` with the entries of this table and
+    /// `other`, calling `merger` to combine the values of keys present in both.
+    pub fn merge<F>(
+        &self,
+        other: &TypedHash<'pool, V>,
+        pool: &'pool Pool<'pool>,
+        mut merger: F,
+    ) -> Self
+    where
+        F: FnMut(&[u8], &'pool V, &'pool V) -> &'pool V,
+    {
+        let inner = self.inner.merge(&other.inner, pool, |key, a, b| {
+            let (a, b) = unsafe { (&*(a as *const V), &*(b as *const V)) };
+            merger(key, a, b) as *const V as *mut c_void
+        });
+        Self {
+            inner,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Create a copy of the hash table in `pool` holding only the entries for
+    /// which `keep` returns `true`.
+    pub fn filter<'a, F>(&self, pool: &'a Pool<'a>, mut keep: F) -> TypedHash<'a, V>
+    where
+        F: FnMut(&[u8], &'pool V) -> bool,
+        'pool: 'a,
+    {
+        TypedHash {
+            inner: self.inner.filter(pool, |key, value| {
+                keep(key, unsafe { &*(value as *const V) })
+            }),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Call `f` with each key and value, stopping early if it returns `false`.
+    ///
+    /// Returns `false` if iteration was stopped by `f`.
+    pub fn do_each<F>(&self, mut f: F) -> bool
+    where
+        F: FnMut(&[u8], &'pool V) -> bool,
+    {
+        self.inner
+            .do_each(|key, value| f(key, unsafe { &*(value as *const V) }))
+    }
+}
+
 impl<'pool, V: 'pool> TypedHash<'pool, V> {
     /// Create a typed hash from an iterator of key-value pairs.
     pub fn from_iter<'a, I>(pool: &'pool Pool, iter: I) -> Self
@@ -394,9 +634,428 @@ impl<'pool, 'a, V: 'pool> Extend<(&'a [u8], &'pool V)> for TypedHash<'pool, V> {
     }
 }
 
+/// A hash table that owns its values, storing them in pool memory.
+///
+/// Keys are byte strings copied into the pool. Values are moved into the pool
+/// and dropped when they are removed or replaced, when the map is cleared, or
+/// when the pool is cleared or destroyed. The underlying `apr_hash_t` stays
+/// valid until then, even after the `PoolHashMap` itself has gone away, so it
+/// can be handed to C code expecting a hash of `V` pointers.
+pub struct PoolHashMap<'pool, V: 'static> {
+    hash: Hash<'pool>,
+    pool: &'pool Pool<'pool>,
+    _phantom: PhantomData<V>,
+}
+
+/// Drop every value left in the hash behind `ht`.
+unsafe fn drop_values<V>(ht: *mut apr_hash_t) {
+    let values: Vec<*mut V> = Hash::from_ptr(ht)
+        .iter()
+        .map(|(_, value)| value as *mut V)
+        .collect();
+    apr_sys::apr_hash_clear(ht);
+    for value in values {
+        core::ptr::drop_in_place(value);
+    }
+}
+
+impl<'pool, V: 'static> PoolHashMap<'pool, V> {
+    /// Create an empty map in `pool`.
+    pub fn new(pool: &'pool Pool<'pool>) -> Self {
+        let hash = Hash::new(pool);
+        let ptr = hash.ptr;
+        pool.register_cleanup(move || unsafe { drop_values::<V>(ptr) });
+        Self {
+            hash,
+            pool,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Create a map in `pool` from key-value pairs.
+    ///
+    /// Later pairs replace earlier ones with the same key.
+    pub fn from_iter<K, I>(pool: &'pool Pool<'pool>, iter: I) -> Self
+    where
+        K: AsRef<[u8]>,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut map = Self::new(pool);
+        map.extend(iter);
+        map
+    }
+
+    /// The pool keys and values are stored in.
+    pub fn pool(&self) -> &'pool Pool<'pool> {
+        self.pool
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.hash.len()
+    }
+
+    /// Whether the map has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.hash.is_empty()
+    }
+
+    fn value_ptr(&self, key: &[u8]) -> Option<*mut V> {
+        self.hash.get(key).map(|value| value as *mut V)
+    }
+
+    /// Move `value` into the pool and add it under a pool copy of `key`.
+    fn insert_new(&mut self, key: &[u8], value: V) -> *mut V {
+        let layout = Layout::new::<V>();
+        let slot = self.pool.alloc_layout(layout) as *mut V;
+        if slot.is_null() {
+            alloc::alloc::handle_alloc_error(layout);
+        }
+        unsafe {
+            slot.write(value);
+            let copy = apr_sys::apr_pmemdup(
+                self.pool.as_mut_ptr(),
+                key.as_ptr() as *const c_void,
+                key.len(),
+            ) as *const u8;
+            let key = core::slice::from_raw_parts(copy, key.len());
+            self.hash.insert(key, slot as *mut c_void);
+        }
+        slot
+    }
+
+    /// Whether there is an entry for `key`.
+    pub fn contains_key<K: AsRef<[u8]> + ?Sized>(&self, key: &K) -> bool {
+        self.hash.get(key.as_ref()).is_some()
+    }
+
+    /// The value stored under `key`.
+    pub fn get<K: AsRef<[u8]> + ?Sized>(&self, key: &K) -> Option<&V> {
+        self.value_ptr(key.as_ref()).map(|value| unsafe { &*value })
+    }
+
+    /// Mutable access to the value stored under `key`.
+    pub fn get_mut<K: AsRef<[u8]> + ?Sized>(&mut self, key: &K) -> Option<&mut V> {
+        self.value_ptr(key.as_ref())
+            .map(|value| unsafe { &mut *value })
+    }
+
+    /// Store `value` under `key`, returning the value it replaced.
+    pub fn insert<K: AsRef<[u8]> + ?Sized>(&mut self, key: &K, value: V) -> Option<V> {
+        let key = key.as_ref();
+        match self.value_ptr(key) {
+            Some(slot) => Some(unsafe { core::ptr::replace(slot, value) }),
+            None => {
+                self.insert_new(key, value);
+                None
+            }
+        }
+    }
+
+    /// Remove the entry for `key`, returning its value.
+    pub fn remove<K: AsRef<[u8]> + ?Sized>(&mut self, key: &K) -> Option<V> {
+        let key = key.as_ref();
+        let slot = self.value_ptr(key)?;
+        self.hash.remove(key);
+        Some(unsafe { slot.read() })
+    }
+
+    /// Remove all entries, dropping their values.
+    pub fn clear(&mut self) {
+        unsafe { drop_values::<V>(self.hash.ptr) };
+    }
+
+    /// Get the entry for `key` for in-place manipulation.
+    pub fn entry<'a, K: AsRef<[u8]> + ?Sized>(&'a mut self, key: &'a K) -> Entry<'a, 'pool, V> {
+        let key = key.as_ref();
+        match self.value_ptr(key) {
+            Some(value) => Entry::Occupied(OccupiedEntry {
+                map: self,
+                key,
+                value,
+            }),
+            None => Entry::Vacant(VacantEntry { map: self, key }),
+        }
+    }
+
+    /// Iterate over the entries.
+    pub fn iter(&self) -> Iter<'_, V> {
+        Iter {
+            inner: self.hash.iter(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Iterate over the entries with mutable access to the values.
+    pub fn iter_mut(&mut self) -> IterMut<'_, V> {
+        IterMut {
+            inner: self.hash.iter(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Iterate over the keys.
+    pub fn keys(&self) -> impl Iterator<Item = &[u8]> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Iterate over the values.
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    /// Get the raw pointer to the APR hash table.
+    ///
+    /// Its values are `V` pointers owned by the map.
+    pub fn as_ptr(&self) -> *const apr_hash_t {
+        self.hash.ptr
+    }
+
+    /// Get a mutable raw pointer to the APR hash table.
+    ///
+    /// # Safety
+    /// Any value the caller stores in the table must point to a valid `V` in
+    /// pool memory, which the map will take ownership of. Entries the caller
+    /// removes are leaked rather than dropped.
+    pub unsafe fn as_mut_ptr(&mut self) -> *mut apr_hash_t {
+        self.hash.ptr
+    }
+}
+
+impl<'pool, V: 'static, K: AsRef<[u8]> + ?Sized> Index<&K> for PoolHashMap<'pool, V> {
+    type Output = V;
+
+    /// # Panics
+    /// Panics if there is no entry for `key`.
+    fn index(&self, key: &K) -> &V {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
+impl<'pool, V: 'static, K: AsRef<[u8]>> Extend<(K, V)> for PoolHashMap<'pool, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(&key, value);
+        }
+    }
+}
+
+impl<'pool, V: 'static + fmt::Debug> fmt::Debug for PoolHashMap<'pool, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(
+                self.iter()
+                    .map(|(key, value)| (alloc::string::String::from_utf8_lossy(key), value)),
+            )
+            .finish()
+    }
+}
+
+impl<'a, 'pool, V: 'static> IntoIterator for &'a PoolHashMap<'pool, V> {
+    type Item = (&'a [u8], &'a V);
+    type IntoIter = Iter<'a, V>;
+
+    fn into_iter(self) -> Iter<'a, V> {
+        self.iter()
+    }
+}
+
+impl<'a, 'pool, V: 'static> IntoIterator for &'a mut PoolHashMap<'pool, V> {
+    type Item = (&'a [u8], &'a mut V);
+    type IntoIter = IterMut<'a, V>;
+
+    fn into_iter(self) -> IterMut<'a, V> {
+        self.iter_mut()
+    }
+}
+
+impl<'pool, V: 'static> IntoIterator for PoolHashMap<'pool, V> {
+    type Item = (&'pool [u8], V);
+    type IntoIter = IntoIter<'pool, V>;
+
+    /// Move the values out of the pool; the keys stay in pool memory.
+    fn into_iter(mut self) -> IntoIter<'pool, V> {
+        let entries: Vec<_> = self
+            .hash
+            .iter()
+            .map(|(key, value)| (key, value as *mut V))
+            .collect();
+        self.hash.clear();
+        IntoIter {
+            entries: entries.into_iter(),
+        }
+    }
+}
+
+/// Iterator over the entries of a [`PoolHashMap`].
+pub struct Iter<'a, V> {
+    inner: HashIter<'a>,
+    _phantom: PhantomData<&'a V>,
+}
+
+impl<'a, V> Iterator for Iter<'a, V> {
+    type Item = (&'a [u8], &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|(key, value)| (key, unsafe { &*(value as *const V) }))
+    }
+}
+
+/// Iterator over the entries of a [`PoolHashMap`] with mutable values.
+pub struct IterMut<'a, V> {
+    inner: HashIter<'a>,
+    _phantom: PhantomData<&'a mut V>,
+}
+
+impl<'a, V> Iterator for IterMut<'a, V> {
+    type Item = (&'a [u8], &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|(key, value)| (key, unsafe { &mut *(value as *mut V) }))
+    }
+}
+
+/// Owning iterator over the entries of a [`PoolHashMap`].
+///
+/// Values not consumed are dropped with the iterator.
+pub struct IntoIter<'pool, V> {
+    entries: alloc::vec::IntoIter<(&'pool [u8], *mut V)>,
+}
+
+impl<'pool, V> Iterator for IntoIter<'pool, V> {
+    type Item = (&'pool [u8], V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries
+            .next()
+            .map(|(key, value)| (key, unsafe { value.read() }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<'pool, V> Drop for IntoIter<'pool, V> {
+    fn drop(&mut self) {
+        for (_, value) in self.entries.by_ref() {
+            unsafe { core::ptr::drop_in_place(value) };
+        }
+    }
+}
+
+/// A view into a single entry of a [`PoolHashMap`].
+pub enum Entry<'a, 'pool, V: 'static> {
+    /// The key has a value.
+    Occupied(OccupiedEntry<'a, 'pool, V>),
+    /// The key has no value.
+    Vacant(VacantEntry<'a, 'pool, V>),
+}
+
+impl<'a, 'pool, V: 'static> Entry<'a, 'pool, V> {
+    /// The key of the entry.
+    pub fn key(&self) -> &[u8] {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Insert `default` if the entry is vacant, and return the value.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    /// Insert the result of `default` if the entry is vacant, and return the value.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Call `f` on the value if the entry is occupied.
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, 'pool, V: Default + 'static> Entry<'a, 'pool, V> {
+    /// Insert the default value if the entry is vacant, and return the value.
+    pub fn or_default(self) -> &'a mut V {
+        self.or_insert_with(V::default)
+    }
+}
+
+/// An entry of a [`PoolHashMap`] that has a value.
+pub struct OccupiedEntry<'a, 'pool, V: 'static> {
+    map: &'a mut PoolHashMap<'pool, V>,
+    key: &'a [u8],
+    value: *mut V,
+}
+
+impl<'a, 'pool, V: 'static> OccupiedEntry<'a, 'pool, V> {
+    /// The key of the entry.
+    pub fn key(&self) -> &[u8] {
+        self.key
+    }
+
+    /// The value of the entry.
+    pub fn get(&self) -> &V {
+        unsafe { &*self.value }
+    }
+
+    /// Mutable access to the value of the entry.
+    pub fn get_mut(&mut self) -> &mut V {
+        unsafe { &mut *self.value }
+    }
+
+    /// Turn the entry into a mutable reference to its value.
+    pub fn into_mut(self) -> &'a mut V {
+        unsafe { &mut *self.value }
+    }
+
+    /// Replace the value, returning the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        unsafe { core::ptr::replace(self.value, value) }
+    }
+
+    /// Remove the entry, returning its value.
+    pub fn remove(self) -> V {
+        self.map.hash.remove(self.key);
+        unsafe { self.value.read() }
+    }
+}
+
+/// An entry of a [`PoolHashMap`] that has no value.
+pub struct VacantEntry<'a, 'pool, V: 'static> {
+    map: &'a mut PoolHashMap<'pool, V>,
+    key: &'a [u8],
+}
+
+impl<'a, 'pool, V: 'static> VacantEntry<'a, 'pool, V> {
+    /// The key of the entry.
+    pub fn key(&self) -> &[u8] {
+        self.key
+    }
+
+    /// Store `value` in the entry, returning a mutable reference to it.
+    pub fn insert(self, value: V) -> &'a mut V {
+        unsafe { &mut *self.map.insert_new(self.key, value) }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::rc::Rc;
     use alloc::string::{String, ToString};
     use alloc::vec;
     use alloc::vec::Vec;
@@ -582,4 +1241,213 @@ mod tests {
         assert_eq!(hash.len(), 3);
         assert_eq!(hash.get_ref("c"), Some(&val3));
     }
+
+    #[test]
+    fn test_hash_copy_and_overlay() {
+        let pool = Pool::new();
+        let (a, b, c) = (1, 2, 3);
+        let base = TypedHash::<i32>::from_iter(&pool, [("x", &a), ("y", &b)]);
+        let overlay = TypedHash::<i32>::from_iter(&pool, [("y", &c)]);
+
+        let copy = base.copy(&pool);
+        assert_eq!(copy.len(), 2);
+        assert_eq!(copy.get_ref("x"), Some(&1));
+
+        let combined = overlay.overlay(&base, &pool);
+        assert_eq!(combined.len(), 2);
+        assert_eq!(combined.get_ref("x"), Some(&1));
+        assert_eq!(combined.get_ref("y"), Some(&3));
+    }
+
+    #[test]
+    fn test_hash_merge() {
+        let pool = Pool::new();
+        let values = [1, 10, 2, 20];
+        let first = TypedHash::<i32>::from_iter(&pool, [("a", &values[0]), ("b", &values[2])]);
+        let second = TypedHash::<i32>::from_iter(&pool, [("a", &values[1]), ("c", &values[3])]);
+
+        let mut conflicts = Vec::new();
+        let merged = first.merge(&second, &pool, |key, x, y| {
+            conflicts.push(key.to_vec());
+            if x > y {
+                x
+            } else {
+                y
+            }
+        });
+        assert_eq!(conflicts, vec![b"a".to_vec()]);
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged.get_ref("a"), Some(&10));
+        assert_eq!(merged.get_ref("b"), Some(&2));
+        assert_eq!(merged.get_ref("c"), Some(&20));
+    }
+
+    #[test]
+    fn test_hash_filter_and_do_each() {
+        let pool = Pool::new();
+        let values = [1, 2, 3, 4];
+        let hash = TypedHash::<i32>::from_iter(
+            &pool,
+            [
+                ("a", &values[0]),
+                ("b", &values[1]),
+                ("c", &values[2]),
+                ("d", &values[3]),
+            ],
+        );
+
+        let even = hash.filter(&pool, |_, value| value % 2 == 0);
+        assert_eq!(even.len(), 2);
+        assert_eq!(even.get_ref("b"), Some(&2));
+        assert_eq!(even.get_ref("a"), None);
+        assert_eq!(hash.len(), 4);
+
+        let mut sum = 0;
+        assert!(hash.do_each(|_, value| {
+            sum += value;
+            true
+        }));
+        assert_eq!(sum, 10);
+
+        let mut visited = 0;
+        assert!(!hash.do_each(|_, _| {
+            visited += 1;
+            false
+        }));
+        assert_eq!(visited, 1);
+    }
+
+    #[test]
+    fn test_custom_hash_function() {
+        let pool = Pool::new();
+        let mut hash = TypedHash::<i32>::with_hash_function(&pool, |key| key.len() as u32);
+        let (a, b, c) = (1, 2, 3);
+        hash.insert_ref("ab", &a);
+        hash.insert_ref("cd", &b);
+        hash.insert_ref("efg", &c);
+
+        assert_eq!(hash.len(), 3);
+        assert_eq!(hash.get_ref("ab"), Some(&1));
+        assert_eq!(hash.get_ref("cd"), Some(&2));
+        assert_eq!(hash.get_ref("efg"), Some(&3));
+        assert_eq!(hash.get_ref("gh"), None);
+
+        let copy = hash.copy(&pool);
+        assert_eq!(copy.get_ref("cd"), Some(&2));
+
+        let raw = Hash::with_hash_function(&pool, hash_default);
+        assert!(raw.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "must not capture")]
+    fn test_custom_hash_function_rejects_state() {
+        let pool = Pool::new();
+        let seed = 7u32;
+        let _ = Hash::with_hash_function(&pool, move |key| key.len() as u32 ^ seed);
+    }
+
+    #[test]
+    fn test_pool_hash_map() {
+        let pool = Pool::new();
+        let mut map = PoolHashMap::new(&pool);
+        assert!(map.is_empty());
+
+        let key = String::from("one");
+        assert_eq!(map.insert(&key, vec![1]), None);
+        drop(key);
+        map.insert("two", vec![2, 2]);
+        assert_eq!(map.insert("one", vec![1, 1]), Some(vec![1]));
+
+        assert_eq!(map.len(), 2);
+        assert!(map.contains_key("one"));
+        assert_eq!(map["one"], vec![1, 1]);
+        assert_eq!(map.get(b"two"), Some(&vec![2, 2]));
+        map.get_mut("two").unwrap().push(2);
+        assert_eq!(map["two"].len(), 3);
+
+        assert_eq!(map.remove("one"), Some(vec![1, 1]));
+        assert_eq!(map.remove("one"), None);
+        assert_eq!(map.len(), 1);
+
+        for (_, value) in &mut map {
+            value.clear();
+        }
+        assert!(map.values().all(|value| value.is_empty()));
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec![b"two".as_slice()]);
+    }
+
+    #[test]
+    fn test_pool_hash_map_entry() {
+        let pool = Pool::new();
+        let mut counts = PoolHashMap::<usize>::new(&pool);
+        for word in ["a", "b", "a", "c", "a"] {
+            *counts.entry(word).or_default() += 1;
+        }
+        assert_eq!(counts["a"], 3);
+        assert_eq!(counts["b"], 1);
+
+        counts.entry("b").and_modify(|n| *n += 10).or_insert(0);
+        counts.entry("d").and_modify(|n| *n += 10).or_insert(7);
+        assert_eq!(counts["b"], 11);
+        assert_eq!(counts["d"], 7);
+
+        match counts.entry("c") {
+            Entry::Occupied(entry) => {
+                assert_eq!(entry.key(), b"c");
+                assert_eq!(entry.remove(), 1);
+            }
+            Entry::Vacant(_) => panic!("expected an occupied entry"),
+        }
+        assert!(!counts.contains_key("c"));
+        assert!(matches!(counts.entry("c"), Entry::Vacant(_)));
+    }
+
+    #[test]
+    fn test_pool_hash_map_drops_values() {
+        let marker = Rc::new(());
+        let pool = Pool::new();
+        let mut map = PoolHashMap::from_iter(&pool, [("a", marker.clone()), ("b", marker.clone())]);
+        assert_eq!(Rc::strong_count(&marker), 3);
+
+        map.insert("a", marker.clone());
+        assert_eq!(Rc::strong_count(&marker), 3);
+        map.remove("a");
+        assert_eq!(Rc::strong_count(&marker), 2);
+        map.clear();
+        assert_eq!(Rc::strong_count(&marker), 1);
+
+        // The map itself owns nothing; the value lives until the pool goes.
+        map.insert("c", marker.clone());
+        assert_eq!(Rc::strong_count(&marker), 2);
+        drop(pool);
+        assert_eq!(Rc::strong_count(&marker), 1);
+    }
+
+    #[test]
+    fn test_pool_hash_map_into_iter() {
+        let pool = Pool::new();
+        let marker = Rc::new(());
+        let map = PoolHashMap::from_iter(
+            &pool,
+            [
+                ("x", marker.clone()),
+                ("y", marker.clone()),
+                ("z", marker.clone()),
+            ],
+        );
+        let raw = map.as_ptr();
+        assert_eq!(
+            unsafe { apr_sys::apr_hash_count(raw as *mut apr_hash_t) },
+            3
+        );
+
+        let mut iter = map.into_iter();
+        let (key, value) = iter.next().unwrap();
+        assert_eq!(key.len(), 1);
+        drop(value);
+        assert_eq!(Rc::strong_count(&marker), 3);
+        drop(iter);
+        assert_eq!(Rc::strong_count(&marker), 1);
+    }
 }
@@ -1,5 +1,6 @@
 #![deny(missing_docs)]
 #![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(apr_nightly, feature(allocator_api))]
 
 //! Safe Rust bindings for the Apache Portable Runtime (APR) library.
 //!
@@ -66,17 +67,28 @@
 //! # Module Overview
 //!
 //! - [`pool`] - Memory pool management (fundamental to APR)
+//! - [`pool_alloc`] - Building vectors and strings directly in pool memory
 //! - [`error`] - Error types and status code handling
 //! - [`file`] - File I/O operations
+//! - [`buckets`] - Bucket brigades for streaming data
 //! - [`network`] - Network I/O and socket operations
+//! - [`poll`] - Waiting on many sockets and files at once
+//! - [`proc`] - Process creation and management
+//! - [`dso`] - Loading shared libraries and plugins
+//! - [`shm`] - Shared memory segments
+//! - [`rmm`] - Allocating inside shared memory
 //! - [`hash`] - Hash table implementation
 //! - [`tables`] - Ordered key-value pairs
 //! - [`strings`] - String manipulation utilities
+//! - [`thread`] - Threads with per-thread pools
+//! - [`locks`] - Mutexes, read-write locks and condition variables
 //! - [`time`] - Time handling and formatting
 //! - [`crypto`] - Cryptographic functions (MD5, SHA1)
 //! - [`base64`] - Base64 encoding/decoding
 //! - [`uri`] - URI parsing and manipulation
+//! - [`escape`] - Percent-encoding, entity escaping and query-string parsing
 //! - [`uuid`] - UUID generation
+//! - `serde` - Serialization of tables, hashes, arrays, URIs, UUIDs and times (`serde` feature)
 //! - [`xml`] - XML parsing utilities
 //!
 //! # Safety
@@ -91,6 +103,9 @@ extern crate alloc;
 
 /// Base64 encoding and decoding
 pub mod base64;
+/// Bucket brigades for streaming data between layers
+#[cfg(feature = "std")]
+pub mod buckets;
 /// Callback function types and utilities
 pub mod callbacks;
 /// Cryptographic operations (encryption, decryption)
@@ -98,6 +113,11 @@ pub mod callbacks;
 pub mod crypto;
 /// Date parsing and formatting utilities
 pub mod date;
+/// Dynamic loading of shared objects
+#[cfg(feature = "std")]
+pub mod dso;
+/// Escaping for URLs, HTML, shells and LDAP
+pub mod escape;
 /// Error types and result handling
 pub mod error;
 /// File I/O operations
@@ -107,6 +127,9 @@ pub mod file;
 pub mod getopt;
 /// Hash table data structure
 pub mod hash;
+/// Thread, process and global locks
+#[cfg(feature = "std")]
+pub mod locks;
 /// MD5 hashing functions
 pub mod md5;
 /// Memory-mapped file support
@@ -117,10 +140,27 @@ pub mod network;
 /// File path manipulation utilities
 #[cfg(feature = "std")]
 pub mod paths;
+/// Polling sockets and files for readiness
+#[cfg(feature = "std")]
+pub mod poll;
 /// Memory pool management
 pub mod pool;
+/// Growable containers stored in pool memory
+pub mod pool_alloc;
+/// Process creation and management
+#[cfg(feature = "std")]
+pub mod proc;
 /// Thread-safe queue data structure
 pub mod queue;
+/// Shared memory segments
+#[cfg(feature = "std")]
+pub mod shm;
+/// Relocatable memory management inside shared memory
+#[cfg(feature = "std")]
+pub mod rmm;
+/// Serde support for APR data structures
+#[cfg(feature = "serde")]
+pub mod serde;
 /// SHA1 hashing functions
 pub mod sha1;
 /// APR status codes
@@ -131,6 +171,9 @@ pub mod strings;
 pub mod strmatch;
 /// APR table data structure (ordered key-value pairs)
 pub mod tables;
+/// APR threads, thread-once and thread-local keys
+#[cfg(feature = "std")]
+pub mod thread;
 /// Time handling and conversion
 pub mod time;
 /// URI parsing and manipulation
@@ -1,48 +1,527 @@
-//! Process and global locking mechanisms
+//! Thread, process and global locking mechanisms
+//!
+//! [`ThreadMutex`], [`ThreadRwLock`] and [`ThreadCond`] wrap APR's in-process
+//! primitives and, like their `std::sync` counterparts, protect the data they
+//! guard and release the lock when the guard is dropped. [`ProcMutex`] and
+//! [`GlobalMutex`] serialize access across processes.
 
 use crate::{pool::Pool, Result};
 use alloc::ffi::CString;
-use core::ffi::c_char;
+use core::cell::UnsafeCell;
 use core::marker::PhantomData;
-use std::path::Path;
+use core::ops::{BitOr, Deref, DerefMut};
 use core::ptr;
+use std::path::Path;
+use std::time::Duration;
+
+/// Flags controlling the behaviour of a [`ThreadMutex`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MutexFlags(u32);
+
+impl MutexFlags {
+    /// Platform-optimal lock behaviour
+    pub const DEFAULT: MutexFlags = MutexFlags(apr_sys::APR_THREAD_MUTEX_DEFAULT);
+    /// Allow the owning thread to lock the mutex again without deadlocking
+    pub const NESTED: MutexFlags = MutexFlags(apr_sys::APR_THREAD_MUTEX_NESTED);
+    /// Disallow recursive locking by the owning thread
+    pub const UNNESTED: MutexFlags = MutexFlags(apr_sys::APR_THREAD_MUTEX_UNNESTED);
+    /// Enable [`ThreadMutex::lock_timeout`] on platforms that need it
+    pub const TIMED: MutexFlags = MutexFlags(apr_sys::APR_THREAD_MUTEX_TIMED);
+
+    /// Whether all flags in `other` are set
+    pub fn contains(self, other: MutexFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for MutexFlags {
+    type Output = MutexFlags;
+
+    fn bitor(self, rhs: MutexFlags) -> MutexFlags {
+        MutexFlags(self.0 | rhs.0)
+    }
+}
+
+fn micros(timeout: Duration) -> apr_sys::apr_interval_time_t {
+    timeout
+        .as_micros()
+        .min(apr_sys::apr_interval_time_t::MAX as u128) as apr_sys::apr_interval_time_t
+}
+
+/// Mutual exclusion lock for threads within this process, protecting a `T`
+///
+/// Nested mutexes (created with [`MutexFlags::NESTED`]) may be locked again
+/// by the thread that already holds them, so their guards only hand out
+/// shared access; use interior mutability for the protected data.
+pub struct ThreadMutex<'pool, T: ?Sized> {
+    raw: *mut apr_sys::apr_thread_mutex_t,
+    nested: bool,
+    _phantom: PhantomData<&'pool Pool<'pool>>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<'pool, T: ?Sized + Send> Send for ThreadMutex<'pool, T> {}
+unsafe impl<'pool, T: ?Sized + Send> Sync for ThreadMutex<'pool, T> {}
+
+impl<'pool, T> ThreadMutex<'pool, T> {
+    /// Create a mutex with the default flags, protecting `value`
+    pub fn new(value: T, pool: &'pool Pool<'pool>) -> Result<Self> {
+        Self::with_flags(value, MutexFlags::DEFAULT, pool)
+    }
+
+    /// Create a mutex with explicit flags, protecting `value`
+    pub fn with_flags(value: T, flags: MutexFlags, pool: &'pool Pool<'pool>) -> Result<Self> {
+        let mut mutex: *mut apr_sys::apr_thread_mutex_t = ptr::null_mut();
+        let status =
+            unsafe { apr_sys::apr_thread_mutex_create(&mut mutex, flags.0, pool.as_mut_ptr()) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+
+        Ok(ThreadMutex {
+            raw: mutex,
+            nested: flags.contains(MutexFlags::NESTED),
+            _phantom: PhantomData,
+            data: UnsafeCell::new(value),
+        })
+    }
+
+    /// Destroy the mutex and return the protected value
+    pub fn into_inner(self) -> T {
+        let this = core::mem::ManuallyDrop::new(self);
+        unsafe {
+            apr_sys::apr_thread_mutex_destroy(this.raw);
+            ptr::read(this.data.get())
+        }
+    }
+}
+
+impl<'pool, T: ?Sized> ThreadMutex<'pool, T> {
+    /// Block until the mutex is acquired
+    pub fn lock(&self) -> Result<ThreadMutexGuard<'_, 'pool, T>> {
+        let status = unsafe { apr_sys::apr_thread_mutex_lock(self.raw) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+        Ok(ThreadMutexGuard::new(self))
+    }
+
+    /// Acquire the mutex if it is free, returning `None` if it is held
+    pub fn try_lock(&self) -> Result<Option<ThreadMutexGuard<'_, 'pool, T>>> {
+        let status = unsafe { apr_sys::apr_thread_mutex_trylock(self.raw) };
+        match status as u32 {
+            x if x == apr_sys::APR_SUCCESS => Ok(Some(ThreadMutexGuard::new(self))),
+            x if x == apr_sys::APR_EBUSY => Ok(None),
+            _ => Err(crate::Error::from_status(status.into())),
+        }
+    }
+
+    /// Wait up to `timeout` for the mutex, returning `None` if it timed out
+    ///
+    /// Some platforms require the mutex to be created with
+    /// [`MutexFlags::TIMED`] for this to work.
+    pub fn lock_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<Option<ThreadMutexGuard<'_, 'pool, T>>> {
+        let status = unsafe { apr_sys::apr_thread_mutex_timedlock(self.raw, micros(timeout)) };
+        match status as u32 {
+            x if x == apr_sys::APR_SUCCESS => Ok(Some(ThreadMutexGuard::new(self))),
+            x if x == apr_sys::APR_TIMEUP => Ok(None),
+            _ => Err(crate::Error::from_status(status.into())),
+        }
+    }
+
+    /// Mutable access to the protected value without locking
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Raw pointer to the underlying APR mutex
+    pub fn as_ptr(&self) -> *const apr_sys::apr_thread_mutex_t {
+        self.raw
+    }
+
+    /// Mutable raw pointer to the underlying APR mutex, for handing to C code
+    pub fn as_mut_ptr(&self) -> *mut apr_sys::apr_thread_mutex_t {
+        self.raw
+    }
+}
+
+impl<'pool, T: ?Sized> Drop for ThreadMutex<'pool, T> {
+    fn drop(&mut self) {
+        unsafe {
+            apr_sys::apr_thread_mutex_destroy(self.raw);
+        }
+    }
+}
+
+impl<'pool, T: ?Sized> core::fmt::Debug for ThreadMutex<'pool, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ThreadMutex")
+            .field("raw", &self.raw)
+            .field("nested", &self.nested)
+            .finish_non_exhaustive()
+    }
+}
+
+/// RAII guard for a locked [`ThreadMutex`]; the mutex is unlocked on drop
+#[must_use = "if unused the mutex will immediately unlock"]
+pub struct ThreadMutexGuard<'a, 'pool, T: ?Sized> {
+    mutex: &'a ThreadMutex<'pool, T>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'a, 'pool, T: ?Sized> ThreadMutexGuard<'a, 'pool, T> {
+    fn new(mutex: &'a ThreadMutex<'pool, T>) -> Self {
+        ThreadMutexGuard {
+            mutex,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl<'a, 'pool, T: ?Sized> Deref for ThreadMutexGuard<'a, 'pool, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<'a, 'pool, T: ?Sized> DerefMut for ThreadMutexGuard<'a, 'pool, T> {
+    /// # Panics
+    ///
+    /// Panics if the mutex is nested, since another guard held by this
+    /// thread may alias the data.
+    fn deref_mut(&mut self) -> &mut T {
+        assert!(
+            !self.mutex.nested,
+            "nested ThreadMutex guards only provide shared access"
+        );
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<'a, 'pool, T: ?Sized> Drop for ThreadMutexGuard<'a, 'pool, T> {
+    fn drop(&mut self) {
+        unsafe {
+            apr_sys::apr_thread_mutex_unlock(self.mutex.raw);
+        }
+    }
+}
+
+/// Reader-writer lock for threads within this process, protecting a `T`
+pub struct ThreadRwLock<'pool, T: ?Sized> {
+    raw: *mut apr_sys::apr_thread_rwlock_t,
+    _phantom: PhantomData<&'pool Pool<'pool>>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<'pool, T: ?Sized + Send> Send for ThreadRwLock<'pool, T> {}
+unsafe impl<'pool, T: ?Sized + Send + Sync> Sync for ThreadRwLock<'pool, T> {}
+
+impl<'pool, T> ThreadRwLock<'pool, T> {
+    /// Create a reader-writer lock protecting `value`
+    pub fn new(value: T, pool: &'pool Pool<'pool>) -> Result<Self> {
+        let mut lock: *mut apr_sys::apr_thread_rwlock_t = ptr::null_mut();
+        let status = unsafe { apr_sys::apr_thread_rwlock_create(&mut lock, pool.as_mut_ptr()) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+
+        Ok(ThreadRwLock {
+            raw: lock,
+            _phantom: PhantomData,
+            data: UnsafeCell::new(value),
+        })
+    }
+
+    /// Destroy the lock and return the protected value
+    pub fn into_inner(self) -> T {
+        let this = core::mem::ManuallyDrop::new(self);
+        unsafe {
+            apr_sys::apr_thread_rwlock_destroy(this.raw);
+            ptr::read(this.data.get())
+        }
+    }
+}
+
+impl<'pool, T: ?Sized> ThreadRwLock<'pool, T> {
+    /// Block until shared read access is acquired
+    pub fn read(&self) -> Result<ThreadRwLockReadGuard<'_, 'pool, T>> {
+        let status = unsafe { apr_sys::apr_thread_rwlock_rdlock(self.raw) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+        Ok(ThreadRwLockReadGuard {
+            lock: self,
+            _not_send: PhantomData,
+        })
+    }
+
+    /// Acquire shared read access if no writer holds the lock
+    pub fn try_read(&self) -> Result<Option<ThreadRwLockReadGuard<'_, 'pool, T>>> {
+        let status = unsafe { apr_sys::apr_thread_rwlock_tryrdlock(self.raw) };
+        match status as u32 {
+            x if x == apr_sys::APR_SUCCESS => Ok(Some(ThreadRwLockReadGuard {
+                lock: self,
+                _not_send: PhantomData,
+            })),
+            x if x == apr_sys::APR_EBUSY => Ok(None),
+            _ => Err(crate::Error::from_status(status.into())),
+        }
+    }
+
+    /// Block until exclusive write access is acquired
+    pub fn write(&self) -> Result<ThreadRwLockWriteGuard<'_, 'pool, T>> {
+        let status = unsafe { apr_sys::apr_thread_rwlock_wrlock(self.raw) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+        Ok(ThreadRwLockWriteGuard {
+            lock: self,
+            _not_send: PhantomData,
+        })
+    }
+
+    /// Acquire exclusive write access if the lock is free
+    pub fn try_write(&self) -> Result<Option<ThreadRwLockWriteGuard<'_, 'pool, T>>> {
+        let status = unsafe { apr_sys::apr_thread_rwlock_trywrlock(self.raw) };
+        match status as u32 {
+            x if x == apr_sys::APR_SUCCESS => Ok(Some(ThreadRwLockWriteGuard {
+                lock: self,
+                _not_send: PhantomData,
+            })),
+            x if x == apr_sys::APR_EBUSY => Ok(None),
+            _ => Err(crate::Error::from_status(status.into())),
+        }
+    }
+
+    /// Mutable access to the protected value without locking
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Raw pointer to the underlying APR lock
+    pub fn as_ptr(&self) -> *const apr_sys::apr_thread_rwlock_t {
+        self.raw
+    }
+
+    /// Mutable raw pointer to the underlying APR lock, for handing to C code
+    pub fn as_mut_ptr(&self) -> *mut apr_sys::apr_thread_rwlock_t {
+        self.raw
+    }
+}
+
+impl<'pool, T: ?Sized> Drop for ThreadRwLock<'pool, T> {
+    fn drop(&mut self) {
+        unsafe {
+            apr_sys::apr_thread_rwlock_destroy(self.raw);
+        }
+    }
+}
 
+impl<'pool, T: ?Sized> core::fmt::Debug for ThreadRwLock<'pool, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ThreadRwLock")
+            .field("raw", &self.raw)
+            .finish_non_exhaustive()
+    }
+}
+
+/// RAII guard for shared access to a [`ThreadRwLock`]
+#[must_use = "if unused the lock will immediately unlock"]
+pub struct ThreadRwLockReadGuard<'a, 'pool, T: ?Sized> {
+    lock: &'a ThreadRwLock<'pool, T>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'a, 'pool, T: ?Sized> Deref for ThreadRwLockReadGuard<'a, 'pool, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, 'pool, T: ?Sized> Drop for ThreadRwLockReadGuard<'a, 'pool, T> {
+    fn drop(&mut self) {
+        unsafe {
+            apr_sys::apr_thread_rwlock_unlock(self.lock.raw);
+        }
+    }
+}
+
+/// RAII guard for exclusive access to a [`ThreadRwLock`]
+#[must_use = "if unused the lock will immediately unlock"]
+pub struct ThreadRwLockWriteGuard<'a, 'pool, T: ?Sized> {
+    lock: &'a ThreadRwLock<'pool, T>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'a, 'pool, T: ?Sized> Deref for ThreadRwLockWriteGuard<'a, 'pool, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'a, 'pool, T: ?Sized> DerefMut for ThreadRwLockWriteGuard<'a, 'pool, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<'a, 'pool, T: ?Sized> Drop for ThreadRwLockWriteGuard<'a, 'pool, T> {
+    fn drop(&mut self) {
+        unsafe {
+            apr_sys::apr_thread_rwlock_unlock(self.lock.raw);
+        }
+    }
+}
+
+/// Condition variable for use with a [`ThreadMutex`]
+pub struct ThreadCond<'pool> {
+    raw: *mut apr_sys::apr_thread_cond_t,
+    _phantom: PhantomData<&'pool Pool<'pool>>,
+}
+
+unsafe impl<'pool> Send for ThreadCond<'pool> {}
+unsafe impl<'pool> Sync for ThreadCond<'pool> {}
+
+impl<'pool> ThreadCond<'pool> {
+    /// Create a condition variable
+    pub fn new(pool: &'pool Pool<'pool>) -> Result<Self> {
+        let mut cond: *mut apr_sys::apr_thread_cond_t = ptr::null_mut();
+        let status = unsafe { apr_sys::apr_thread_cond_create(&mut cond, pool.as_mut_ptr()) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+
+        Ok(ThreadCond {
+            raw: cond,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Atomically release the guarded mutex and block until woken
+    ///
+    /// The mutex is re-acquired before returning. As with any condition
+    /// variable, spurious wakeups are possible.
+    pub fn wait<'a, 'm, T: ?Sized>(
+        &self,
+        guard: ThreadMutexGuard<'a, 'm, T>,
+    ) -> Result<ThreadMutexGuard<'a, 'm, T>> {
+        let status = unsafe { apr_sys::apr_thread_cond_wait(self.raw, guard.mutex.raw) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+        Ok(guard)
+    }
+
+    /// Like [`ThreadCond::wait`], but gives up after `timeout`
+    ///
+    /// The returned flag is `true` if the wait timed out.
+    pub fn wait_timeout<'a, 'm, T: ?Sized>(
+        &self,
+        guard: ThreadMutexGuard<'a, 'm, T>,
+        timeout: Duration,
+    ) -> Result<(ThreadMutexGuard<'a, 'm, T>, bool)> {
+        let status = unsafe {
+            apr_sys::apr_thread_cond_timedwait(self.raw, guard.mutex.raw, micros(timeout))
+        };
+        match status as u32 {
+            x if x == apr_sys::APR_SUCCESS => Ok((guard, false)),
+            x if x == apr_sys::APR_TIMEUP => Ok((guard, true)),
+            _ => Err(crate::Error::from_status(status.into())),
+        }
+    }
+
+    /// Wake one thread blocked on this condition variable
+    pub fn notify_one(&self) -> Result<()> {
+        let status = unsafe { apr_sys::apr_thread_cond_signal(self.raw) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+        Ok(())
+    }
+
+    /// Wake all threads blocked on this condition variable
+    pub fn notify_all(&self) -> Result<()> {
+        let status = unsafe { apr_sys::apr_thread_cond_broadcast(self.raw) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+        Ok(())
+    }
+
+    /// Raw pointer to the underlying APR condition variable
+    pub fn as_ptr(&self) -> *const apr_sys::apr_thread_cond_t {
+        self.raw
+    }
+
+    /// Mutable raw pointer to the underlying APR condition variable
+    pub fn as_mut_ptr(&self) -> *mut apr_sys::apr_thread_cond_t {
+        self.raw
+    }
+}
+
+impl<'pool> Drop for ThreadCond<'pool> {
+    fn drop(&mut self) {
+        unsafe {
+            apr_sys::apr_thread_cond_destroy(self.raw);
+        }
+    }
+}
+
+/// Mutex shared between processes
 #[repr(transparent)]
 pub struct ProcMutex<'a> {
     raw: *mut apr_sys::apr_proc_mutex_t,
     _phantom: PhantomData<&'a Pool<'a>>,
 }
 
+/// Mutex shared between both threads and processes
 #[repr(transparent)]
 pub struct GlobalMutex<'a> {
     raw: *mut apr_sys::apr_global_mutex_t,
     _phantom: PhantomData<&'a Pool<'a>>,
 }
 
+/// Locking mechanism used by [`ProcMutex`] and [`GlobalMutex`]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LockMech {
+    /// `fcntl()` record locking
     FcntlSerialize,
+    /// `flock()` file locking
     FLockSerialize,
+    /// System V semaphores
     SysVSem,
+    /// POSIX semaphores
     PosixSem,
+    /// Process-shared pthread mutexes
     ProcPthread,
+    /// Platform default mechanism
     Default,
 }
 
 impl From<LockMech> for apr_sys::apr_lockmech_e {
     fn from(mech: LockMech) -> Self {
         match mech {
-            LockMech::FcntlSerialize => apr_sys::APR_LOCK_FCNTL,
-            LockMech::FLockSerialize => apr_sys::APR_LOCK_FLOCK,
-            LockMech::SysVSem => apr_sys::APR_LOCK_SYSVSEM,
-            LockMech::PosixSem => apr_sys::APR_LOCK_POSIXSEM,
-            LockMech::ProcPthread => apr_sys::APR_LOCK_PROC_PTHREAD,
-            LockMech::Default => apr_sys::APR_LOCK_DEFAULT,
+            LockMech::FcntlSerialize => apr_sys::apr_lockmech_e_APR_LOCK_FCNTL,
+            LockMech::FLockSerialize => apr_sys::apr_lockmech_e_APR_LOCK_FLOCK,
+            LockMech::SysVSem => apr_sys::apr_lockmech_e_APR_LOCK_SYSVSEM,
+            LockMech::PosixSem => apr_sys::apr_lockmech_e_APR_LOCK_POSIXSEM,
+            LockMech::ProcPthread => apr_sys::apr_lockmech_e_APR_LOCK_PROC_PTHREAD,
+            LockMech::Default => apr_sys::apr_lockmech_e_APR_LOCK_DEFAULT,
         }
     }
 }
 
 impl<'a> ProcMutex<'a> {
+    /// Create a process mutex, optionally backed by the lock file `fname`
     pub fn new(fname: Option<&Path>, mech: LockMech, pool: &'a Pool<'a>) -> Result<Self> {
         let mut mutex: *mut apr_sys::apr_proc_mutex_t = ptr::null_mut();
 
@@ -52,7 +531,7 @@ impl<'a> ProcMutex<'a> {
                 .ok_or_else(|| crate::Error::from_status((apr_sys::APR_EINVAL as i32).into()))?;
             Some(
                 CString::new(path_str)
-                    .map_err(|_| crate::Error::from_status((apr_sys::APR_EINVAL as i32).into()))?
+                    .map_err(|_| crate::Error::from_status((apr_sys::APR_EINVAL as i32).into()))?,
             )
         } else {
             None
@@ -74,6 +553,7 @@ impl<'a> ProcMutex<'a> {
         })
     }
 
+    /// Block until the mutex is acquired
     pub fn lock(&mut self) -> Result<()> {
         let status = unsafe { apr_sys::apr_proc_mutex_lock(self.raw) };
 
@@ -83,6 +563,7 @@ impl<'a> ProcMutex<'a> {
         Ok(())
     }
 
+    /// Acquire the mutex if it is free, returning whether it was acquired
     pub fn trylock(&mut self) -> Result<bool> {
         let status = unsafe { apr_sys::apr_proc_mutex_trylock(self.raw) };
 
@@ -93,6 +574,7 @@ impl<'a> ProcMutex<'a> {
         }
     }
 
+    /// Release the mutex
     pub fn unlock(&mut self) -> Result<()> {
         let status = unsafe { apr_sys::apr_proc_mutex_unlock(self.raw) };
 
@@ -102,10 +584,12 @@ impl<'a> ProcMutex<'a> {
         Ok(())
     }
 
+    /// Raw pointer to the underlying APR mutex
     pub fn as_ptr(&self) -> *const apr_sys::apr_proc_mutex_t {
         self.raw
     }
 
+    /// Mutable raw pointer to the underlying APR mutex
     pub fn as_mut_ptr(&mut self) -> *mut apr_sys::apr_proc_mutex_t {
         self.raw
     }
@@ -120,6 +604,7 @@ impl<'a> Drop for ProcMutex<'a> {
 }
 
 impl<'a> GlobalMutex<'a> {
+    /// Create a global mutex, optionally backed by the lock file `fname`
     pub fn new(fname: Option<&Path>, mech: LockMech, pool: &'a Pool<'a>) -> Result<Self> {
         let mut mutex: *mut apr_sys::apr_global_mutex_t = ptr::null_mut();
 
@@ -129,7 +614,7 @@ impl<'a> GlobalMutex<'a> {
                 .ok_or_else(|| crate::Error::from_status((apr_sys::APR_EINVAL as i32).into()))?;
             Some(
                 CString::new(path_str)
-                    .map_err(|_| crate::Error::from_status((apr_sys::APR_EINVAL as i32).into()))?
+                    .map_err(|_| crate::Error::from_status((apr_sys::APR_EINVAL as i32).into()))?,
             )
         } else {
             None
@@ -151,6 +636,7 @@ impl<'a> GlobalMutex<'a> {
         })
     }
 
+    /// Re-open the mutex in a child process after `fork()`
     pub fn child_init(&mut self, fname: Option<&Path>, pool: &Pool<'_>) -> Result<()> {
         let c_fname = if let Some(path) = fname {
             let path_str = path
@@ -158,7 +644,7 @@ impl<'a> GlobalMutex<'a> {
                 .ok_or_else(|| crate::Error::from_status((apr_sys::APR_EINVAL as i32).into()))?;
             Some(
                 CString::new(path_str)
-                    .map_err(|_| crate::Error::from_status((apr_sys::APR_EINVAL as i32).into()))?
+                    .map_err(|_| crate::Error::from_status((apr_sys::APR_EINVAL as i32).into()))?,
             )
         } else {
             None
@@ -176,6 +662,7 @@ impl<'a> GlobalMutex<'a> {
         Ok(())
     }
 
+    /// Block until the mutex is acquired
     pub fn lock(&mut self) -> Result<()> {
         let status = unsafe { apr_sys::apr_global_mutex_lock(self.raw) };
 
@@ -185,6 +672,7 @@ impl<'a> GlobalMutex<'a> {
         Ok(())
     }
 
+    /// Acquire the mutex if it is free, returning whether it was acquired
     pub fn trylock(&mut self) -> Result<bool> {
         let status = unsafe { apr_sys::apr_global_mutex_trylock(self.raw) };
 
@@ -195,6 +683,7 @@ impl<'a> GlobalMutex<'a> {
         }
     }
 
+    /// Release the mutex
     pub fn unlock(&mut self) -> Result<()> {
         let status = unsafe { apr_sys::apr_global_mutex_unlock(self.raw) };
 
@@ -204,10 +693,12 @@ impl<'a> GlobalMutex<'a> {
         Ok(())
     }
 
+    /// Raw pointer to the underlying APR mutex
     pub fn as_ptr(&self) -> *const apr_sys::apr_global_mutex_t {
         self.raw
     }
 
+    /// Mutable raw pointer to the underlying APR mutex
     pub fn as_mut_ptr(&mut self) -> *mut apr_sys::apr_global_mutex_t {
         self.raw
     }
@@ -221,46 +712,32 @@ impl<'a> Drop for GlobalMutex<'a> {
     }
 }
 
-pub fn proc_mutex_lockfile(mutex: &ProcMutex) -> Result<String> {
-    let mut lockfile: *const c_char = ptr::null();
-
-    let status = unsafe { apr_sys::apr_proc_mutex_lockfile(mutex.raw, &mut lockfile) };
-
-    if status != apr_sys::APR_SUCCESS as i32 {
-        return Err(crate::Error::from_status(status.into()));
-    }
-
-    if lockfile.is_null() {
-        return Ok(String::new());
-    }
-
+/// Name of the file backing the mutex, if its mechanism uses one
+pub fn proc_mutex_lockfile(mutex: &ProcMutex) -> Option<String> {
     unsafe {
-        Ok(core::ffi::CStr::from_ptr(lockfile)
-            .to_string_lossy()
-            .into_owned())
+        let lockfile = apr_sys::apr_proc_mutex_lockfile(mutex.raw);
+        if lockfile.is_null() {
+            None
+        } else {
+            Some(
+                core::ffi::CStr::from_ptr(lockfile)
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        }
     }
 }
 
-pub fn proc_mutex_name(mutex: &ProcMutex) -> Result<String> {
-    let mut name: *const c_char = ptr::null();
-
-    let status = unsafe { apr_sys::apr_proc_mutex_name(mutex.raw, &mut name) };
-
-    if status != apr_sys::APR_SUCCESS as i32 {
-        return Err(crate::Error::from_status(status.into()));
-    }
-
-    if name.is_null() {
-        return Ok(String::new());
-    }
-
+/// Name of the locking mechanism used by the mutex
+pub fn proc_mutex_name(mutex: &ProcMutex) -> String {
     unsafe {
-        Ok(core::ffi::CStr::from_ptr(name)
+        core::ffi::CStr::from_ptr(apr_sys::apr_proc_mutex_name(mutex.raw))
             .to_string_lossy()
-            .into_owned())
+            .into_owned()
     }
 }
 
+/// Name of the platform's default process locking mechanism
 pub fn proc_mutex_defname() -> String {
     unsafe {
         let name = apr_sys::apr_proc_mutex_defname();
@@ -277,7 +754,6 @@ pub fn proc_mutex_defname() -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::path::PathBuf;
 
     #[test]
     fn test_lock_mech_conversion() {
@@ -289,6 +765,92 @@ mod tests {
         let _pthread: apr_sys::apr_lockmech_e = LockMech::ProcPthread.into();
     }
 
+    #[test]
+    fn test_thread_mutex_guard() {
+        let pool = Pool::new();
+        let mutex = ThreadMutex::new(1, &pool).unwrap();
+        {
+            let mut guard = mutex.lock().unwrap();
+            *guard += 1;
+            assert!(mutex.try_lock().unwrap().is_none());
+        }
+        assert_eq!(*mutex.try_lock().unwrap().unwrap(), 2);
+        assert_eq!(mutex.into_inner(), 2);
+    }
+
+    #[test]
+    fn test_thread_mutex_nested() {
+        let pool = Pool::new();
+        let mutex = ThreadMutex::with_flags(5, MutexFlags::NESTED, &pool).unwrap();
+        let outer = mutex.lock().unwrap();
+        let inner = mutex.lock().unwrap();
+        assert_eq!(*outer + *inner, 10);
+    }
+
+    #[test]
+    fn test_thread_mutex_timed() {
+        let pool = Pool::new();
+        let mutex = ThreadMutex::with_flags((), MutexFlags::TIMED, &pool).unwrap();
+        let _guard = mutex.lock().unwrap();
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                let res = mutex.lock_timeout(Duration::from_millis(10)).unwrap();
+                assert!(res.is_none());
+            });
+        });
+    }
+
+    #[test]
+    fn test_thread_mutex_across_threads() {
+        let pool = Pool::new();
+        let mutex = ThreadMutex::new(0u32, &pool).unwrap();
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..100 {
+                        *mutex.lock().unwrap() += 1;
+                    }
+                });
+            }
+        });
+        assert_eq!(*mutex.lock().unwrap(), 400);
+    }
+
+    #[test]
+    fn test_thread_rwlock() {
+        let pool = Pool::new();
+        let lock = ThreadRwLock::new(vec![1, 2], &pool).unwrap();
+        {
+            let r1 = lock.read().unwrap();
+            let r2 = lock.try_read().unwrap().unwrap();
+            assert_eq!(r1.len() + r2.len(), 4);
+            assert!(lock.try_write().unwrap().is_none());
+        }
+        lock.write().unwrap().push(3);
+        assert_eq!(*lock.read().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_thread_cond() {
+        let pool = Pool::new();
+        let ready = ThreadMutex::new(false, &pool).unwrap();
+        let cond = ThreadCond::new(&pool).unwrap();
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                *ready.lock().unwrap() = true;
+                cond.notify_all().unwrap();
+            });
+            let mut guard = ready.lock().unwrap();
+            while !*guard {
+                guard = cond.wait(guard).unwrap();
+            }
+        });
+
+        let guard = ready.lock().unwrap();
+        let (_guard, timed_out) = cond.wait_timeout(guard, Duration::from_millis(1)).unwrap();
+        assert!(timed_out);
+    }
+
     #[test]
     fn test_proc_mutex_creation() {
         let pool = Pool::new();
@@ -324,7 +886,7 @@ mod tests {
             let _ = mutex.trylock();
             
             mutex.unlock().unwrap();
-        }
+        };
     }
 
     #[test]
@@ -362,7 +924,7 @@ mod tests {
             let _ = proc_mutex_lockfile(&mutex);
             
             // Just verify they don't crash
-        }
+        };
     }
 
     #[test]
@@ -375,6 +937,13 @@ mod tests {
                 mutex.lock().unwrap();
                 mutex.unlock().unwrap();
             }
-        }
+        };
+    }
+
+    #[test]
+    fn test_proc_mutex_name() {
+        let pool = Pool::new();
+        let mutex = ProcMutex::new(None, LockMech::Default, &pool).unwrap();
+        assert!(!proc_mutex_name(&mutex).is_empty());
     }
 }
\ No newline at end of file
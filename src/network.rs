@@ -6,7 +6,7 @@ use core::ffi::c_char;
 use core::ffi::CStr;
 use core::marker::PhantomData;
 use core::ptr;
-use std::net::{Ipv4Addr, Ipv6Addr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::time::Duration;
 
 /// Network socket
@@ -32,6 +32,8 @@ pub enum SocketFamily {
     Inet6,
     /// Unix domain socket
     Unix,
+    /// Unspecified; let the resolver pick
+    Unspec,
 }
 
 impl From<SocketFamily> for i32 {
@@ -40,10 +42,24 @@ impl From<SocketFamily> for i32 {
             SocketFamily::Inet => apr_sys::APR_INET as i32,
             SocketFamily::Inet6 => apr_sys::APR_INET6 as i32,
             SocketFamily::Unix => apr_sys::APR_UNIX as i32,
+            SocketFamily::Unspec => apr_sys::APR_UNSPEC as i32,
         }
     }
 }
 
+/// Flags for [`SockAddr::resolve`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResolveFlags(i32);
+
+impl ResolveFlags {
+    /// Default resolver behaviour
+    pub const NONE: ResolveFlags = ResolveFlags(0);
+    /// With [`SocketFamily::Unspec`], try IPv4 before IPv6
+    pub const IPV4_ADDR_OK: ResolveFlags = ResolveFlags(apr_sys::APR_IPV4_ADDR_OK as i32);
+    /// With [`SocketFamily::Unspec`], try IPv6 before IPv4
+    pub const IPV6_ADDR_OK: ResolveFlags = ResolveFlags(apr_sys::APR_IPV6_ADDR_OK as i32);
+}
+
 /// Socket type
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SocketType {
@@ -200,6 +216,101 @@ impl<'a> SockAddr<'a> {
         })
     }
 
+    /// Resolve `hostname` into the list of matching socket addresses
+    ///
+    /// `hostname` may also be a numeric IPv4 or IPv6 address.
+    pub fn resolve(
+        hostname: &str,
+        family: SocketFamily,
+        port: u16,
+        flags: ResolveFlags,
+        pool: &'a Pool<'a>,
+    ) -> Result<SockAddrs<'a>> {
+        let mut sockaddr: *mut apr_sys::apr_sockaddr_t = ptr::null_mut();
+
+        let c_host = CString::new(hostname)
+            .map_err(|_| crate::Error::from_status((apr_sys::APR_EINVAL as i32).into()))?;
+
+        let status = unsafe {
+            apr_sys::apr_sockaddr_info_get(
+                &mut sockaddr,
+                c_host.as_ptr(),
+                family.into(),
+                port as apr_sys::apr_port_t,
+                flags.0,
+                pool.as_mut_ptr(),
+            )
+        };
+
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+
+        Ok(SockAddrs {
+            next: sockaddr,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Create a socket address from a standard library one
+    pub fn from_socket_addr(addr: SocketAddr, pool: &'a Pool<'a>) -> Result<Self> {
+        match addr {
+            SocketAddr::V4(v4) => Self::new_inet(*v4.ip(), v4.port(), pool),
+            SocketAddr::V6(v6) => Self::new_inet6(*v6.ip(), v6.port(), pool),
+        }
+    }
+
+    /// Convert to a standard library socket address
+    ///
+    /// Returns `None` for address families other than IPv4 and IPv6.
+    pub fn to_socket_addr(&self) -> Option<SocketAddr> {
+        let ip = self.ip_string().ok()?;
+        // Link-local IPv6 addresses may carry a "%scope" suffix.
+        let ip: IpAddr = ip.split('%').next()?.parse().ok()?;
+        Some(SocketAddr::new(ip, self.port()))
+    }
+
+    /// The numeric IP address, e.g. `"127.0.0.1"` or `"::1"`
+    pub fn ip_string(&self) -> Result<String> {
+        let mut buf = [0 as c_char; 128];
+        let status =
+            unsafe { apr_sys::apr_sockaddr_ip_getbuf(buf.as_mut_ptr(), buf.len(), self.raw) };
+
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+
+        Ok(unsafe { CStr::from_ptr(buf.as_ptr()) }
+            .to_string_lossy()
+            .into_owned())
+    }
+
+    /// Look up the host name for this address
+    pub fn reverse_lookup(&self) -> Result<String> {
+        let mut hostname: *mut c_char = ptr::null_mut();
+        let status = unsafe { apr_sys::apr_getnameinfo(&mut hostname, self.raw, 0) };
+
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+
+        Ok(unsafe { CStr::from_ptr(hostname) }
+            .to_string_lossy()
+            .into_owned())
+    }
+
+    /// Whether both addresses refer to the same IP address
+    ///
+    /// Ports are not compared.
+    pub fn equal(&self, other: &SockAddr) -> bool {
+        unsafe { apr_sys::apr_sockaddr_equal(self.raw, other.raw) != 0 }
+    }
+
+    /// Whether this is the wildcard address (`0.0.0.0` or `::`)
+    pub fn is_wildcard(&self) -> bool {
+        unsafe { apr_sys::apr_sockaddr_is_wildcard(self.raw) != 0 }
+    }
+
     /// Get the port number
     pub fn port(&self) -> u16 {
         unsafe { (*self.raw).port }
@@ -221,6 +332,72 @@ impl<'a> SockAddr<'a> {
     }
 }
 
+/// Iterator over the addresses returned by [`SockAddr::resolve`]
+pub struct SockAddrs<'a> {
+    next: *mut apr_sys::apr_sockaddr_t,
+    _phantom: PhantomData<&'a Pool<'a>>,
+}
+
+impl<'a> Iterator for SockAddrs<'a> {
+    type Item = SockAddr<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next.is_null() {
+            return None;
+        }
+        let current = self.next;
+        self.next = unsafe { (*current).next };
+        Some(SockAddr {
+            raw: current,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+/// Host, scope ID and port parsed by [`parse_addr_port`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddrPort {
+    /// Host name or address, if one was given
+    pub host: Option<String>,
+    /// IPv6 scope ID, if one was given
+    pub scope_id: Option<String>,
+    /// Port number, if one was given
+    pub port: Option<u16>,
+}
+
+/// Parse a `host`, `host:port`, `[ipv6]:port` or bare `port` string
+pub fn parse_addr_port(input: &str, pool: &Pool<'_>) -> Result<AddrPort> {
+    let c_input = CString::new(input)
+        .map_err(|_| crate::Error::from_status((apr_sys::APR_EINVAL as i32).into()))?;
+    let mut addr: *mut c_char = ptr::null_mut();
+    let mut scope_id: *mut c_char = ptr::null_mut();
+    let mut port: apr_sys::apr_port_t = 0;
+
+    let status = unsafe {
+        apr_sys::apr_parse_addr_port(
+            &mut addr,
+            &mut scope_id,
+            &mut port,
+            c_input.as_ptr(),
+            pool.as_mut_ptr(),
+        )
+    };
+
+    if status != apr_sys::APR_SUCCESS as i32 {
+        return Err(crate::Error::from_status(status.into()));
+    }
+
+    let to_string = |p: *mut c_char| {
+        (!p.is_null()).then(|| unsafe { CStr::from_ptr(p) }.to_string_lossy().into_owned())
+    };
+
+    Ok(AddrPort {
+        host: to_string(addr),
+        scope_id: to_string(scope_id),
+        port: (port != 0).then_some(port),
+    })
+}
+
 impl<'a> Socket<'a> {
     /// Create a new socket
     pub fn new(
@@ -499,7 +676,7 @@ mod tests {
 
         let addr = SockAddr::new_inet(Ipv4Addr::new(127, 0, 0, 1), 8080, &pool).unwrap();
         assert_eq!(addr.port(), 8080);
-        assert_eq!(addr.family(), SocketFamily::Inet.into());
+        assert_eq!(addr.family(), i32::from(SocketFamily::Inet));
     }
 
     #[test]
@@ -507,7 +684,78 @@ mod tests {
         let pool = Pool::new();
 
         let addr = SockAddr::new_any(0, SocketFamily::Inet, &pool).unwrap();
-        assert_eq!(addr.family(), SocketFamily::Inet.into());
+        assert_eq!(addr.family(), i32::from(SocketFamily::Inet));
+    }
+
+    #[test]
+    fn test_sockaddr_resolve() {
+        let pool = Pool::new();
+
+        let addrs: Vec<_> = SockAddr::resolve(
+            "127.0.0.1",
+            SocketFamily::Inet,
+            80,
+            ResolveFlags::NONE,
+            &pool,
+        )
+        .unwrap()
+        .collect();
+        assert_eq!(addrs.len(), 1);
+        assert_eq!(addrs[0].ip_string().unwrap(), "127.0.0.1");
+        assert_eq!(addrs[0].port(), 80);
+
+        let localhost = SockAddr::resolve(
+            "localhost",
+            SocketFamily::Unspec,
+            80,
+            ResolveFlags::IPV4_ADDR_OK,
+            &pool,
+        )
+        .unwrap();
+        assert!(localhost.count() >= 1);
+    }
+
+    #[test]
+    fn test_sockaddr_equal_and_wildcard() {
+        let pool = Pool::new();
+
+        let a = SockAddr::new_inet(Ipv4Addr::new(127, 0, 0, 1), 80, &pool).unwrap();
+        let b = SockAddr::new_inet(Ipv4Addr::new(127, 0, 0, 1), 443, &pool).unwrap();
+        let any = SockAddr::new_any(0, SocketFamily::Inet, &pool).unwrap();
+        assert!(a.equal(&b));
+        assert!(!a.equal(&any));
+        assert!(any.is_wildcard());
+        assert!(!a.is_wildcard());
+    }
+
+    #[test]
+    fn test_sockaddr_std_conversion() {
+        let pool = Pool::new();
+
+        let std_addr: SocketAddr = "[::1]:8080".parse().unwrap();
+        let addr = SockAddr::from_socket_addr(std_addr, &pool).unwrap();
+        assert_eq!(addr.ip_string().unwrap(), "::1");
+        assert_eq!(addr.to_socket_addr(), Some(std_addr));
+    }
+
+    #[test]
+    fn test_parse_addr_port() {
+        let pool = Pool::new();
+
+        let parsed = parse_addr_port("example.com:8080", &pool).unwrap();
+        assert_eq!(parsed.host.as_deref(), Some("example.com"));
+        assert_eq!(parsed.scope_id, None);
+        assert_eq!(parsed.port, Some(8080));
+
+        let parsed = parse_addr_port("[fe80::1%eth0]:443", &pool).unwrap();
+        assert_eq!(parsed.host.as_deref(), Some("fe80::1"));
+        assert_eq!(parsed.scope_id.as_deref(), Some("eth0"));
+        assert_eq!(parsed.port, Some(443));
+
+        let parsed = parse_addr_port("localhost", &pool).unwrap();
+        assert_eq!(parsed.port, None);
+
+        assert!(parse_addr_port("host:notaport", &pool).is_err());
     }
 
     #[test]
@@ -0,0 +1,723 @@
+//! Waiting on many sockets and files at once
+//!
+//! [`Pollset`] wraps `apr_pollset_t` and returns the ready descriptors from
+//! each call to [`Pollset::poll`]; [`Pollcb`] wraps `apr_pollcb_t` and instead
+//! invokes a callback for every ready descriptor. Both accept
+//! [`Socket`](crate::network::Socket)s and [`File`]s, and hand back references
+//! to the registered objects rather than raw APR descriptors.
+
+use crate::file::File;
+use crate::network::Socket;
+use crate::{pool::Pool, Result};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::ffi::{c_void, CStr};
+use core::marker::PhantomData;
+use core::ops::BitOr;
+use core::ptr;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::time::Duration;
+
+/// Events that can be requested from, or reported by, a poll
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PollEvents(i16);
+
+impl PollEvents {
+    /// No events
+    pub const NONE: PollEvents = PollEvents(0);
+    /// Data can be read without blocking
+    pub const IN: PollEvents = PollEvents(apr_sys::APR_POLLIN as i16);
+    /// Urgent data can be read
+    pub const PRI: PollEvents = PollEvents(apr_sys::APR_POLLPRI as i16);
+    /// Data can be written without blocking
+    pub const OUT: PollEvents = PollEvents(apr_sys::APR_POLLOUT as i16);
+    /// An error occurred on the descriptor
+    pub const ERR: PollEvents = PollEvents(apr_sys::APR_POLLERR as i16);
+    /// The peer hung up
+    pub const HUP: PollEvents = PollEvents(apr_sys::APR_POLLHUP as i16);
+    /// The descriptor is invalid
+    pub const NVAL: PollEvents = PollEvents(apr_sys::APR_POLLNVAL as i16);
+
+    /// Whether all events in `other` are set
+    pub fn contains(self, other: PollEvents) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether no events are set
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether the descriptor is readable
+    pub fn is_readable(self) -> bool {
+        self.contains(PollEvents::IN)
+    }
+
+    /// Whether the descriptor is writable
+    pub fn is_writable(self) -> bool {
+        self.contains(PollEvents::OUT)
+    }
+}
+
+impl BitOr for PollEvents {
+    type Output = PollEvents;
+
+    fn bitor(self, rhs: PollEvents) -> PollEvents {
+        PollEvents(self.0 | rhs.0)
+    }
+}
+
+/// Backend used to implement a [`Pollset`] or [`Pollcb`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollMethod {
+    /// Best method available on this platform
+    Default,
+    /// `select()`
+    Select,
+    /// BSD `kqueue()`
+    Kqueue,
+    /// Solaris event ports
+    Port,
+    /// Linux `epoll()`
+    Epoll,
+    /// `poll()`
+    Poll,
+    /// z/OS asynchronous message queues
+    AioMsgQ,
+}
+
+impl From<PollMethod> for apr_sys::apr_pollset_method_e {
+    fn from(method: PollMethod) -> Self {
+        match method {
+            PollMethod::Default => apr_sys::apr_pollset_method_e_APR_POLLSET_DEFAULT,
+            PollMethod::Select => apr_sys::apr_pollset_method_e_APR_POLLSET_SELECT,
+            PollMethod::Kqueue => apr_sys::apr_pollset_method_e_APR_POLLSET_KQUEUE,
+            PollMethod::Port => apr_sys::apr_pollset_method_e_APR_POLLSET_PORT,
+            PollMethod::Epoll => apr_sys::apr_pollset_method_e_APR_POLLSET_EPOLL,
+            PollMethod::Poll => apr_sys::apr_pollset_method_e_APR_POLLSET_POLL,
+            PollMethod::AioMsgQ => apr_sys::apr_pollset_method_e_APR_POLLSET_AIO_MSGQ,
+        }
+    }
+}
+
+/// Flags used when creating a [`Pollset`] or [`Pollcb`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollsetFlags(u32);
+
+impl PollsetFlags {
+    /// No flags
+    pub const NONE: PollsetFlags = PollsetFlags(0);
+    /// Allow descriptors to be added and removed while another thread polls
+    pub const THREADSAFE: PollsetFlags = PollsetFlags(apr_sys::APR_POLLSET_THREADSAFE);
+    /// Allow a blocked poll to be interrupted with `wakeup()`
+    pub const WAKEABLE: PollsetFlags = PollsetFlags(apr_sys::APR_POLLSET_WAKEABLE);
+    /// Fail rather than fall back to the default method
+    pub const NODEFAULT: PollsetFlags = PollsetFlags(apr_sys::APR_POLLSET_NODEFAULT);
+
+    /// Whether all flags in `other` are set
+    pub fn contains(self, other: PollsetFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for PollsetFlags {
+    type Output = PollsetFlags;
+
+    fn bitor(self, rhs: PollsetFlags) -> PollsetFlags {
+        PollsetFlags(self.0 | rhs.0)
+    }
+}
+
+/// An object that can be registered with a [`Pollset`] or [`Pollcb`]
+#[derive(Clone, Copy)]
+pub enum Pollable<'a> {
+    /// A network socket
+    Socket(&'a Socket<'a>),
+    /// A file or pipe
+    File(&'a File),
+}
+
+impl<'a> From<&'a Socket<'a>> for Pollable<'a> {
+    fn from(socket: &'a Socket<'a>) -> Self {
+        Pollable::Socket(socket)
+    }
+}
+
+impl<'a> From<&'a File> for Pollable<'a> {
+    fn from(file: &'a File) -> Self {
+        Pollable::File(file)
+    }
+}
+
+impl<'a> Pollable<'a> {
+    fn pollfd(self, events: PollEvents, pool: *mut apr_sys::apr_pool_t) -> apr_sys::apr_pollfd_t {
+        let mut pfd: apr_sys::apr_pollfd_t = unsafe { core::mem::zeroed() };
+        pfd.p = pool;
+        pfd.reqevents = events.0;
+        match self {
+            Pollable::Socket(socket) => {
+                pfd.desc_type = apr_sys::apr_datatype_e_APR_POLL_SOCKET;
+                pfd.desc.s = socket.as_ptr() as *mut apr_sys::apr_socket_t;
+                pfd.client_data = socket as *const Socket as *mut c_void;
+            }
+            Pollable::File(file) => {
+                pfd.desc_type = apr_sys::apr_datatype_e_APR_POLL_FILE;
+                pfd.desc.f = file.as_mut_ptr();
+                pfd.client_data = file as *const File as *mut c_void;
+            }
+        }
+        pfd
+    }
+
+    /// Recover the registered object from a descriptor built by `pollfd`.
+    unsafe fn from_pollfd(pfd: &apr_sys::apr_pollfd_t) -> Self {
+        if pfd.desc_type == apr_sys::apr_datatype_e_APR_POLL_SOCKET {
+            Pollable::Socket(&*(pfd.client_data as *const Socket<'a>))
+        } else {
+            Pollable::File(&*(pfd.client_data as *const File))
+        }
+    }
+
+    /// The socket, if this is one
+    pub fn as_socket(&self) -> Option<&'a Socket<'a>> {
+        match *self {
+            Pollable::Socket(socket) => Some(socket),
+            Pollable::File(_) => None,
+        }
+    }
+
+    /// The file, if this is one
+    pub fn as_file(&self) -> Option<&'a File> {
+        match *self {
+            Pollable::File(file) => Some(file),
+            Pollable::Socket(_) => None,
+        }
+    }
+}
+
+/// A descriptor reported as ready by a poll
+#[derive(Clone, Copy)]
+pub struct Ready<'a> {
+    target: Pollable<'a>,
+    events: PollEvents,
+}
+
+impl<'a> Ready<'a> {
+    /// The registered object that is ready
+    pub fn target(&self) -> Pollable<'a> {
+        self.target
+    }
+
+    /// The events that occurred
+    pub fn events(&self) -> PollEvents {
+        self.events
+    }
+}
+
+fn timeout_micros(timeout: Option<Duration>) -> apr_sys::apr_interval_time_t {
+    match timeout {
+        Some(t) => t.as_micros().min(apr_sys::apr_interval_time_t::MAX as u128)
+            as apr_sys::apr_interval_time_t,
+        None => -1,
+    }
+}
+
+/// Whether a poll status means "nothing happened" rather than failure.
+fn is_idle(status: apr_sys::apr_status_t) -> bool {
+    status == apr_sys::APR_TIMEUP as i32 || status == apr_sys::APR_EINTR as i32
+}
+
+/// Handle for interrupting a blocked poll from another thread
+///
+/// Obtained from [`Pollset::waker`] or [`Pollcb::waker`]; it can only wake
+/// the poll, so unlike the pollset itself it may be shared between threads.
+#[derive(Debug, Clone, Copy)]
+pub struct Waker<'a> {
+    target: WakeTarget,
+    _phantom: PhantomData<&'a ()>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum WakeTarget {
+    Pollset(*mut apr_sys::apr_pollset_t),
+    Pollcb(*mut apr_sys::apr_pollcb_t),
+}
+
+// apr_pollset_wakeup and apr_pollcb_wakeup only write to the wakeup pipe,
+// which APR allows from any thread.
+unsafe impl<'a> Send for Waker<'a> {}
+unsafe impl<'a> Sync for Waker<'a> {}
+
+impl<'a> Waker<'a> {
+    /// Interrupt a blocked poll
+    ///
+    /// Only works if the pollset was created with [`PollsetFlags::WAKEABLE`].
+    pub fn wake(&self) -> Result<()> {
+        let status = unsafe {
+            match self.target {
+                WakeTarget::Pollset(raw) => apr_sys::apr_pollset_wakeup(raw),
+                WakeTarget::Pollcb(raw) => apr_sys::apr_pollcb_wakeup(raw),
+            }
+        };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+        Ok(())
+    }
+}
+
+/// A set of descriptors that can be waited on together
+///
+/// A pollset hands out references to the registered sockets and files, so it
+/// stays on the thread that created it; use [`Pollset::waker`] to interrupt
+/// [`Pollset::poll`] from another thread.
+pub struct Pollset<'a> {
+    raw: *mut apr_sys::apr_pollset_t,
+    pool: *mut apr_sys::apr_pool_t,
+    _phantom: PhantomData<&'a Pool<'a>>,
+}
+
+impl<'a> Pollset<'a> {
+    /// Create a pollset for up to `size` descriptors using the default method
+    pub fn new(size: u32, pool: &'a Pool<'a>) -> Result<Self> {
+        Self::with_method(size, PollMethod::Default, PollsetFlags::NONE, pool)
+    }
+
+    /// Create a pollset for up to `size` descriptors with an explicit method
+    pub fn with_method(
+        size: u32,
+        method: PollMethod,
+        flags: PollsetFlags,
+        pool: &'a Pool<'a>,
+    ) -> Result<Self> {
+        let mut pollset: *mut apr_sys::apr_pollset_t = ptr::null_mut();
+        let status = unsafe {
+            apr_sys::apr_pollset_create_ex(
+                &mut pollset,
+                size,
+                pool.as_mut_ptr(),
+                flags.0,
+                method.into(),
+            )
+        };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+
+        Ok(Pollset {
+            raw: pollset,
+            pool: pool.as_mut_ptr(),
+            _phantom: PhantomData,
+        })
+    }
+
+    fn modify(
+        &self,
+        what: Pollable<'a>,
+        events: PollEvents,
+        f: unsafe extern "C" fn(
+            *mut apr_sys::apr_pollset_t,
+            *const apr_sys::apr_pollfd_t,
+        ) -> apr_sys::apr_status_t,
+    ) -> Result<()> {
+        let pfd = what.pollfd(events, self.pool);
+        let status = unsafe { f(self.raw, &pfd) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+        Ok(())
+    }
+
+    /// Start watching `what` for `events`
+    pub fn add(&self, what: impl Into<Pollable<'a>>, events: PollEvents) -> Result<()> {
+        self.modify(what.into(), events, apr_sys::apr_pollset_add)
+    }
+
+    /// Stop watching `what`
+    ///
+    /// When called from the callback of [`Pollcb::poll`], the descriptor may
+    /// still be reported for the current poll.
+    pub fn remove(&self, what: impl Into<Pollable<'a>>) -> Result<()> {
+        self.modify(what.into(), PollEvents::NONE, apr_sys::apr_pollset_remove)
+    }
+
+    /// Wait up to `timeout` (forever if `None`) for registered descriptors
+    ///
+    /// Returns an empty list if the timeout expired or the pollset was woken
+    /// with [`Pollset::wakeup`].
+    pub fn poll(&self, timeout: Option<Duration>) -> Result<Vec<Ready<'a>>> {
+        let mut num: i32 = 0;
+        let mut descriptors: *const apr_sys::apr_pollfd_t = ptr::null();
+        let status = unsafe {
+            apr_sys::apr_pollset_poll(
+                self.raw,
+                timeout_micros(timeout),
+                &mut num,
+                &mut descriptors,
+            )
+        };
+        if is_idle(status) {
+            return Ok(Vec::new());
+        }
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+
+        let ready = unsafe { core::slice::from_raw_parts(descriptors, num as usize) };
+        Ok(ready
+            .iter()
+            .map(|pfd| Ready {
+                target: unsafe { Pollable::from_pollfd(pfd) },
+                events: PollEvents(pfd.rtnevents),
+            })
+            .collect())
+    }
+
+    /// Interrupt a blocked [`Pollset::poll`]
+    ///
+    /// Only works if the pollset was created with [`PollsetFlags::WAKEABLE`].
+    pub fn wakeup(&self) -> Result<()> {
+        self.waker().wake()
+    }
+
+    /// Handle for waking [`Pollset::poll`] from another thread
+    pub fn waker(&self) -> Waker<'_> {
+        Waker {
+            target: WakeTarget::Pollset(self.raw),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Name of the method backing this pollset, e.g. `"epoll"`
+    pub fn method_name(&self) -> &str {
+        unsafe {
+            CStr::from_ptr(apr_sys::apr_pollset_method_name(self.raw))
+                .to_str()
+                .unwrap_or("unknown")
+        }
+    }
+
+    /// Raw pointer to the underlying APR pollset
+    pub fn as_ptr(&self) -> *const apr_sys::apr_pollset_t {
+        self.raw
+    }
+
+    /// Mutable raw pointer to the underlying APR pollset
+    pub fn as_mut_ptr(&self) -> *mut apr_sys::apr_pollset_t {
+        self.raw
+    }
+}
+
+impl<'a> Drop for Pollset<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            apr_sys::apr_pollset_destroy(self.raw);
+        }
+    }
+}
+
+/// A set of descriptors whose readiness is reported through a callback
+///
+/// Unlike [`Pollset`], APR does not copy the descriptors registered with a
+/// pollcb, so they are kept alive here until removed.
+pub struct Pollcb<'a> {
+    raw: *mut apr_sys::apr_pollcb_t,
+    pool: *mut apr_sys::apr_pool_t,
+    // Boxed so the descriptors APR points at don't move when the Vec grows.
+    #[allow(clippy::vec_box)]
+    registered: RefCell<Vec<Box<apr_sys::apr_pollfd_t>>>,
+    // Descriptors removed during a poll, which APR may still read from
+    // until apr_pollcb_poll returns.
+    #[allow(clippy::vec_box)]
+    removed: RefCell<Vec<Box<apr_sys::apr_pollfd_t>>>,
+    polling: Cell<bool>,
+    _phantom: PhantomData<&'a Pool<'a>>,
+}
+
+struct PollcbBaton<'f, 'a> {
+    callback: &'f mut dyn FnMut(Ready<'a>),
+    panic: Option<Box<dyn core::any::Any + Send + 'static>>,
+}
+
+extern "C" fn pollcb_trampoline(
+    baton: *mut c_void,
+    descriptor: *mut apr_sys::apr_pollfd_t,
+) -> apr_sys::apr_status_t {
+    let baton = unsafe { &mut *(baton as *mut PollcbBaton) };
+    let pfd = unsafe { &*descriptor };
+    let ready = Ready {
+        target: unsafe { Pollable::from_pollfd(pfd) },
+        events: PollEvents(pfd.rtnevents),
+    };
+    match catch_unwind(AssertUnwindSafe(|| (baton.callback)(ready))) {
+        Ok(()) => apr_sys::APR_SUCCESS as apr_sys::apr_status_t,
+        Err(payload) => {
+            baton.panic = Some(payload);
+            apr_sys::APR_EGENERAL as apr_sys::apr_status_t
+        }
+    }
+}
+
+impl<'a> Pollcb<'a> {
+    /// Create a pollcb for up to `size` descriptors using the default method
+    pub fn new(size: u32, pool: &'a Pool<'a>) -> Result<Self> {
+        Self::with_method(size, PollMethod::Default, PollsetFlags::NONE, pool)
+    }
+
+    /// Create a pollcb for up to `size` descriptors with an explicit method
+    pub fn with_method(
+        size: u32,
+        method: PollMethod,
+        flags: PollsetFlags,
+        pool: &'a Pool<'a>,
+    ) -> Result<Self> {
+        let mut pollcb: *mut apr_sys::apr_pollcb_t = ptr::null_mut();
+        let status = unsafe {
+            apr_sys::apr_pollcb_create_ex(
+                &mut pollcb,
+                size,
+                pool.as_mut_ptr(),
+                flags.0,
+                method.into(),
+            )
+        };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+
+        Ok(Pollcb {
+            raw: pollcb,
+            pool: pool.as_mut_ptr(),
+            registered: RefCell::new(Vec::new()),
+            removed: RefCell::new(Vec::new()),
+            polling: Cell::new(false),
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Start watching `what` for `events`
+    pub fn add(&self, what: impl Into<Pollable<'a>>, events: PollEvents) -> Result<()> {
+        let mut pfd = Box::new(what.into().pollfd(events, self.pool));
+        let status = unsafe { apr_sys::apr_pollcb_add(self.raw, &mut *pfd) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+        self.registered.borrow_mut().push(pfd);
+        Ok(())
+    }
+
+    /// Stop watching `what`
+    pub fn remove(&self, what: impl Into<Pollable<'a>>) -> Result<()> {
+        let client_data = what.into().pollfd(PollEvents::NONE, self.pool).client_data;
+        let mut registered = self.registered.borrow_mut();
+        let index = registered
+            .iter()
+            .position(|pfd| pfd.client_data == client_data)
+            .ok_or_else(|| crate::Error::from_status(apr_sys::APR_NOTFOUND.into()))?;
+        let status = unsafe { apr_sys::apr_pollcb_remove(self.raw, &mut *registered[index]) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+        let pfd = registered.swap_remove(index);
+        if self.polling.get() {
+            self.removed.borrow_mut().push(pfd);
+        }
+        Ok(())
+    }
+
+    /// Wait up to `timeout` (forever if `None`), calling `callback` for each
+    /// ready descriptor
+    ///
+    /// Returns normally, without calling `callback`, if the timeout expired or
+    /// the pollcb was woken with [`Pollcb::wakeup`].
+    pub fn poll(
+        &self,
+        timeout: Option<Duration>,
+        mut callback: impl FnMut(Ready<'a>),
+    ) -> Result<()> {
+        let mut baton = PollcbBaton {
+            callback: &mut callback,
+            panic: None,
+        };
+        let was_polling = self.polling.replace(true);
+        let status = unsafe {
+            apr_sys::apr_pollcb_poll(
+                self.raw,
+                timeout_micros(timeout),
+                Some(pollcb_trampoline),
+                &mut baton as *mut PollcbBaton as *mut c_void,
+            )
+        };
+        self.polling.set(was_polling);
+        if !was_polling {
+            self.removed.borrow_mut().clear();
+        }
+        if let Some(payload) = baton.panic {
+            resume_unwind(payload);
+        }
+        if is_idle(status) || status == apr_sys::APR_SUCCESS as i32 {
+            return Ok(());
+        }
+        Err(crate::Error::from_status(status.into()))
+    }
+
+    /// Interrupt a blocked [`Pollcb::poll`]
+    ///
+    /// Only works if the pollcb was created with [`PollsetFlags::WAKEABLE`].
+    pub fn wakeup(&self) -> Result<()> {
+        self.waker().wake()
+    }
+
+    /// Handle for waking [`Pollcb::poll`] from another thread
+    pub fn waker(&self) -> Waker<'_> {
+        Waker {
+            target: WakeTarget::Pollcb(self.raw),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Name of the method backing this pollcb, e.g. `"epoll"`
+    pub fn method_name(&self) -> &str {
+        unsafe {
+            CStr::from_ptr(apr_sys::apr_pollcb_method_name(self.raw))
+                .to_str()
+                .unwrap_or("unknown")
+        }
+    }
+
+    /// Raw pointer to the underlying APR pollcb
+    pub fn as_ptr(&self) -> *const apr_sys::apr_pollcb_t {
+        self.raw
+    }
+
+    /// Mutable raw pointer to the underlying APR pollcb
+    pub fn as_mut_ptr(&self) -> *mut apr_sys::apr_pollcb_t {
+        self.raw
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{SockAddr, SocketFamily, SocketProtocol, SocketType};
+    use std::net::Ipv4Addr;
+
+    fn udp_socket<'p>(pool: &'p Pool<'p>) -> Socket<'p> {
+        let mut socket = Socket::new(
+            SocketFamily::Inet,
+            SocketType::Dgram,
+            SocketProtocol::Udp,
+            pool,
+        )
+        .unwrap();
+        let addr = SockAddr::new_inet(Ipv4Addr::new(127, 0, 0, 1), 0, pool).unwrap();
+        socket.bind(&addr).unwrap();
+        socket
+    }
+
+    #[test]
+    fn test_poll_events() {
+        let events = PollEvents::IN | PollEvents::OUT;
+        assert!(events.is_readable());
+        assert!(events.is_writable());
+        assert!(!events.contains(PollEvents::HUP));
+        assert!(PollEvents::NONE.is_empty());
+    }
+
+    #[test]
+    fn test_pollset_writable_socket() {
+        let pool = Pool::new();
+        let socket = udp_socket(&pool);
+        let pollset = Pollset::new(4, &pool).unwrap();
+        assert!(!pollset.method_name().is_empty());
+
+        pollset.add(&socket, PollEvents::OUT).unwrap();
+        let ready = pollset.poll(Some(Duration::from_secs(1))).unwrap();
+        assert_eq!(ready.len(), 1);
+        assert!(ready[0].events().is_writable());
+        let target = ready[0].target().as_socket().unwrap();
+        assert_eq!(target.as_ptr(), socket.as_ptr());
+
+        pollset.remove(&socket).unwrap();
+        let ready = pollset.poll(Some(Duration::from_millis(10))).unwrap();
+        assert!(ready.is_empty());
+    }
+
+    #[test]
+    fn test_pollset_timeout() {
+        let pool = Pool::new();
+        let socket = udp_socket(&pool);
+        let pollset = Pollset::new(4, &pool).unwrap();
+        pollset.add(&socket, PollEvents::IN).unwrap();
+        let ready = pollset.poll(Some(Duration::from_millis(10))).unwrap();
+        assert!(ready.is_empty());
+    }
+
+    #[test]
+    fn test_pollset_wakeup() {
+        let pool = Pool::new();
+        let pollset =
+            Pollset::with_method(4, PollMethod::Default, PollsetFlags::WAKEABLE, &pool).unwrap();
+        let waker = pollset.waker();
+        std::thread::scope(|s| {
+            s.spawn(move || {
+                std::thread::sleep(Duration::from_millis(20));
+                waker.wake().unwrap();
+            });
+            assert!(pollset.poll(None).unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_pollcb() {
+        let pool = Pool::new();
+        let socket = udp_socket(&pool);
+        let pollcb = Pollcb::new(4, &pool).unwrap();
+        pollcb.add(&socket, PollEvents::OUT).unwrap();
+
+        let mut seen = Vec::new();
+        pollcb
+            .poll(Some(Duration::from_secs(1)), |ready| {
+                seen.push(ready.events());
+            })
+            .unwrap();
+        assert_eq!(seen.len(), 1);
+        assert!(seen[0].is_writable());
+
+        pollcb.remove(&socket).unwrap();
+        assert!(pollcb.remove(&socket).is_err());
+    }
+
+    #[test]
+    fn test_pollcb_remove_in_callback() {
+        let pool = Pool::new();
+        let first = udp_socket(&pool);
+        let second = udp_socket(&pool);
+        let pollcb = Pollcb::new(4, &pool).unwrap();
+        pollcb.add(&first, PollEvents::OUT).unwrap();
+        pollcb.add(&second, PollEvents::OUT).unwrap();
+
+        // Both sockets are ready, so whichever is reported first removes the
+        // other while it is still pending in the same batch.
+        let mut calls = 0;
+        pollcb
+            .poll(Some(Duration::from_secs(1)), |_| {
+                if calls == 0 {
+                    pollcb.remove(&first).unwrap();
+                    pollcb.remove(&second).unwrap();
+                }
+                calls += 1;
+            })
+            .unwrap();
+        assert!(calls >= 1);
+
+        assert!(pollcb.remove(&first).is_err());
+        assert!(pollcb.remove(&second).is_err());
+        let mut calls = 0;
+        pollcb
+            .poll(Some(Duration::from_millis(10)), |_| calls += 1)
+            .unwrap();
+        assert_eq!(calls, 0);
+    }
+}
@@ -1,8 +1,12 @@
 //! Memory pool management.
+use crate::callbacks::CallbackHandle;
+use alloc::boxed::Box;
 use alloc::rc::Rc;
 use apr_sys;
+use core::ffi::c_void;
 use core::mem::ManuallyDrop;
 use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicPtr, Ordering};
 
 /// A memory pool.
 ///
@@ -57,6 +61,29 @@ impl Pool<'static> {
         }
     }
 
+    /// Create a new root pool that takes ownership of `allocator`.
+    ///
+    /// The allocator is destroyed together with the pool. Subpools use the
+    /// same allocator.
+    pub fn with_owned_allocator(mut allocator: Allocator) -> Self {
+        let mut pool: *mut apr_sys::apr_pool_t = core::ptr::null_mut();
+        unsafe {
+            apr_sys::apr_pool_create_ex(&mut pool, core::ptr::null_mut(), None, allocator.raw);
+            apr_sys::apr_allocator_owner_set(allocator.raw, pool);
+        }
+        let pool = Pool {
+            raw: pool,
+            _marker: core::marker::PhantomData,
+        };
+        // APR detaches the mutex before destroying an owned allocator, and
+        // cleanups only run once all subpools are gone.
+        if let Some(mutex_pool) = allocator.mutex_pool.take() {
+            pool.register_cleanup(move || drop(mutex_pool));
+        }
+        core::mem::forget(allocator);
+        pool
+    }
+
     #[cfg(feature = "pool-debug")]
     /// Create a new root pool with debug information.
     ///
@@ -93,11 +120,42 @@ impl<'pool> Pool<'pool> {
         }
     }
 
+    /// Create a new root pool that allocates from `allocator`.
+    ///
+    /// The pool and its subpools borrow the allocator, which must outlive them.
+    pub fn with_allocator(allocator: &'pool Allocator) -> Self {
+        let mut pool: *mut apr_sys::apr_pool_t = core::ptr::null_mut();
+        unsafe {
+            apr_sys::apr_pool_create_ex(&mut pool, core::ptr::null_mut(), None, allocator.raw);
+        }
+        Pool {
+            raw: pool,
+            _marker: core::marker::PhantomData,
+        }
+    }
+
     /// Get the raw pointer to the pool.
     pub fn as_ptr(&self) -> *const apr_sys::apr_pool_t {
         self.raw
     }
 
+    /// Get the raw pointer to the allocator this pool allocates from.
+    pub fn allocator_ptr(&self) -> *mut apr_sys::apr_allocator_t {
+        unsafe { apr_sys::apr_pool_allocator_get(self.raw) }
+    }
+
+    /// Abort the process when an allocation from this pool fails, instead of
+    /// letting APR return a null pointer.
+    ///
+    /// The hook set with [`set_oom_hook`] runs first, then the failure is
+    /// reported and the process aborts; nothing unwinds back through APR.
+    /// Subpools created afterwards inherit this behaviour.
+    pub fn abort_on_oom(&self) {
+        unsafe {
+            apr_sys::apr_pool_abort_set(Some(pool_abort), self.raw);
+        }
+    }
+
     /// Get the raw mutable pointer to the pool.
     pub fn as_mut_ptr(&self) -> *mut apr_sys::apr_pool_t {
         self.raw
@@ -131,6 +189,39 @@ impl<'pool> Pool<'pool> {
         unsafe { apr_sys::apr_palloc(self.raw, size) as *mut core::mem::MaybeUninit<T> }
     }
 
+    /// Allocate uninitialized memory for `layout` in the pool.
+    ///
+    /// The memory stays valid until the pool is cleared or destroyed. Returns
+    /// null if APR could not allocate it.
+    pub fn alloc_layout(&self, layout: core::alloc::Layout) -> *mut u8 {
+        if layout.size() == 0 {
+            return layout.align() as *mut u8;
+        }
+        // apr_palloc aligns to APR_ALIGN_DEFAULT; over-allocate for anything stricter.
+        if layout.align() <= POOL_ALIGN {
+            return unsafe { apr_sys::apr_palloc(self.raw, layout.size()) as *mut u8 };
+        }
+        let Some(size) = layout.size().checked_add(layout.align() - 1) else {
+            return core::ptr::null_mut();
+        };
+        let ptr = unsafe { apr_sys::apr_palloc(self.raw, size) as *mut u8 };
+        if ptr.is_null() {
+            return ptr;
+        }
+        ptr.wrapping_add(ptr.align_offset(layout.align()))
+    }
+
+    /// Allocate zeroed memory for `layout` in the pool.
+    ///
+    /// See [`Pool::alloc_layout`].
+    pub fn calloc_layout(&self, layout: core::alloc::Layout) -> *mut u8 {
+        let ptr = self.alloc_layout(layout);
+        if !ptr.is_null() {
+            unsafe { core::ptr::write_bytes(ptr, 0, layout.size()) };
+        }
+        ptr
+    }
+
     /// Allocate memory in the pool and zero it.
     #[allow(clippy::mut_from_ref)]
     pub fn calloc<T: Sized>(&self) -> *mut T {
@@ -212,6 +303,152 @@ impl<'pool> Pool<'pool> {
         }
     }
 
+    /// Register `f` to run when the pool is cleared or destroyed.
+    ///
+    /// Cleanups run in the reverse order of registration. `f` must not panic,
+    /// since unwinding out of an APR cleanup aborts the process.
+    pub fn register_cleanup<F: FnOnce() + 'static>(&self, f: F) -> CleanupHandle<'_> {
+        self.register(f, cleanup_call::<F>, apr_sys::apr_pool_cleanup_null)
+    }
+
+    /// Register `f` to run in a child process just before it calls exec().
+    ///
+    /// If the pool is cleared or destroyed first, `f` is dropped without being
+    /// called.
+    pub fn register_child_cleanup<F: FnOnce() + 'static>(&self, f: F) -> CleanupHandle<'_> {
+        self.register(f, cleanup_discard::<F>, cleanup_call::<F>)
+    }
+
+    fn register<F: FnOnce() + 'static>(
+        &self,
+        f: F,
+        plain: CleanupFn,
+        child: CleanupFn,
+    ) -> CleanupHandle<'_> {
+        // The slot lives in the pool so that it stays valid for as long as
+        // APR might call back into it, even after the closure has been taken.
+        let slot = self.alloc::<CleanupSlot<F>>() as *mut CleanupSlot<F>;
+        unsafe {
+            slot.write(Some(CallbackHandle::new(f)));
+            apr_sys::apr_pool_cleanup_register(
+                self.raw,
+                slot as *const c_void,
+                Some(plain),
+                Some(child),
+            );
+        }
+        CleanupHandle {
+            pool: self.raw,
+            data: slot as *mut c_void,
+            plain,
+            discard: cleanup_discard::<F>,
+            _marker: core::marker::PhantomData,
+        }
+    }
+
+    /// Move `value` into the pool, returning a reference to it.
+    ///
+    /// The value is dropped when the pool is cleared or destroyed.
+    pub fn alloc_value<T: 'static>(&self, value: T) -> &T {
+        let boxed = Box::into_raw(Box::new(value));
+        self.register_cleanup(move || unsafe { drop(Box::from_raw(boxed)) });
+        unsafe { &*boxed }
+    }
+
+    /// Store `value` in the pool under `key`, dropping it with the pool.
+    ///
+    /// Replacing an existing key does not drop the previous value until the
+    /// pool is cleared or destroyed, so references to it stay valid.
+    pub fn set_userdata<T: 'static>(&self, key: &str, value: T) -> crate::Result<()> {
+        let c_key = userdata_key(key)?;
+        let data = self.box_for_userdata(value);
+        unsafe {
+            apr_sys::apr_pool_userdata_set(data, c_key.as_ptr(), None, self.raw);
+        }
+        self.set_userdata_tag::<T>(key, data)
+    }
+
+    /// Like [`Pool::set_userdata`], but without copying the key into the pool
+    /// (`apr_pool_userdata_setn`).
+    pub fn set_userdata_static<T: 'static>(
+        &self,
+        key: &'static core::ffi::CStr,
+        value: T,
+    ) -> crate::Result<()> {
+        let str_key = key
+            .to_str()
+            .map_err(|_| crate::Error::from_status(crate::Status::BadArgument))?;
+        let data = self.box_for_userdata(value);
+        unsafe {
+            apr_sys::apr_pool_userdata_setn(data, key.as_ptr(), None, self.raw);
+        }
+        self.set_userdata_tag::<T>(str_key, data)
+    }
+
+    /// Record which type, and which allocation, is stored under `key`.
+    fn set_userdata_tag<T: 'static>(&self, key: &str, data: *mut c_void) -> crate::Result<()> {
+        let tag_key = userdata_tag_key(key)?;
+        let tag = self.box_for_userdata(UserdataTag {
+            type_id: core::any::TypeId::of::<T>(),
+            data,
+        });
+        unsafe {
+            apr_sys::apr_pool_userdata_set(tag, tag_key.as_ptr(), None, self.raw);
+        }
+        Ok(())
+    }
+
+    /// Box `value` so it is dropped when the pool is cleared or destroyed.
+    ///
+    /// The drop is not a child cleanup, so it does not run in a forked child
+    /// before exec.
+    fn box_for_userdata<T: 'static>(&self, value: T) -> *mut c_void {
+        let data = Box::into_raw(Box::new(value)) as *mut c_void;
+        unsafe {
+            apr_sys::apr_pool_cleanup_register(
+                self.raw,
+                data,
+                Some(drop_boxed::<T>),
+                Some(apr_sys::apr_pool_cleanup_null),
+            );
+        }
+        data
+    }
+
+    /// Get the value stored under `key` by [`Pool::set_userdata`].
+    ///
+    /// Returns `None` if nothing is stored, the value has a different type,
+    /// or the key was last set by C code.
+    pub fn userdata<T: 'static>(&self, key: &str) -> Option<&T> {
+        let data = self.userdata_raw(key);
+        let tag_key = userdata_tag_key(key).ok()?;
+        let mut tag: *mut c_void = core::ptr::null_mut();
+        unsafe {
+            apr_sys::apr_pool_userdata_get(&mut tag, tag_key.as_ptr(), self.raw);
+        }
+        if data.is_null() || tag.is_null() {
+            return None;
+        }
+        let tag = unsafe { &*(tag as *const UserdataTag) };
+        if tag.type_id != core::any::TypeId::of::<T>() || tag.data != data {
+            return None;
+        }
+        Some(unsafe { &*(data as *const T) })
+    }
+
+    /// Get the raw pointer stored under `key`, e.g. by a C library.
+    ///
+    /// Returns null if nothing is stored.
+    pub fn userdata_raw(&self, key: &str) -> *mut c_void {
+        let mut data: *mut c_void = core::ptr::null_mut();
+        if let Ok(c_key) = userdata_key(key) {
+            unsafe {
+                apr_sys::apr_pool_userdata_get(&mut data, c_key.as_ptr(), self.raw);
+            }
+        }
+        data
+    }
+
     /// Try to join two pools.
     #[cfg(feature = "pool-debug")]
     pub fn join(&self, other: &Pool<'_>) {
@@ -257,9 +494,85 @@ impl Drop for Pool<'_> {
     }
 }
 
+/// Alignment `apr_palloc` guarantees (`APR_ALIGN_DEFAULT`).
+const POOL_ALIGN: usize = 8;
+
+type CleanupFn = unsafe extern "C" fn(*mut c_void) -> apr_sys::apr_status_t;
+
+type CleanupSlot<F> = Option<CallbackHandle<F>>;
+
+unsafe extern "C" fn cleanup_call<F: FnOnce()>(data: *mut c_void) -> apr_sys::apr_status_t {
+    if let Some(handle) = (*(data as *mut CleanupSlot<F>)).take() {
+        (handle.into_inner())();
+    }
+    apr_sys::APR_SUCCESS as apr_sys::apr_status_t
+}
+
+unsafe extern "C" fn cleanup_discard<F: FnOnce()>(data: *mut c_void) -> apr_sys::apr_status_t {
+    drop((*(data as *mut CleanupSlot<F>)).take());
+    apr_sys::APR_SUCCESS as apr_sys::apr_status_t
+}
+
+/// Type tag stored alongside each value set with [`Pool::set_userdata`].
+struct UserdataTag {
+    type_id: core::any::TypeId,
+    data: *mut c_void,
+}
+
+fn userdata_key(key: &str) -> crate::Result<alloc::ffi::CString> {
+    alloc::ffi::CString::new(key).map_err(|_| crate::Error::from_status(crate::Status::BadArgument))
+}
+
+fn userdata_tag_key(key: &str) -> crate::Result<alloc::ffi::CString> {
+    userdata_key(&alloc::format!("apr-rs:userdata-type:{}", key))
+}
+
+unsafe extern "C" fn drop_boxed<T>(data: *mut c_void) -> apr_sys::apr_status_t {
+    drop(Box::from_raw(data as *mut T));
+    apr_sys::APR_SUCCESS as apr_sys::apr_status_t
+}
+
+/// Handle to a cleanup registered with [`Pool::register_cleanup`] or
+/// [`Pool::register_child_cleanup`].
+///
+/// Dropping the handle leaves the cleanup registered.
+pub struct CleanupHandle<'pool> {
+    pool: *mut apr_sys::apr_pool_t,
+    data: *mut c_void,
+    plain: CleanupFn,
+    discard: CleanupFn,
+    _marker: core::marker::PhantomData<&'pool ()>,
+}
+
+impl CleanupHandle<'_> {
+    /// Unregister the cleanup without running it.
+    pub fn kill(self) {
+        unsafe {
+            apr_sys::apr_pool_cleanup_kill(self.pool, self.data, Some(self.plain));
+            (self.discard)(self.data);
+        }
+    }
+
+    /// Run the cleanup now and unregister it.
+    ///
+    /// For a child cleanup this drops the closure without calling it, just as
+    /// clearing the pool would.
+    pub fn run(self) {
+        unsafe {
+            apr_sys::apr_pool_cleanup_run(self.pool, self.data, Some(self.plain));
+        }
+    }
+}
+
 /// An allocator.
+///
+/// Pools hand memory they no longer need back to their allocator, which keeps
+/// it for reuse. Give each worker its own allocator with
+/// [`Allocator::set_max_free`] set to cap how much memory it retains.
 pub struct Allocator {
     raw: *mut apr_sys::apr_allocator_t,
+    // Pool holding the mutex set by `set_thread_safe`, if any
+    mutex_pool: Option<Pool<'static>>,
     // Allocators are not Send or Sync
     _no_send: core::marker::PhantomData<*mut ()>,
 }
@@ -273,14 +586,67 @@ impl Allocator {
         }
         Allocator {
             raw: allocator,
+            mutex_pool: None,
             _no_send: core::marker::PhantomData,
         }
     }
 
+    /// Limit the amount of free memory the allocator keeps for reuse.
+    ///
+    /// Memory beyond `bytes` is returned to the system as soon as pools free
+    /// it. Zero means no limit, which is the default.
+    pub fn set_max_free(&self, bytes: usize) {
+        unsafe {
+            apr_sys::apr_allocator_max_free_set(self.raw, bytes);
+        }
+    }
+
+    /// Size of the block the allocator would hand out for a request of `size` bytes.
+    pub fn align(&self, size: usize) -> usize {
+        unsafe { apr_sys::apr_allocator_align(self.raw, size) }
+    }
+
+    /// Protect the allocator with a mutex.
+    ///
+    /// This is needed when pools using the allocator are used from more than
+    /// one thread, e.g. subpools handed to [`crate::thread`] threads.
+    pub fn set_thread_safe(&mut self) -> crate::Result<()> {
+        if self.mutex_pool.is_some() {
+            return Ok(());
+        }
+        let mutex_pool = Pool::new();
+        let mut mutex: *mut apr_sys::apr_thread_mutex_t = core::ptr::null_mut();
+        let status = unsafe {
+            apr_sys::apr_thread_mutex_create(
+                &mut mutex,
+                apr_sys::APR_THREAD_MUTEX_DEFAULT,
+                mutex_pool.as_mut_ptr(),
+            )
+        };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+        unsafe {
+            apr_sys::apr_allocator_mutex_set(self.raw, mutex);
+        }
+        self.mutex_pool = Some(mutex_pool);
+        Ok(())
+    }
+
+    /// Whether [`Allocator::set_thread_safe`] has been called.
+    pub fn is_thread_safe(&self) -> bool {
+        self.mutex_pool.is_some()
+    }
+
     /// Return the raw pointer to the allocator.
     pub fn as_ptr(&self) -> *const apr_sys::apr_allocator_t {
         self.raw
     }
+
+    /// Return the raw mutable pointer to the allocator.
+    pub fn as_mut_ptr(&self) -> *mut apr_sys::apr_allocator_t {
+        self.raw
+    }
 }
 
 impl Default for Allocator {
@@ -297,6 +663,40 @@ impl Drop for Allocator {
     }
 }
 
+static OOM_HOOK: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Set a hook called when a pool set up with [`Pool::abort_on_oom`] fails to
+/// allocate memory.
+///
+/// The hook is process-wide and receives the status APR reported. It runs
+/// before the process aborts, e.g. to log or flush state.
+pub fn set_oom_hook(hook: fn(crate::Status)) {
+    OOM_HOOK.store(hook as *mut (), Ordering::Release);
+}
+
+/// Remove the hook set with [`set_oom_hook`].
+pub fn take_oom_hook() -> Option<fn(crate::Status)> {
+    let hook = OOM_HOOK.swap(core::ptr::null_mut(), Ordering::AcqRel);
+    (!hook.is_null()).then(|| unsafe { core::mem::transmute::<*mut (), fn(crate::Status)>(hook) })
+}
+
+extern "C" fn pool_abort(retcode: core::ffi::c_int) -> core::ffi::c_int {
+    let status = crate::Status::from(retcode);
+    let hook = OOM_HOOK.load(Ordering::Acquire);
+    if !hook.is_null() {
+        let hook = unsafe { core::mem::transmute::<*mut (), fn(crate::Status)>(hook) };
+        hook(status);
+    }
+    #[cfg(feature = "std")]
+    {
+        std::eprintln!("APR pool allocation failed: {}", status);
+        std::process::abort();
+    }
+    // Panicking out of an `extern "C"` function aborts the process.
+    #[cfg(not(feature = "std"))]
+    panic!("APR pool allocation failed: {}", status);
+}
+
 /// Create a temporary pool, run a function with it, then destroy the pool.
 ///
 /// This is useful for short-lived operations that need a pool for temporary allocations.
@@ -700,6 +1100,165 @@ mod tests {
         pool.tag("pool");
     }
 
+    #[test]
+    fn test_register_cleanup() {
+        use core::cell::RefCell;
+
+        let order = Rc::new(RefCell::new(alloc::vec::Vec::new()));
+        let pool = Pool::new();
+        for i in 0..3 {
+            let order = order.clone();
+            pool.register_cleanup(move || order.borrow_mut().push(i));
+        }
+        assert!(order.borrow().is_empty());
+        drop(pool);
+        assert_eq!(*order.borrow(), [2, 1, 0]);
+    }
+
+    #[test]
+    fn test_cleanup_handle_kill_and_run() {
+        use core::cell::Cell;
+
+        let count = Rc::new(Cell::new(0));
+        let pool = Pool::new();
+
+        let c = count.clone();
+        pool.register_cleanup(move || c.set(c.get() + 1)).kill();
+
+        let c = count.clone();
+        pool.register_cleanup(move || c.set(c.get() + 10)).run();
+        assert_eq!(count.get(), 10);
+
+        drop(pool);
+        assert_eq!(count.get(), 10);
+        assert_eq!(Rc::strong_count(&count), 1);
+    }
+
+    #[test]
+    fn test_child_cleanup_not_run_on_destroy() {
+        use core::cell::Cell;
+
+        let called = Rc::new(Cell::new(false));
+        let pool = Pool::new();
+        let c = called.clone();
+        pool.register_child_cleanup(move || c.set(true));
+        drop(pool);
+        assert!(!called.get());
+        assert_eq!(Rc::strong_count(&called), 1);
+    }
+
+    #[test]
+    fn test_alloc_value() {
+        let marker = Rc::new(());
+        let pool = Pool::new();
+        let value = pool.alloc_value((marker.clone(), 42));
+        assert_eq!(value.1, 42);
+        assert_eq!(Rc::strong_count(&marker), 2);
+        drop(pool);
+        assert_eq!(Rc::strong_count(&marker), 1);
+    }
+
+    #[test]
+    fn test_pool_with_allocator() {
+        let allocator = Allocator::new();
+        allocator.set_max_free(64 * 1024);
+        assert!(allocator.align(1) >= 1);
+
+        let pool = Pool::with_allocator(&allocator);
+        assert_eq!(pool.allocator_ptr(), allocator.as_mut_ptr());
+        let subpool = pool.subpool();
+        assert_eq!(subpool.allocator_ptr(), allocator.as_mut_ptr());
+        assert!(!subpool.calloc::<u64>().is_null());
+    }
+
+    #[test]
+    fn test_pool_with_owned_allocator() {
+        let mut allocator = Allocator::new();
+        allocator.set_thread_safe().unwrap();
+        assert!(allocator.is_thread_safe());
+        let raw = allocator.as_mut_ptr();
+
+        let pool = Pool::with_owned_allocator(allocator);
+        assert_eq!(pool.allocator_ptr(), raw);
+        assert_eq!(
+            unsafe { apr_sys::apr_allocator_owner_get(raw) },
+            pool.as_mut_ptr()
+        );
+        pool.abort_on_oom();
+        let subpool = pool.subpool();
+        assert!(!subpool.pstrdup("worker").is_null());
+    }
+
+    #[test]
+    fn test_oom_hook() {
+        fn hook(_: crate::Status) {}
+        set_oom_hook(hook);
+        assert!(take_oom_hook().is_some());
+        assert!(take_oom_hook().is_none());
+    }
+
+    #[test]
+    fn test_userdata() {
+        let pool = Pool::new();
+        assert_eq!(pool.userdata::<u32>("answer"), None);
+
+        pool.set_userdata("answer", 42u32).unwrap();
+        assert_eq!(pool.userdata::<u32>("answer"), Some(&42));
+        assert_eq!(pool.userdata::<i64>("answer"), None);
+        assert!(!pool.userdata_raw("answer").is_null());
+
+        pool.set_userdata("answer", alloc::string::String::from("forty-two"))
+            .unwrap();
+        assert_eq!(pool.userdata::<u32>("answer"), None);
+        assert_eq!(
+            pool.userdata::<alloc::string::String>("answer").unwrap(),
+            "forty-two"
+        );
+    }
+
+    #[test]
+    fn test_userdata_static_key() {
+        let pool = Pool::new();
+        pool.set_userdata_static(c"static-key", 7u8).unwrap();
+        assert_eq!(pool.userdata::<u8>("static-key"), Some(&7));
+    }
+
+    #[test]
+    fn test_userdata_static_key_invalid() {
+        let pool = Pool::new();
+        assert!(pool.set_userdata_static(c"\xff", 7u8).is_err());
+        let mut data: *mut c_void = core::ptr::null_mut();
+        unsafe {
+            apr_sys::apr_pool_userdata_get(&mut data, c"\xff".as_ptr(), pool.as_mut_ptr());
+        }
+        assert!(data.is_null());
+    }
+
+    #[test]
+    fn test_userdata_set_from_c() {
+        let pool = Pool::new();
+        unsafe {
+            apr_sys::apr_pool_userdata_setn(
+                pool.pstrdup("c-value") as *const c_void,
+                c"c-key".as_ptr(),
+                None,
+                pool.as_mut_ptr(),
+            );
+        }
+        assert!(!pool.userdata_raw("c-key").is_null());
+        assert_eq!(pool.userdata::<u8>("c-key"), None);
+    }
+
+    #[test]
+    fn test_userdata_dropped_with_pool() {
+        let marker = Rc::new(());
+        let pool = Pool::new();
+        pool.set_userdata("marker", marker.clone()).unwrap();
+        assert_eq!(Rc::strong_count(&marker), 2);
+        drop(pool);
+        assert_eq!(Rc::strong_count(&marker), 1);
+    }
+
     #[test]
     fn test_pool_handle_owned() {
         // Create an owned pool handle
@@ -0,0 +1,468 @@
+//! Building data directly in pool memory
+//!
+//! [`PoolVec`] and [`PoolStringBuf`] are growable containers whose storage comes
+//! from a [`Pool`], so their contents can be handed to C code without copying.
+//! Like any pool allocation, memory is only reclaimed when the pool is cleared
+//! or destroyed; growing a container leaves its previous buffer behind.
+//!
+//! When built on a nightly compiler with `RUSTFLAGS="--cfg apr_nightly"`,
+//! [`PoolAllocator`] also implements `core::alloc::Allocator`, so the standard
+//! collections can allocate from a pool with e.g. `Vec::new_in`.
+
+use crate::pool::Pool;
+use crate::Result;
+use alloc::string::String;
+use core::alloc::Layout;
+use core::ffi::{c_char, CStr};
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use core::ptr::{self, NonNull};
+
+/// Allocator handing out memory from a [`Pool`]
+///
+/// Deallocation is a no-op; memory is released with the pool.
+#[derive(Debug, Clone, Copy)]
+pub struct PoolAllocator<'pool> {
+    pool: &'pool Pool<'pool>,
+}
+
+impl<'pool> PoolAllocator<'pool> {
+    /// Create an allocator for `pool`
+    pub fn new(pool: &'pool Pool<'pool>) -> Self {
+        PoolAllocator { pool }
+    }
+
+    /// The pool memory is allocated from
+    pub fn pool(&self) -> &'pool Pool<'pool> {
+        self.pool
+    }
+
+    fn allocate_raw(&self, layout: Layout) -> NonNull<u8> {
+        match NonNull::new(self.pool.alloc_layout(layout)) {
+            Some(ptr) => ptr,
+            None => alloc::alloc::handle_alloc_error(layout),
+        }
+    }
+}
+
+#[cfg(apr_nightly)]
+unsafe impl<'pool> core::alloc::Allocator for PoolAllocator<'pool> {
+    fn allocate(
+        &self,
+        layout: Layout,
+    ) -> core::result::Result<NonNull<[u8]>, core::alloc::AllocError> {
+        let ptr = NonNull::new(self.pool.alloc_layout(layout)).ok_or(core::alloc::AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    fn allocate_zeroed(
+        &self,
+        layout: Layout,
+    ) -> core::result::Result<NonNull<[u8]>, core::alloc::AllocError> {
+        let ptr = NonNull::new(self.pool.calloc_layout(layout)).ok_or(core::alloc::AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+}
+
+/// Growable array stored in pool memory
+///
+/// Elements are dropped when the `PoolVec` is dropped, unless it has been
+/// turned into a slice with [`PoolVec::into_slice`].
+pub struct PoolVec<'pool, T> {
+    ptr: NonNull<T>,
+    len: usize,
+    cap: usize,
+    alloc: PoolAllocator<'pool>,
+    _phantom: PhantomData<T>,
+}
+
+impl<'pool, T> PoolVec<'pool, T> {
+    /// Create an empty vector allocating from `pool`
+    pub fn new_in(pool: &'pool Pool<'pool>) -> Self {
+        PoolVec {
+            ptr: NonNull::dangling(),
+            len: 0,
+            cap: if core::mem::size_of::<T>() == 0 {
+                usize::MAX
+            } else {
+                0
+            },
+            alloc: PoolAllocator::new(pool),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Create an empty vector with room for `capacity` elements
+    pub fn with_capacity_in(capacity: usize, pool: &'pool Pool<'pool>) -> Self {
+        let mut vec = Self::new_in(pool);
+        vec.reserve(capacity);
+        vec
+    }
+
+    /// Number of elements
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the vector has no elements
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of elements the vector can hold without reallocating
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    /// The pool the elements are stored in
+    pub fn pool(&self) -> &'pool Pool<'pool> {
+        self.alloc.pool()
+    }
+
+    /// Make room for at least `additional` more elements
+    pub fn reserve(&mut self, additional: usize) {
+        let required = self.len.checked_add(additional).expect("capacity overflow");
+        if required <= self.cap {
+            return;
+        }
+        let new_cap = required.max(self.cap.saturating_mul(2)).max(4);
+        let layout = Layout::array::<T>(new_cap).expect("capacity overflow");
+        let ptr = self.alloc.allocate_raw(layout).cast::<T>();
+        unsafe { ptr::copy_nonoverlapping(self.ptr.as_ptr(), ptr.as_ptr(), self.len) };
+        self.ptr = ptr;
+        self.cap = new_cap;
+    }
+
+    /// Append an element
+    pub fn push(&mut self, value: T) {
+        if self.len == self.cap {
+            self.reserve(1);
+        }
+        unsafe { self.ptr.as_ptr().add(self.len).write(value) };
+        self.len += 1;
+    }
+
+    /// Remove and return the last element
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        Some(unsafe { self.ptr.as_ptr().add(self.len).read() })
+    }
+
+    /// Shorten the vector to `len` elements, dropping the rest
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        let tail =
+            ptr::slice_from_raw_parts_mut(unsafe { self.ptr.as_ptr().add(len) }, self.len - len);
+        self.len = len;
+        unsafe { ptr::drop_in_place(tail) };
+    }
+
+    /// Remove all elements
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// View the elements as a slice
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { core::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// View the elements as a mutable slice
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { core::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Pointer to the first element, for passing to C
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr.as_ptr()
+    }
+
+    /// Mutable pointer to the first element, for passing to C
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+
+    /// Turn the vector into a slice that lives as long as the pool
+    ///
+    /// The elements will never be dropped.
+    pub fn into_slice(self) -> &'pool mut [T] {
+        let this = core::mem::ManuallyDrop::new(self);
+        unsafe { core::slice::from_raw_parts_mut(this.ptr.as_ptr(), this.len) }
+    }
+}
+
+impl<'pool, T: Clone> PoolVec<'pool, T> {
+    /// Append clones of all elements in `values`
+    pub fn extend_from_slice(&mut self, values: &[T]) {
+        self.reserve(values.len());
+        for value in values {
+            self.push(value.clone());
+        }
+    }
+}
+
+impl<'pool, T> Drop for PoolVec<'pool, T> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.as_mut_slice()) };
+    }
+}
+
+impl<'pool, T> Deref for PoolVec<'pool, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<'pool, T> DerefMut for PoolVec<'pool, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<'pool, T> Extend<T> for PoolVec<'pool, T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<'pool, T: fmt::Debug> fmt::Debug for PoolVec<'pool, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'pool, T: PartialEq> PartialEq<[T]> for PoolVec<'pool, T> {
+    fn eq(&self, other: &[T]) -> bool {
+        self.as_slice() == other
+    }
+}
+
+/// Growable UTF-8 string stored in pool memory
+pub struct PoolStringBuf<'pool> {
+    bytes: PoolVec<'pool, u8>,
+}
+
+impl<'pool> PoolStringBuf<'pool> {
+    /// Create an empty string allocating from `pool`
+    pub fn new_in(pool: &'pool Pool<'pool>) -> Self {
+        PoolStringBuf {
+            bytes: PoolVec::new_in(pool),
+        }
+    }
+
+    /// Create an empty string with room for `capacity` bytes
+    pub fn with_capacity_in(capacity: usize, pool: &'pool Pool<'pool>) -> Self {
+        PoolStringBuf {
+            bytes: PoolVec::with_capacity_in(capacity, pool),
+        }
+    }
+
+    /// Create a string holding a copy of `s`
+    pub fn from_str_in(s: &str, pool: &'pool Pool<'pool>) -> Self {
+        let mut string = Self::with_capacity_in(s.len(), pool);
+        string.push_str(s);
+        string
+    }
+
+    /// Length in bytes
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Whether the string is empty
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Number of bytes the string can hold without reallocating
+    pub fn capacity(&self) -> usize {
+        self.bytes.capacity()
+    }
+
+    /// Append a string slice
+    pub fn push_str(&mut self, s: &str) {
+        self.bytes.extend_from_slice(s.as_bytes());
+    }
+
+    /// Append a character
+    pub fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Remove all contents
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+    }
+
+    /// View the string as a `&str`
+    pub fn as_str(&self) -> &str {
+        unsafe { core::str::from_utf8_unchecked(self.bytes.as_slice()) }
+    }
+
+    /// Turn the string into a `&str` that lives as long as the pool
+    pub fn into_str(self) -> &'pool str {
+        unsafe { core::str::from_utf8_unchecked(self.bytes.into_slice()) }
+    }
+
+    /// NUL-terminate the string and turn it into a C string that lives as
+    /// long as the pool
+    ///
+    /// Fails with [`crate::Status::BadArgument`] if the string contains a NUL byte.
+    pub fn into_c_str(mut self) -> Result<&'pool CStr> {
+        if self.bytes.contains(&0) {
+            return Err(crate::Error::from_status(crate::Status::BadArgument));
+        }
+        self.bytes.push(0);
+        let bytes = self.bytes.into_slice();
+        Ok(unsafe { CStr::from_bytes_with_nul_unchecked(bytes) })
+    }
+
+    /// NUL-terminate the string and return a pointer for passing to C
+    ///
+    /// The pointer stays valid until the pool is cleared or destroyed. If the
+    /// string contains NUL bytes, C code will see it truncated at the first one.
+    pub fn into_c_ptr(mut self) -> *const c_char {
+        self.bytes.push(0);
+        self.bytes.into_slice().as_ptr() as *const c_char
+    }
+}
+
+impl<'pool> Deref for PoolStringBuf<'pool> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<'pool> fmt::Write for PoolStringBuf<'pool> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl<'pool> fmt::Display for PoolStringBuf<'pool> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl<'pool> fmt::Debug for PoolStringBuf<'pool> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl<'pool> PartialEq<str> for PoolStringBuf<'pool> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<'pool> PartialEq<&str> for PoolStringBuf<'pool> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<'pool> From<PoolStringBuf<'pool>> for String {
+    fn from(s: PoolStringBuf<'pool>) -> Self {
+        String::from(s.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::rc::Rc;
+    use core::fmt::Write;
+
+    #[test]
+    fn test_pool_vec_grows() {
+        let pool = Pool::new();
+        let mut vec = PoolVec::new_in(&pool);
+        for i in 0..100u32 {
+            vec.push(i);
+        }
+        assert_eq!(vec.len(), 100);
+        assert!(vec.capacity() >= 100);
+        assert_eq!(vec[42], 42);
+        assert_eq!(vec.pop(), Some(99));
+
+        vec.truncate(3);
+        vec.extend_from_slice(&[7, 8]);
+        assert_eq!(vec, *[0, 1, 2, 7, 8].as_slice());
+        let slice = vec.into_slice();
+        slice[0] = 9;
+        assert_eq!(slice, &[9, 1, 2, 7, 8]);
+    }
+
+    #[test]
+    fn test_pool_vec_drops_elements() {
+        let marker = Rc::new(());
+        let pool = Pool::new();
+        let mut vec = PoolVec::new_in(&pool);
+        vec.extend((0..10).map(|_| marker.clone()));
+        assert_eq!(Rc::strong_count(&marker), 11);
+        vec.truncate(5);
+        assert_eq!(Rc::strong_count(&marker), 6);
+        drop(vec);
+        assert_eq!(Rc::strong_count(&marker), 1);
+    }
+
+    #[test]
+    fn test_pool_vec_alignment() {
+        #[repr(align(64))]
+        struct Aligned(#[allow(dead_code)] u8);
+
+        let pool = Pool::new();
+        let mut vec = PoolVec::new_in(&pool);
+        vec.push(Aligned(1));
+        vec.push(Aligned(2));
+        assert_eq!(vec.as_ptr() as usize % 64, 0);
+
+        let mut units = PoolVec::new_in(&pool);
+        units.push(());
+        assert_eq!(units.len(), 1);
+    }
+
+    #[test]
+    fn test_pool_string() {
+        let pool = Pool::new();
+        let mut s = PoolStringBuf::from_str_in("hello", &pool);
+        s.push(',');
+        write!(s, " world {}!", 42).unwrap();
+        assert_eq!(s, "hello, world 42!");
+
+        let c_str = s.into_c_str().unwrap();
+        assert_eq!(c_str.to_str().unwrap(), "hello, world 42!");
+
+        let mut s = PoolStringBuf::new_in(&pool);
+        s.push_str("a\0b");
+        assert!(s.into_c_str().is_err());
+    }
+
+    #[cfg(apr_nightly)]
+    #[test]
+    fn test_allocator_api() {
+        let pool = Pool::new();
+        let mut vec = alloc::vec::Vec::new_in(PoolAllocator::new(&pool));
+        vec.extend_from_slice(b"pool");
+        assert_eq!(vec, b"pool");
+    }
+}
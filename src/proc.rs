@@ -0,0 +1,709 @@
+//! Process creation and management
+//!
+//! Processes are launched through `apr_proc_create`, exactly as APR-based C
+//! code does, so pool cleanups registered with a child cleanup are run in the
+//! child before it execs.
+
+use crate::file::File;
+use crate::pool::Pool;
+use crate::{Error, Result, Status};
+use alloc::ffi::CString;
+use core::ffi::{c_char, c_int, c_void, CStr};
+use core::marker::PhantomData;
+use core::ptr;
+use std::path::{Path, PathBuf};
+
+/// How the program passed to [`ProcAttr::spawn`] is invoked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmdType {
+    /// Run the command through the shell, with an explicit environment
+    Shell,
+    /// Run the program directly, with an explicit environment
+    Program,
+    /// Run the program directly, inheriting the parent's environment
+    ProgramEnv,
+    /// Search the `PATH` for the program, inheriting the parent's environment
+    ProgramPath,
+    /// Run the command through the shell, inheriting the parent's environment
+    ShellEnv,
+}
+
+impl From<CmdType> for apr_sys::apr_cmdtype_e {
+    fn from(cmdtype: CmdType) -> Self {
+        match cmdtype {
+            CmdType::Shell => apr_sys::apr_cmdtype_e_APR_SHELLCMD,
+            CmdType::Program => apr_sys::apr_cmdtype_e_APR_PROGRAM,
+            CmdType::ProgramEnv => apr_sys::apr_cmdtype_e_APR_PROGRAM_ENV,
+            CmdType::ProgramPath => apr_sys::apr_cmdtype_e_APR_PROGRAM_PATH,
+            CmdType::ShellEnv => apr_sys::apr_cmdtype_e_APR_SHELLCMD_ENV,
+        }
+    }
+}
+
+/// Where one of the child's standard streams is connected
+pub enum Stdio<'a> {
+    /// Inherit the parent's stream
+    Inherit,
+    /// Create a pipe; the parent's end is available on the [`Proc`]
+    Pipe,
+    /// Close the stream in the child
+    Null,
+    /// Connect the stream to an open file
+    File(&'a File),
+}
+
+impl<'a> Stdio<'a> {
+    fn io_kind(&self) -> i32 {
+        match self {
+            Stdio::Inherit | Stdio::File(_) => apr_sys::APR_NO_PIPE as i32,
+            Stdio::Pipe => apr_sys::APR_FULL_BLOCK as i32,
+            Stdio::Null => apr_sys::APR_NO_FILE as i32,
+        }
+    }
+}
+
+/// Conditions under which a subprocess is killed when its pool is cleaned up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KillCondition {
+    /// The process is never sent any signals
+    Never,
+    /// The process is sent SIGKILL on pool cleanup
+    Always,
+    /// SIGTERM, wait 3 seconds, then SIGKILL
+    AfterTimeout,
+    /// Wait forever for the process to complete
+    JustWait,
+    /// Send SIGTERM and then wait
+    OnlyOnce,
+}
+
+impl From<KillCondition> for apr_sys::apr_kill_conditions_e {
+    fn from(how: KillCondition) -> Self {
+        match how {
+            KillCondition::Never => apr_sys::apr_kill_conditions_e_APR_KILL_NEVER,
+            KillCondition::Always => apr_sys::apr_kill_conditions_e_APR_KILL_ALWAYS,
+            KillCondition::AfterTimeout => apr_sys::apr_kill_conditions_e_APR_KILL_AFTER_TIMEOUT,
+            KillCondition::JustWait => apr_sys::apr_kill_conditions_e_APR_JUST_WAIT,
+            KillCondition::OnlyOnce => apr_sys::apr_kill_conditions_e_APR_KILL_ONLY_ONCE,
+        }
+    }
+}
+
+/// Callback invoked in the child if it fails to start the program
+pub type ErrorCallback = dyn Fn(Status, &str);
+
+// Pool userdata key under which the error callback is stored for the child.
+// Each spawn with a callback uses its own subpool, so the key is not shared.
+const ERRFN_KEY: &CStr = c"apr-rs:proc:errfn";
+
+extern "C" fn errfn_trampoline(
+    pool: *mut apr_sys::apr_pool_t,
+    err: apr_sys::apr_status_t,
+    description: *const c_char,
+) {
+    unsafe {
+        let mut data: *mut c_void = ptr::null_mut();
+        apr_sys::apr_pool_userdata_get(&mut data, ERRFN_KEY.as_ptr(), pool);
+        if data.is_null() {
+            return;
+        }
+        let callback = &*(data as *const Box<ErrorCallback>);
+        let description = if description.is_null() {
+            ""
+        } else {
+            CStr::from_ptr(description).to_str().unwrap_or("")
+        };
+        callback(Status::from(err), description);
+    }
+}
+
+extern "C" fn errfn_cleanup(data: *mut c_void) -> apr_sys::apr_status_t {
+    unsafe {
+        drop(Box::from_raw(data as *mut Box<ErrorCallback>));
+    }
+    apr_sys::APR_SUCCESS as apr_sys::apr_status_t
+}
+
+/// Builder for the attributes of a new process
+pub struct ProcAttr<'a> {
+    cmdtype: CmdType,
+    dir: Option<PathBuf>,
+    env: Option<Vec<(String, String)>>,
+    stdin: Stdio<'a>,
+    stdout: Stdio<'a>,
+    stderr: Stdio<'a>,
+    detach: bool,
+    user: Option<(String, Option<String>)>,
+    group: Option<String>,
+    errfn: Option<Box<ErrorCallback>>,
+}
+
+impl<'a> ProcAttr<'a> {
+    /// Create a new set of process attributes
+    ///
+    /// By default the program is run directly, in the current directory, with
+    /// all standard streams inherited from the parent.
+    pub fn new() -> Self {
+        ProcAttr {
+            cmdtype: CmdType::Program,
+            dir: None,
+            env: None,
+            stdin: Stdio::Inherit,
+            stdout: Stdio::Inherit,
+            stderr: Stdio::Inherit,
+            detach: false,
+            user: None,
+            group: None,
+            errfn: None,
+        }
+    }
+
+    /// Set how the program is invoked
+    pub fn cmdtype(mut self, cmdtype: CmdType) -> Self {
+        self.cmdtype = cmdtype;
+        self
+    }
+
+    /// Set the working directory of the child
+    pub fn dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set an environment variable for the child
+    ///
+    /// Only [`CmdType::Program`] and [`CmdType::Shell`] pass an explicit
+    /// environment; the other command types always inherit the parent's.
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        let env = self
+            .env
+            .get_or_insert_with(|| std::env::vars().collect::<Vec<_>>());
+        env.retain(|(k, _)| k != key);
+        env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Start the child with an empty environment
+    pub fn env_clear(mut self) -> Self {
+        self.env = Some(Vec::new());
+        self
+    }
+
+    /// Set where the child's standard input comes from
+    pub fn stdin(mut self, stdin: Stdio<'a>) -> Self {
+        self.stdin = stdin;
+        self
+    }
+
+    /// Set where the child's standard output goes
+    pub fn stdout(mut self, stdout: Stdio<'a>) -> Self {
+        self.stdout = stdout;
+        self
+    }
+
+    /// Set where the child's standard error goes
+    pub fn stderr(mut self, stderr: Stdio<'a>) -> Self {
+        self.stderr = stderr;
+        self
+    }
+
+    /// Detach the child from the controlling terminal
+    pub fn detach(mut self, detach: bool) -> Self {
+        self.detach = detach;
+        self
+    }
+
+    /// Run the child as another user
+    pub fn user(mut self, username: &str, password: Option<&str>) -> Self {
+        self.user = Some((username.to_string(), password.map(|p| p.to_string())));
+        self
+    }
+
+    /// Run the child as another group
+    pub fn group(mut self, groupname: &str) -> Self {
+        self.group = Some(groupname.to_string());
+        self
+    }
+
+    /// Set a callback invoked in the child if starting the program fails
+    ///
+    /// The callback runs in the forked child, before it exits.
+    pub fn error_callback<F: Fn(Status, &str) + 'static>(mut self, callback: F) -> Self {
+        self.errfn = Some(Box::new(callback));
+        self
+    }
+
+    /// Spawn `program` with the given arguments
+    ///
+    /// `args` does not include the program name; it is passed as `argv[0]`.
+    pub fn spawn<'pool>(
+        self,
+        program: &str,
+        args: &[&str],
+        pool: &'pool Pool<'pool>,
+    ) -> Result<Proc<'pool>> {
+        let invalid = || Error::from_status(Status::from(apr_sys::APR_EINVAL as i32));
+        let check = |status: apr_sys::apr_status_t| -> Result<()> {
+            if status != apr_sys::APR_SUCCESS as i32 {
+                return Err(Error::from_status(status.into()));
+            }
+            Ok(())
+        };
+
+        let mut attr: *mut apr_sys::apr_procattr_t = ptr::null_mut();
+        check(unsafe { apr_sys::apr_procattr_create(&mut attr, pool.as_mut_ptr()) })?;
+
+        unsafe {
+            check(apr_sys::apr_procattr_cmdtype_set(attr, self.cmdtype.into()))?;
+            // Report failures such as a missing working directory in the
+            // parent rather than only in the child.
+            check(apr_sys::apr_procattr_error_check_set(attr, 1))?;
+            check(apr_sys::apr_procattr_io_set(
+                attr,
+                self.stdin.io_kind(),
+                self.stdout.io_kind(),
+                self.stderr.io_kind(),
+            ))?;
+            if let Stdio::File(file) = self.stdin {
+                check(apr_sys::apr_procattr_child_in_set(
+                    attr,
+                    file.as_mut_ptr(),
+                    ptr::null_mut(),
+                ))?;
+            }
+            if let Stdio::File(file) = self.stdout {
+                check(apr_sys::apr_procattr_child_out_set(
+                    attr,
+                    file.as_mut_ptr(),
+                    ptr::null_mut(),
+                ))?;
+            }
+            if let Stdio::File(file) = self.stderr {
+                check(apr_sys::apr_procattr_child_err_set(
+                    attr,
+                    file.as_mut_ptr(),
+                    ptr::null_mut(),
+                ))?;
+            }
+            if self.detach {
+                check(apr_sys::apr_procattr_detach_set(attr, 1))?;
+            }
+        }
+
+        if let Some(dir) = &self.dir {
+            let dir = CString::new(dir.to_string_lossy().as_ref()).map_err(|_| invalid())?;
+            // APR copies the directory into the attribute's pool.
+            check(unsafe { apr_sys::apr_procattr_dir_set(attr, dir.as_ptr()) })?;
+        }
+
+        if let Some((username, password)) = &self.user {
+            let username = CString::new(username.as_str()).map_err(|_| invalid())?;
+            let password = password
+                .as_deref()
+                .map(CString::new)
+                .transpose()
+                .map_err(|_| invalid())?;
+            check(unsafe {
+                apr_sys::apr_procattr_user_set(
+                    attr,
+                    username.as_ptr(),
+                    password.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+                )
+            })?;
+        }
+
+        if let Some(groupname) = &self.group {
+            let groupname = CString::new(groupname.as_str()).map_err(|_| invalid())?;
+            check(unsafe { apr_sys::apr_procattr_group_set(attr, groupname.as_ptr()) })?;
+        }
+
+        // APR passes the pool given to apr_proc_create to the error callback.
+        let mut proc_pool = pool.as_mut_ptr();
+        if let Some(errfn) = self.errfn {
+            unsafe {
+                check(apr_sys::apr_pool_create_ex(
+                    &mut proc_pool,
+                    pool.as_mut_ptr(),
+                    None,
+                    ptr::null_mut(),
+                ))?;
+                let data = Box::into_raw(Box::new(errfn)) as *mut c_void;
+                apr_sys::apr_pool_userdata_setn(data, ERRFN_KEY.as_ptr(), None, proc_pool);
+                // The child runs apr_pool_cleanup_for_exec before it calls
+                // the callback, so the box must not be freed by a child
+                // cleanup.
+                apr_sys::apr_pool_cleanup_register(
+                    proc_pool,
+                    data,
+                    Some(errfn_cleanup),
+                    Some(apr_sys::apr_pool_cleanup_null),
+                );
+                check(apr_sys::apr_procattr_child_errfn_set(
+                    attr,
+                    Some(errfn_trampoline),
+                ))?;
+            }
+        }
+
+        let program_c = CString::new(program).map_err(|_| invalid())?;
+        let args_c = args
+            .iter()
+            .map(|arg| CString::new(*arg))
+            .collect::<core::result::Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        let mut argv: Vec<*const c_char> = Vec::with_capacity(args_c.len() + 2);
+        argv.push(program_c.as_ptr());
+        argv.extend(args_c.iter().map(|arg| arg.as_ptr()));
+        argv.push(ptr::null());
+
+        // Commands that take an explicit environment get the parent's unless
+        // one was configured, matching what the *_ENV variants do.
+        let env = match &self.env {
+            Some(env) => env.clone(),
+            None => std::env::vars().collect(),
+        };
+        let env_c = env
+            .iter()
+            .map(|(k, v)| CString::new(format!("{}={}", k, v)))
+            .collect::<core::result::Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        let mut envp: Vec<*const c_char> = env_c.iter().map(|e| e.as_ptr()).collect();
+        envp.push(ptr::null());
+
+        // The apr_proc_t must live in the pool so it can be registered with
+        // apr_pool_note_subprocess.
+        let raw = pool.calloc::<apr_sys::apr_proc_t>();
+        check(unsafe {
+            apr_sys::apr_proc_create(
+                raw,
+                program_c.as_ptr(),
+                argv.as_ptr(),
+                envp.as_ptr(),
+                attr,
+                proc_pool,
+            )
+        })?;
+
+        unsafe {
+            let take = |f: *mut apr_sys::apr_file_t| (!f.is_null()).then(|| File::from_raw(f));
+            Ok(Proc {
+                raw,
+                stdin: take((*raw).in_),
+                stdout: take((*raw).out),
+                stderr: take((*raw).err),
+                _phantom: PhantomData,
+            })
+        }
+    }
+}
+
+impl<'a> Default for ProcAttr<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reason a child process exited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitStatus {
+    code: i32,
+    why: apr_sys::apr_exit_why_e,
+}
+
+impl ExitStatus {
+    /// Whether the process exited normally with status 0
+    pub fn success(&self) -> bool {
+        self.code() == Some(0)
+    }
+
+    /// Exit code, if the process exited normally
+    pub fn code(&self) -> Option<i32> {
+        (self.why & apr_sys::apr_exit_why_e_APR_PROC_EXIT != 0).then_some(self.code)
+    }
+
+    /// Signal that terminated the process, if any
+    pub fn signal(&self) -> Option<i32> {
+        (self.why
+            & (apr_sys::apr_exit_why_e_APR_PROC_SIGNAL
+                | apr_sys::apr_exit_why_e_APR_PROC_SIGNAL_CORE)
+            != 0)
+            .then_some(self.code)
+    }
+
+    /// Whether the process dumped core
+    pub fn core_dumped(&self) -> bool {
+        self.why & apr_sys::apr_exit_why_e_APR_PROC_SIGNAL_CORE != 0
+    }
+}
+
+/// A running or exited child process
+pub struct Proc<'pool> {
+    raw: *mut apr_sys::apr_proc_t,
+    stdin: Option<File>,
+    stdout: Option<File>,
+    stderr: Option<File>,
+    _phantom: PhantomData<&'pool Pool<'pool>>,
+}
+
+impl<'pool> Proc<'pool> {
+    /// Process id of the child
+    pub fn pid(&self) -> i32 {
+        unsafe { (*self.raw).pid }
+    }
+
+    /// Parent's end of the child's stdin pipe
+    pub fn stdin(&mut self) -> Option<&mut File> {
+        self.stdin.as_mut()
+    }
+
+    /// Parent's end of the child's stdout pipe
+    pub fn stdout(&mut self) -> Option<&mut File> {
+        self.stdout.as_mut()
+    }
+
+    /// Parent's end of the child's stderr pipe
+    pub fn stderr(&mut self) -> Option<&mut File> {
+        self.stderr.as_mut()
+    }
+
+    /// Take ownership of the child's stdin pipe, e.g. to close it
+    pub fn take_stdin(&mut self) -> Option<File> {
+        self.stdin.take()
+    }
+
+    /// Take ownership of the child's stdout pipe
+    pub fn take_stdout(&mut self) -> Option<File> {
+        self.stdout.take()
+    }
+
+    /// Take ownership of the child's stderr pipe
+    pub fn take_stderr(&mut self) -> Option<File> {
+        self.stderr.take()
+    }
+
+    fn wait_how(&mut self, how: apr_sys::apr_wait_how_e) -> Result<Option<ExitStatus>> {
+        let mut code: c_int = 0;
+        let mut why: apr_sys::apr_exit_why_e = 0;
+        let status = unsafe { apr_sys::apr_proc_wait(self.raw, &mut code, &mut why, how) };
+        match status as u32 {
+            apr_sys::APR_CHILD_DONE => Ok(Some(ExitStatus { code, why })),
+            apr_sys::APR_CHILD_NOTDONE => Ok(None),
+            _ => Err(Error::from_status(status.into())),
+        }
+    }
+
+    /// Wait for the child to exit
+    ///
+    /// The child's stdin pipe is closed first so it doesn't wait for input.
+    pub fn wait(&mut self) -> Result<ExitStatus> {
+        drop(self.stdin.take());
+        self.wait_how(apr_sys::apr_wait_how_e_APR_WAIT)
+            .map(|status| status.expect("blocking wait returned without a status"))
+    }
+
+    /// Check whether the child has exited, without blocking
+    pub fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        self.wait_how(apr_sys::apr_wait_how_e_APR_NOWAIT)
+    }
+
+    /// Send a signal to the child
+    pub fn kill(&mut self, signal: i32) -> Result<()> {
+        let status = unsafe { apr_sys::apr_proc_kill(self.raw, signal) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(Error::from_status(status.into()));
+        }
+        Ok(())
+    }
+
+    /// Have the pool that created this process terminate it on cleanup
+    pub fn note_subprocess(&self, pool: &'pool Pool<'pool>, how: KillCondition) {
+        unsafe { apr_sys::apr_pool_note_subprocess(pool.as_mut_ptr(), self.raw, how.into()) }
+    }
+
+    /// Get a raw pointer to the underlying process structure
+    pub fn as_ptr(&self) -> *const apr_sys::apr_proc_t {
+        self.raw
+    }
+
+    /// Get a mutable raw pointer to the underlying process structure
+    pub fn as_mut_ptr(&mut self) -> *mut apr_sys::apr_proc_t {
+        self.raw
+    }
+}
+
+/// Wait for any child process to exit
+///
+/// Returns the pid and exit status of the child, or `None` if `wait` is
+/// false and no child has exited yet.
+pub fn wait_all_procs(wait: bool, pool: &Pool<'_>) -> Result<Option<(i32, ExitStatus)>> {
+    let mut proc = core::mem::MaybeUninit::<apr_sys::apr_proc_t>::zeroed();
+    let mut code: c_int = 0;
+    let mut why: apr_sys::apr_exit_why_e = 0;
+    let how = if wait {
+        apr_sys::apr_wait_how_e_APR_WAIT
+    } else {
+        apr_sys::apr_wait_how_e_APR_NOWAIT
+    };
+    let status = unsafe {
+        apr_sys::apr_proc_wait_all_procs(
+            proc.as_mut_ptr(),
+            &mut code,
+            &mut why,
+            how,
+            pool.as_mut_ptr(),
+        )
+    };
+    match status as u32 {
+        apr_sys::APR_CHILD_DONE => {
+            let pid = unsafe { proc.assume_init().pid };
+            Ok(Some((pid, ExitStatus { code, why })))
+        }
+        apr_sys::APR_CHILD_NOTDONE => Ok(None),
+        _ => Err(Error::from_status(status.into())),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn test_spawn_exit_code() {
+        let pool = Pool::new();
+        let mut proc = ProcAttr::new()
+            .cmdtype(CmdType::ProgramPath)
+            .spawn("sh", &["-c", "exit 3"], &pool)
+            .unwrap();
+        assert!(proc.pid() > 0);
+        let status = proc.wait().unwrap();
+        assert_eq!(status.code(), Some(3));
+        assert!(!status.success());
+        assert_eq!(status.signal(), None);
+    }
+
+    #[test]
+    fn test_spawn_pipes() {
+        let pool = Pool::new();
+        let mut proc = ProcAttr::new()
+            .cmdtype(CmdType::ProgramPath)
+            .stdin(Stdio::Pipe)
+            .stdout(Stdio::Pipe)
+            .spawn("cat", &[], &pool)
+            .unwrap();
+
+        let mut stdin = proc.take_stdin().unwrap();
+        stdin.write_all(b"through the pipe").unwrap();
+        drop(stdin);
+
+        let mut output = String::new();
+        proc.stdout().unwrap().read_to_string(&mut output).unwrap();
+        assert_eq!(output, "through the pipe");
+        assert!(proc.stderr().is_none());
+        assert!(proc.wait().unwrap().success());
+    }
+
+    #[test]
+    fn test_spawn_env_and_dir() {
+        let pool = Pool::new();
+        let dir = tempfile::tempdir().unwrap();
+        let mut proc = ProcAttr::new()
+            .cmdtype(CmdType::Shell)
+            .env_clear()
+            .env("APR_RS_TEST", "value")
+            .dir(dir.path())
+            .stdout(Stdio::Pipe)
+            .spawn("echo $APR_RS_TEST; pwd", &[], &pool)
+            .unwrap();
+
+        let mut output = String::new();
+        proc.stdout().unwrap().read_to_string(&mut output).unwrap();
+        let mut lines = output.lines();
+        assert_eq!(lines.next(), Some("value"));
+        assert_eq!(
+            std::fs::canonicalize(lines.next().unwrap()).unwrap(),
+            std::fs::canonicalize(dir.path()).unwrap()
+        );
+        proc.wait().unwrap();
+    }
+
+    #[test]
+    fn test_error_callback() {
+        let pool = Pool::new();
+        let dir = tempfile::tempdir().unwrap();
+        let spawn = |name: &str| {
+            let path = dir.path().join(name);
+            // The missing program is only found out by execvp in the child.
+            ProcAttr::new()
+                .cmdtype(CmdType::ProgramPath)
+                .error_callback(move |_, description| {
+                    std::fs::write(&path, description).unwrap();
+                })
+                .spawn("apr-rs-no-such-program", &[], &pool)
+                .unwrap()
+        };
+        let mut first = spawn("first");
+        let mut second = spawn("second");
+        assert!(!first.wait().unwrap().success());
+        assert!(!second.wait().unwrap().success());
+
+        for name in ["first", "second"] {
+            let description = std::fs::read_to_string(dir.path().join(name)).unwrap();
+            assert!(
+                description.contains("apr-rs-no-such-program"),
+                "{}",
+                description
+            );
+        }
+    }
+
+    #[test]
+    fn test_spawn_stdout_to_file() {
+        let pool = Pool::new();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.txt");
+        let file = File::open(
+            &path,
+            crate::file::OpenFlags::combine(&[
+                crate::file::OpenFlags::WRITE,
+                crate::file::OpenFlags::CREATE,
+            ]),
+            0o644,
+            &pool,
+        )
+        .unwrap();
+
+        let mut proc = ProcAttr::new()
+            .cmdtype(CmdType::ProgramPath)
+            .stdout(Stdio::File(&file))
+            .spawn("echo", &["to a file"], &pool)
+            .unwrap();
+        assert!(proc.stdout().is_none());
+        assert!(proc.wait().unwrap().success());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "to a file\n");
+    }
+
+    #[test]
+    fn test_kill() {
+        let pool = Pool::new();
+        let mut proc = ProcAttr::new()
+            .cmdtype(CmdType::ProgramPath)
+            .spawn("sleep", &["30"], &pool)
+            .unwrap();
+        assert_eq!(proc.try_wait().unwrap(), None);
+        proc.kill(9).unwrap();
+        let status = proc.wait().unwrap();
+        assert_eq!(status.signal(), Some(9));
+        assert_eq!(status.code(), None);
+    }
+
+    #[test]
+    fn test_spawn_missing_dir() {
+        let pool = Pool::new();
+        let result = ProcAttr::new()
+            .cmdtype(CmdType::ProgramPath)
+            .dir("/nonexistent/apr-rs/dir")
+            .spawn("true", &[], &pool);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,289 @@
+//! Relocatable memory management inside shared memory
+//!
+//! An [`Rmm`] carves allocations out of a [`Shm`] segment. Allocations are
+//! identified by [`RmmOffset`]s relative to the start of the segment, so they
+//! remain meaningful in every process that maps it, whatever address the
+//! segment ends up at.
+
+use crate::locks::{GlobalMutex, ProcMutex};
+use crate::shm::{Pod, Shm};
+use crate::{pool::Pool, Result};
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::ptr;
+
+/// Lock serializing access to an [`Rmm`] between processes
+pub trait RmmLock {
+    /// Acquire the lock
+    fn lock(&mut self) -> Result<()>;
+    /// Release the lock
+    fn unlock(&mut self) -> Result<()>;
+}
+
+impl<'a> RmmLock for ProcMutex<'a> {
+    fn lock(&mut self) -> Result<()> {
+        ProcMutex::lock(self)
+    }
+
+    fn unlock(&mut self) -> Result<()> {
+        ProcMutex::unlock(self)
+    }
+}
+
+impl<'a> RmmLock for GlobalMutex<'a> {
+    fn lock(&mut self) -> Result<()> {
+        GlobalMutex::lock(self)
+    }
+
+    fn unlock(&mut self) -> Result<()> {
+        GlobalMutex::unlock(self)
+    }
+}
+
+impl<L: RmmLock + ?Sized> RmmLock for &mut L {
+    fn lock(&mut self) -> Result<()> {
+        (**self).lock()
+    }
+
+    fn unlock(&mut self) -> Result<()> {
+        (**self).unlock()
+    }
+}
+
+/// No locking, for regions only ever used by a single process
+impl RmmLock for () {
+    fn lock(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn unlock(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Position of an allocation relative to the start of an [`Rmm`] region
+///
+/// Offsets can be stored in the shared region itself and used from any
+/// process attached to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct RmmOffset(apr_sys::apr_rmm_off_t);
+
+unsafe impl Pod for RmmOffset {}
+
+impl RmmOffset {
+    /// Wrap a raw `apr_rmm_off_t`
+    ///
+    /// # Safety
+    ///
+    /// `offset` must have been returned by an allocation in the [`Rmm`] it
+    /// is used with, for example one read back from the shared region.
+    pub unsafe fn from_raw(offset: apr_sys::apr_rmm_off_t) -> Self {
+        RmmOffset(offset)
+    }
+
+    /// The raw `apr_rmm_off_t`
+    pub fn into_raw(self) -> apr_sys::apr_rmm_off_t {
+        self.0
+    }
+}
+
+/// Relocatable memory manager over a shared memory segment
+pub struct Rmm<'a, L: RmmLock> {
+    raw: *mut apr_sys::apr_rmm_t,
+    lock: L,
+    owner: bool,
+    _phantom: PhantomData<(&'a Shm<'a>, &'a Pool<'a>)>,
+}
+
+impl<'a, L: RmmLock> Rmm<'a, L> {
+    /// Initialize a new allocator covering all of `shm`
+    ///
+    /// Any existing contents of the segment are overwritten.
+    pub fn init(shm: &'a Shm<'_>, lock: L, pool: &'a Pool<'a>) -> Result<Self> {
+        let mut rmm: *mut apr_sys::apr_rmm_t = ptr::null_mut();
+        let status = unsafe {
+            apr_sys::apr_rmm_init(
+                &mut rmm,
+                ptr::null_mut(),
+                shm.base_addr() as *mut c_void,
+                shm.len(),
+                pool.as_mut_ptr(),
+            )
+        };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+
+        Ok(Rmm {
+            raw: rmm,
+            lock,
+            owner: true,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Attach to an allocator another process initialized in `shm`
+    pub fn attach(shm: &'a Shm<'_>, lock: L, pool: &'a Pool<'a>) -> Result<Self> {
+        let mut rmm: *mut apr_sys::apr_rmm_t = ptr::null_mut();
+        let status = unsafe {
+            apr_sys::apr_rmm_attach(
+                &mut rmm,
+                ptr::null_mut(),
+                shm.base_addr() as *mut c_void,
+                pool.as_mut_ptr(),
+            )
+        };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+
+        Ok(Rmm {
+            raw: rmm,
+            lock,
+            owner: false,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Run `f` with the lock held.
+    fn locked<R>(&mut self, f: impl FnOnce(*mut apr_sys::apr_rmm_t) -> R) -> Result<R> {
+        self.lock.lock()?;
+        let result = f(self.raw);
+        self.lock.unlock()?;
+        Ok(result)
+    }
+
+    fn check(offset: apr_sys::apr_rmm_off_t) -> Result<RmmOffset> {
+        if offset == 0 {
+            return Err(crate::Error::from_status(
+                (apr_sys::APR_ENOMEM as i32).into(),
+            ));
+        }
+        Ok(RmmOffset(offset))
+    }
+
+    /// Allocate `size` bytes
+    pub fn malloc(&mut self, size: usize) -> Result<RmmOffset> {
+        let offset = self.locked(|rmm| unsafe { apr_sys::apr_rmm_malloc(rmm, size) })?;
+        Self::check(offset)
+    }
+
+    /// Allocate `size` zeroed bytes
+    pub fn calloc(&mut self, size: usize) -> Result<RmmOffset> {
+        let offset = self.locked(|rmm| unsafe { apr_sys::apr_rmm_calloc(rmm, size) })?;
+        Self::check(offset)
+    }
+
+    /// Resize the allocation at `offset` to `size` bytes, possibly moving it
+    ///
+    /// # Safety
+    ///
+    /// `offset` must be a live allocation from this region. On success it is
+    /// no longer valid, in this or any other process; use the returned one.
+    pub unsafe fn realloc(&mut self, offset: RmmOffset, size: usize) -> Result<RmmOffset> {
+        let entity = self.addr(offset) as *mut c_void;
+        let offset = self.locked(|rmm| unsafe { apr_sys::apr_rmm_realloc(rmm, entity, size) })?;
+        Self::check(offset)
+    }
+
+    /// Release the allocation at `offset`
+    ///
+    /// # Safety
+    ///
+    /// `offset` must be a live allocation from this region, and must not be
+    /// used again by this or any other process once freed.
+    pub unsafe fn free(&mut self, offset: RmmOffset) -> Result<()> {
+        let status = self.locked(|rmm| unsafe { apr_sys::apr_rmm_free(rmm, offset.0) })?;
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+        Ok(())
+    }
+
+    /// Address of the allocation at `offset` in this process
+    pub fn addr(&self, offset: RmmOffset) -> *mut u8 {
+        unsafe { apr_sys::apr_rmm_addr_get(self.raw, offset.0) as *mut u8 }
+    }
+
+    /// Offset of the allocation at address `ptr`, if it lies inside the region
+    pub fn offset_of(&self, ptr: *const u8) -> Option<RmmOffset> {
+        let offset = unsafe { apr_sys::apr_rmm_offset_get(self.raw, ptr as *mut c_void) };
+        (offset != 0).then_some(RmmOffset(offset))
+    }
+
+    /// Bytes of bookkeeping needed to hold `n` allocations
+    pub fn overhead(n: usize) -> usize {
+        unsafe { apr_sys::apr_rmm_overhead_get(n as core::ffi::c_int) }
+    }
+
+    /// Raw pointer to the underlying APR allocator
+    pub fn as_ptr(&self) -> *const apr_sys::apr_rmm_t {
+        self.raw
+    }
+
+    /// Mutable raw pointer to the underlying APR allocator
+    pub fn as_mut_ptr(&mut self) -> *mut apr_sys::apr_rmm_t {
+        self.raw
+    }
+}
+
+impl<'a, L: RmmLock> Drop for Rmm<'a, L> {
+    fn drop(&mut self) {
+        unsafe {
+            if self.owner {
+                apr_sys::apr_rmm_destroy(self.raw);
+            } else {
+                apr_sys::apr_rmm_detach(self.raw);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locks::LockMech;
+
+    #[test]
+    fn test_rmm_alloc_free() {
+        let pool = Pool::new();
+        let shm = Shm::create_anonymous(4096, &pool).unwrap();
+        let mut rmm = Rmm::init(&shm, (), &pool).unwrap();
+
+        let a = rmm.malloc(16).unwrap();
+        let b = rmm.calloc(32).unwrap();
+        assert_ne!(a, b);
+        assert!(unsafe { core::slice::from_raw_parts(rmm.addr(b), 32) }
+            .iter()
+            .all(|&byte| byte == 0));
+        assert_eq!(rmm.offset_of(rmm.addr(a)), Some(a));
+
+        unsafe { ptr::copy_nonoverlapping(b"shared".as_ptr(), rmm.addr(a), 6) };
+        let a = unsafe { rmm.realloc(a, 256) }.unwrap();
+        assert_eq!(
+            unsafe { core::slice::from_raw_parts(rmm.addr(a), 6) },
+            b"shared"
+        );
+
+        unsafe {
+            rmm.free(a).unwrap();
+            rmm.free(b).unwrap();
+        }
+        assert!(rmm.malloc(1 << 20).is_err());
+    }
+
+    #[test]
+    fn test_rmm_with_proc_mutex() {
+        let pool = Pool::new();
+        let shm = Shm::create_anonymous(4096, &pool).unwrap();
+        let mutex = ProcMutex::new(None, LockMech::Default, &pool).unwrap();
+        let mut rmm = Rmm::init(&shm, mutex, &pool).unwrap();
+
+        let offset = rmm.malloc(8).unwrap();
+        let raw = offset.into_raw();
+        let offset = unsafe { RmmOffset::from_raw(raw) };
+        unsafe { rmm.free(offset) }.unwrap();
+        assert!(Rmm::<()>::overhead(1) > 0);
+    }
+}
@@ -0,0 +1,289 @@
+//! Shared memory segments
+//!
+//! A [`Shm`] segment is either anonymous, in which case it is shared with
+//! child processes created after it, or backed by a name in the filesystem
+//! that unrelated processes can [`Shm::attach`] to. Coordinate access between
+//! processes with a [`ProcMutex`](crate::locks::ProcMutex) or
+//! [`GlobalMutex`](crate::locks::GlobalMutex).
+
+use crate::{pool::Pool, Result};
+use alloc::ffi::CString;
+use core::marker::PhantomData;
+use core::ptr;
+use std::path::Path;
+
+/// Types that can be safely viewed in shared memory
+///
+/// # Safety
+///
+/// Implementors must be valid for every bit pattern, contain no pointers or
+/// references, and have no padding, since the bytes may be written by
+/// another process.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($t:ty),*) => { $( unsafe impl Pod for $t {} )* };
+}
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+fn path_cstring(path: &Path) -> Result<CString> {
+    CString::new(path.to_string_lossy().as_ref())
+        .map_err(|_| crate::Error::from_status((apr_sys::APR_EINVAL as i32).into()))
+}
+
+/// Shared memory segment
+pub struct Shm<'pool> {
+    raw: *mut apr_sys::apr_shm_t,
+    attached: bool,
+    _phantom: PhantomData<&'pool Pool<'pool>>,
+}
+
+unsafe impl<'pool> Send for Shm<'pool> {}
+
+impl<'pool> Shm<'pool> {
+    fn create_raw(
+        size: usize,
+        filename: *const core::ffi::c_char,
+        pool: &'pool Pool,
+    ) -> Result<Self> {
+        let mut shm: *mut apr_sys::apr_shm_t = ptr::null_mut();
+        let status =
+            unsafe { apr_sys::apr_shm_create(&mut shm, size, filename, pool.as_mut_ptr()) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+
+        Ok(Shm {
+            raw: shm,
+            attached: false,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Create an anonymous segment of `size` bytes, inherited by child processes
+    pub fn create_anonymous(size: usize, pool: &'pool Pool) -> Result<Self> {
+        Self::create_raw(size, ptr::null(), pool)
+    }
+
+    /// Create a segment of `size` bytes that other processes can attach to by `path`
+    pub fn create<P: AsRef<Path>>(size: usize, path: P, pool: &'pool Pool) -> Result<Self> {
+        let c_path = path_cstring(path.as_ref())?;
+        Self::create_raw(size, c_path.as_ptr(), pool)
+    }
+
+    /// Attach to a segment previously created by [`Shm::create`]
+    pub fn attach<P: AsRef<Path>>(path: P, pool: &'pool Pool) -> Result<Self> {
+        let c_path = path_cstring(path.as_ref())?;
+        let mut shm: *mut apr_sys::apr_shm_t = ptr::null_mut();
+        let status =
+            unsafe { apr_sys::apr_shm_attach(&mut shm, c_path.as_ptr(), pool.as_mut_ptr()) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+
+        Ok(Shm {
+            raw: shm,
+            attached: true,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Remove the named segment at `path`, e.g. one left over from a crash
+    pub fn remove<P: AsRef<Path>>(path: P, pool: &Pool) -> Result<()> {
+        let c_path = path_cstring(path.as_ref())?;
+        let status = unsafe { apr_sys::apr_shm_remove(c_path.as_ptr(), pool.as_mut_ptr()) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+        Ok(())
+    }
+
+    /// Detach from (or, if this process created it, destroy) the segment
+    ///
+    /// This happens automatically on drop; call it to observe errors.
+    pub fn detach(mut self) -> Result<()> {
+        let status = self.release();
+        self.raw = ptr::null_mut();
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+        Ok(())
+    }
+
+    fn release(&mut self) -> apr_sys::apr_status_t {
+        if self.raw.is_null() {
+            return apr_sys::APR_SUCCESS as apr_sys::apr_status_t;
+        }
+        unsafe {
+            if self.attached {
+                apr_sys::apr_shm_detach(self.raw)
+            } else {
+                apr_sys::apr_shm_destroy(self.raw)
+            }
+        }
+    }
+
+    /// Size of the segment in bytes
+    pub fn len(&self) -> usize {
+        unsafe { apr_sys::apr_shm_size_get(self.raw) }
+    }
+
+    /// Whether the segment is zero bytes long
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Address of the segment in this process
+    pub fn base_addr(&self) -> *mut u8 {
+        unsafe { apr_sys::apr_shm_baseaddr_get(self.raw) as *mut u8 }
+    }
+
+    /// View the segment as bytes
+    ///
+    /// # Safety
+    ///
+    /// Nothing may write to the segment while the slice is in use: neither a
+    /// [`ShmCell`] in this process nor another process attached to it. Hold
+    /// a cross-process lock that every writer also takes.
+    pub unsafe fn as_bytes(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.base_addr(), self.len()) }
+    }
+
+    /// View the segment as mutable bytes
+    ///
+    /// # Safety
+    ///
+    /// No other process may access the segment while the slice is in use;
+    /// hold a cross-process lock that every other user also takes.
+    pub unsafe fn as_bytes_mut(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.base_addr(), self.len()) }
+    }
+
+    /// Typed view of the `T` stored at byte `offset` in the segment
+    ///
+    /// Fails with `APR_EINVAL` if the value would not fit in the segment or
+    /// `offset` is not suitably aligned for `T`.
+    pub fn cell<T: Pod>(&self, offset: usize) -> Result<ShmCell<'_, T>> {
+        let fits = offset
+            .checked_add(core::mem::size_of::<T>())
+            .is_some_and(|end| end <= self.len());
+        let ptr = self.base_addr().wrapping_add(offset) as *mut T;
+        if !fits || !ptr.is_aligned() {
+            return Err(crate::Error::from_status(
+                (apr_sys::APR_EINVAL as i32).into(),
+            ));
+        }
+
+        Ok(ShmCell {
+            ptr,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Raw pointer to the underlying APR segment
+    pub fn as_ptr(&self) -> *const apr_sys::apr_shm_t {
+        self.raw
+    }
+
+    /// Mutable raw pointer to the underlying APR segment
+    pub fn as_mut_ptr(&mut self) -> *mut apr_sys::apr_shm_t {
+        self.raw
+    }
+}
+
+impl<'pool> Drop for Shm<'pool> {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// Typed view of a value inside a [`Shm`] segment
+///
+/// Values are read and written with volatile accesses, since another process
+/// may change them at any time.
+pub struct ShmCell<'shm, T: Pod> {
+    ptr: *mut T,
+    _phantom: PhantomData<&'shm Shm<'shm>>,
+}
+
+impl<'shm, T: Pod> ShmCell<'shm, T> {
+    /// Read the current value
+    pub fn get(&self) -> T {
+        unsafe { ptr::read_volatile(self.ptr) }
+    }
+
+    /// Overwrite the value
+    pub fn set(&self, value: T) {
+        unsafe { ptr::write_volatile(self.ptr, value) }
+    }
+
+    /// Read, modify and write back the value
+    ///
+    /// This is not atomic; hold a cross-process lock if other processes may
+    /// update the value concurrently.
+    pub fn update(&self, f: impl FnOnce(T) -> T) -> T {
+        let value = f(self.get());
+        self.set(value);
+        value
+    }
+
+    /// Raw pointer to the value, for sharing with C code
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anonymous_shm() {
+        let pool = Pool::new();
+        let mut shm = Shm::create_anonymous(1024, &pool).unwrap();
+        assert_eq!(shm.len(), 1024);
+
+        // The segment is anonymous and has no other users.
+        unsafe {
+            shm.as_bytes_mut()[..5].copy_from_slice(b"hello");
+            assert_eq!(&shm.as_bytes()[..5], b"hello");
+        }
+    }
+
+    #[test]
+    fn test_shm_cell() {
+        let pool = Pool::new();
+        let shm = Shm::create_anonymous(64, &pool).unwrap();
+
+        let counter = shm.cell::<u64>(8).unwrap();
+        counter.set(41);
+        assert_eq!(counter.update(|n| n + 1), 42);
+        assert_eq!(shm.cell::<u64>(8).unwrap().get(), 42);
+
+        let scoreboard = shm.cell::<[u32; 4]>(16).unwrap();
+        scoreboard.set([1, 2, 3, 4]);
+        assert_eq!(scoreboard.get()[2], 3);
+
+        assert!(shm.cell::<u64>(60).is_err());
+        assert!(shm.cell::<u64>(3).is_err());
+    }
+
+    #[test]
+    fn test_named_shm_attach() {
+        let pool = Pool::new();
+        let path = std::env::temp_dir().join(format!("apr-rs-shm-{}", std::process::id()));
+        let _ = Shm::remove(&path, &pool);
+
+        let shm = Shm::create(128, &path, &pool).unwrap();
+        shm.cell::<u32>(0).unwrap().set(0xdead_beef);
+
+        if let Ok(attached) = Shm::attach(&path, &pool) {
+            assert_eq!(attached.cell::<u32>(0).unwrap().get(), 0xdead_beef);
+            attached.detach().unwrap();
+        }
+
+        shm.detach().unwrap();
+        let _ = Shm::remove(&path, &pool);
+    }
+}
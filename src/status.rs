@@ -97,6 +97,128 @@ pub enum Status {
     Busy, // APR_EBUSY
     /// The process is not recognized by the system.
     ProcessUnknown, // APR_EPROC_UNKNOWN
+
+    /// An error reported by the operating system, holding the native error
+    /// code (an `errno` value on Unix).
+    Os(i32),
+    /// Any other status code, such as an APR-util or resolver error, kept
+    /// verbatim.
+    Other(StatusCode),
+}
+
+/// Extract the native error code from an OS error status (`APR_TO_OS_ERROR`).
+fn to_os_error(status: StatusCode) -> Option<i32> {
+    #[cfg(unix)]
+    {
+        (status != apr_sys::APR_SUCCESS && status < apr_sys::APR_OS_START_ERROR)
+            .then_some(status as i32)
+    }
+    #[cfg(not(unix))]
+    {
+        status
+            .checked_sub(apr_sys::APR_OS_START_SYSERR)
+            .map(|code| code as i32)
+    }
+}
+
+/// Build the status for a native error code (`APR_FROM_OS_ERROR`).
+fn from_os_error(code: i32) -> StatusCode {
+    #[cfg(unix)]
+    {
+        code as StatusCode
+    }
+    #[cfg(not(unix))]
+    {
+        code as StatusCode + apr_sys::APR_OS_START_SYSERR
+    }
+}
+
+/// Windows system and Winsock error codes that apr_errno.h folds into the
+/// portable `APR_STATUS_IS_*` tests, as `APR_OS_START_SYSERR + code`.
+mod win {
+    pub const ERROR_INVALID_FUNCTION: u32 = 1;
+    pub const ERROR_FILE_NOT_FOUND: u32 = 2;
+    pub const ERROR_PATH_NOT_FOUND: u32 = 3;
+    pub const ERROR_TOO_MANY_OPEN_FILES: u32 = 4;
+    pub const ERROR_ACCESS_DENIED: u32 = 5;
+    pub const ERROR_INVALID_HANDLE: u32 = 6;
+    pub const ERROR_ARENA_TRASHED: u32 = 7;
+    pub const ERROR_NOT_ENOUGH_MEMORY: u32 = 8;
+    pub const ERROR_INVALID_BLOCK: u32 = 9;
+    pub const ERROR_INVALID_ACCESS: u32 = 12;
+    pub const ERROR_INVALID_DATA: u32 = 13;
+    pub const ERROR_OUTOFMEMORY: u32 = 14;
+    pub const ERROR_INVALID_DRIVE: u32 = 15;
+    pub const ERROR_CURRENT_DIRECTORY: u32 = 16;
+    pub const ERROR_NOT_SAME_DEVICE: u32 = 17;
+    pub const ERROR_NO_MORE_FILES: u32 = 18;
+    pub const ERROR_SHARING_VIOLATION: u32 = 32;
+    pub const ERROR_LOCK_VIOLATION: u32 = 33;
+    pub const ERROR_BAD_NETPATH: u32 = 53;
+    pub const ERROR_NETWORK_ACCESS_DENIED: u32 = 65;
+    pub const ERROR_BAD_NET_NAME: u32 = 67;
+    pub const ERROR_FILE_EXISTS: u32 = 80;
+    pub const ERROR_CANNOT_MAKE: u32 = 82;
+    pub const ERROR_FAIL_I24: u32 = 83;
+    pub const ERROR_INVALID_PARAMETER: u32 = 87;
+    pub const ERROR_NO_PROC_SLOTS: u32 = 89;
+    pub const ERROR_DRIVE_LOCKED: u32 = 108;
+    pub const ERROR_BROKEN_PIPE: u32 = 109;
+    pub const ERROR_OPEN_FAILED: u32 = 110;
+    pub const ERROR_DISK_FULL: u32 = 112;
+    pub const ERROR_INVALID_TARGET_HANDLE: u32 = 114;
+    pub const ERROR_NEGATIVE_SEEK: u32 = 131;
+    pub const ERROR_DIR_NOT_EMPTY: u32 = 145;
+    pub const ERROR_NOT_LOCKED: u32 = 158;
+    pub const ERROR_BAD_PATHNAME: u32 = 161;
+    pub const ERROR_MAX_THRDS_REACHED: u32 = 164;
+    pub const ERROR_LOCK_FAILED: u32 = 167;
+    pub const ERROR_ALREADY_EXISTS: u32 = 183;
+    pub const ERROR_FILENAME_EXCED_RANGE: u32 = 206;
+    pub const ERROR_NESTING_NOT_ALLOWED: u32 = 215;
+    pub const ERROR_NO_DATA: u32 = 232;
+    pub const WAIT_TIMEOUT: u32 = 258;
+    pub const ERROR_DIRECTORY: u32 = 267;
+    pub const ERROR_NOT_ENOUGH_QUOTA: u32 = 1816;
+    pub const WSAEINTR: u32 = 10004;
+    pub const WSAEWOULDBLOCK: u32 = 10035;
+    pub const WSAEINPROGRESS: u32 = 10036;
+    pub const WSAENETUNREACH: u32 = 10051;
+    pub const WSAECONNABORTED: u32 = 10053;
+    pub const WSAECONNRESET: u32 = 10054;
+    pub const WSAETIMEDOUT: u32 = 10060;
+    pub const WSAECONNREFUSED: u32 = 10061;
+    pub const WSAENAMETOOLONG: u32 = 10063;
+    pub const WSAEHOSTUNREACH: u32 = 10065;
+}
+
+/// Whether `code` is one of the Windows `errors` as reported by APR.
+#[cfg(windows)]
+fn is_windows_error(code: StatusCode, errors: &[u32]) -> bool {
+    errors
+        .iter()
+        .any(|&error| code == apr_sys::APR_OS_START_SYSERR + error)
+}
+
+#[cfg(not(windows))]
+fn is_windows_error(_code: StatusCode, _errors: &[u32]) -> bool {
+    false
+}
+
+macro_rules! status_predicates {
+    ($(
+        $(#[$meta:meta])*
+        $name:ident => $($code:ident)|+ $(, windows: $($win:ident)|+)?;
+    )*) => {
+        $(
+            $(#[$meta])*
+            pub fn $name(&self) -> bool {
+                let code = self.code();
+                $(code == apr_sys::$code)||+
+                    $(|| is_windows_error(code, &[$(win::$win),+]))?
+            }
+        )*
+    };
 }
 
 impl Status {
@@ -110,31 +232,124 @@ impl Status {
         !self.is_success()
     }
 
+    /// Get the raw `apr_status_t` value
+    pub fn code(&self) -> StatusCode {
+        (*self).into()
+    }
+
     /// Get the raw OS error code, if available
     pub fn raw_os_error(&self) -> Option<i32> {
-        let status_code: u32 = (*self).into();
         match self {
-            Status::Success => None,
-            _ if status_code >= apr_sys::APR_OS_START_SYSERR => {
-                Some((status_code - apr_sys::APR_OS_START_SYSERR) as i32)
-            }
+            Status::Os(code) => Some(*code),
             _ => None,
         }
     }
 
     /// Get the error message for this status code
     pub fn strerror(&self) -> String {
-        let status_code: u32 = (*self).into();
-        let buf = unsafe {
-            let mut buf = [0u8; 1024];
+        let mut buf = [0u8; 1024];
+        unsafe {
             apr_sys::apr_strerror(
-                status_code as apr_sys::apr_status_t,
+                self.code() as apr_sys::apr_status_t,
                 buf.as_mut_ptr() as *mut core::ffi::c_char,
                 buf.len(),
             );
-            buf
-        };
-        String::from_utf8_lossy(&buf).to_string()
+        }
+        let msg = core::ffi::CStr::from_bytes_until_nul(&buf).unwrap_or_default();
+        String::from_utf8_lossy(msg.to_bytes()).to_string()
+    }
+
+    // The APR_STATUS_IS_* family. On Windows APR reports OS errors as
+    // APR_OS_START_SYSERR + GetLastError(), so the Windows codes listed in
+    // apr_errno.h match as well.
+    status_predicates! {
+        /// Whether the file or directory does not exist (`APR_STATUS_IS_ENOENT`)
+        is_enoent => APR_ENOENT,
+            windows: ERROR_FILE_NOT_FOUND | ERROR_PATH_NOT_FOUND | ERROR_OPEN_FAILED
+                | ERROR_NO_MORE_FILES;
+        /// Whether permission was denied (`APR_STATUS_IS_EACCES`)
+        is_eacces => APR_EACCES,
+            windows: ERROR_ACCESS_DENIED | ERROR_CANNOT_MAKE | ERROR_CURRENT_DIRECTORY
+                | ERROR_DRIVE_LOCKED | ERROR_FAIL_I24 | ERROR_LOCK_VIOLATION | ERROR_LOCK_FAILED
+                | ERROR_NOT_LOCKED | ERROR_NETWORK_ACCESS_DENIED | ERROR_SHARING_VIOLATION;
+        /// Whether the file already exists (`APR_STATUS_IS_EEXIST`)
+        is_eexist => APR_EEXIST,
+            windows: ERROR_FILE_EXISTS | ERROR_ALREADY_EXISTS;
+        /// Whether a path component is not a directory (`APR_STATUS_IS_ENOTDIR`)
+        is_enotdir => APR_ENOTDIR,
+            windows: ERROR_PATH_NOT_FOUND | ERROR_BAD_NETPATH | ERROR_BAD_NET_NAME
+                | ERROR_BAD_PATHNAME | ERROR_INVALID_DRIVE | ERROR_DIRECTORY;
+        /// Whether the directory is not empty (`APR_STATUS_IS_ENOTEMPTY`)
+        is_enotempty => APR_ENOTEMPTY,
+            windows: ERROR_DIR_NOT_EMPTY;
+        /// Whether the path is too long (`APR_STATUS_IS_ENAMETOOLONG`)
+        is_enametoolong => APR_ENAMETOOLONG,
+            windows: ERROR_FILENAME_EXCED_RANGE | WSAENAMETOOLONG;
+        /// Whether the device has no space left (`APR_STATUS_IS_ENOSPC`)
+        is_enospc => APR_ENOSPC,
+            windows: ERROR_DISK_FULL;
+        /// Whether memory ran out (`APR_STATUS_IS_ENOMEM`)
+        is_enomem => APR_ENOMEM,
+            windows: ERROR_ARENA_TRASHED | ERROR_NOT_ENOUGH_MEMORY | ERROR_INVALID_BLOCK
+                | ERROR_NOT_ENOUGH_QUOTA | ERROR_OUTOFMEMORY;
+        /// Whether the process has too many open files (`APR_STATUS_IS_EMFILE`)
+        is_emfile => APR_EMFILE,
+            windows: ERROR_TOO_MANY_OPEN_FILES;
+        /// Whether the system has too many open files (`APR_STATUS_IS_ENFILE`)
+        is_enfile => APR_ENFILE;
+        /// Whether a file descriptor was invalid (`APR_STATUS_IS_EBADF`)
+        is_ebadf => APR_EBADF,
+            windows: ERROR_INVALID_HANDLE | ERROR_INVALID_TARGET_HANDLE;
+        /// Whether an argument was invalid (`APR_STATUS_IS_EINVAL`)
+        is_einval => APR_EINVAL,
+            windows: ERROR_INVALID_ACCESS | ERROR_INVALID_DATA | ERROR_INVALID_FUNCTION
+                | ERROR_INVALID_HANDLE | ERROR_INVALID_PARAMETER | ERROR_NEGATIVE_SEEK;
+        /// Whether a rename crossed devices (`APR_STATUS_IS_EXDEV`)
+        is_exdev => APR_EXDEV,
+            windows: ERROR_NOT_SAME_DEVICE;
+        /// Whether the operation would block (`APR_STATUS_IS_EAGAIN`)
+        is_eagain => APR_EAGAIN,
+            windows: ERROR_NO_DATA | ERROR_NO_PROC_SLOTS | ERROR_NESTING_NOT_ALLOWED
+                | ERROR_MAX_THRDS_REACHED | ERROR_LOCK_VIOLATION | WSAEWOULDBLOCK;
+        /// Whether the call was interrupted by a signal (`APR_STATUS_IS_EINTR`)
+        is_eintr => APR_EINTR,
+            windows: WSAEINTR;
+        /// Whether the other end of a pipe was closed (`APR_STATUS_IS_EPIPE`)
+        is_epipe => APR_EPIPE,
+            windows: ERROR_BROKEN_PIPE;
+        /// Whether a connection was refused (`APR_STATUS_IS_ECONNREFUSED`)
+        is_econnrefused => APR_ECONNREFUSED,
+            windows: WSAECONNREFUSED;
+        /// Whether a connection was aborted (`APR_STATUS_IS_ECONNABORTED`)
+        is_econnaborted => APR_ECONNABORTED,
+            windows: WSAECONNABORTED;
+        /// Whether a connection was reset by the peer (`APR_STATUS_IS_ECONNRESET`)
+        is_econnreset => APR_ECONNRESET,
+            windows: WSAECONNRESET;
+        /// Whether a non-blocking connect is still in progress (`APR_STATUS_IS_EINPROGRESS`)
+        is_einprogress => APR_EINPROGRESS,
+            windows: WSAEINPROGRESS;
+        /// Whether a network operation timed out (`APR_STATUS_IS_ETIMEDOUT`)
+        is_etimedout => APR_ETIMEDOUT,
+            windows: WSAETIMEDOUT | WAIT_TIMEOUT;
+        /// Whether the host is unreachable (`APR_STATUS_IS_EHOSTUNREACH`)
+        is_ehostunreach => APR_EHOSTUNREACH,
+            windows: WSAEHOSTUNREACH;
+        /// Whether the network is unreachable (`APR_STATUS_IS_ENETUNREACH`)
+        is_enetunreach => APR_ENETUNREACH,
+            windows: WSAENETUNREACH;
+        /// Whether an APR timeout expired (`APR_STATUS_IS_TIMEUP`)
+        is_timeup => APR_TIMEUP;
+        /// Whether the end of file was reached (`APR_STATUS_IS_EOF`)
+        is_eof => APR_EOF;
+        /// Whether the operation was incomplete (`APR_STATUS_IS_INCOMPLETE`)
+        is_incomplete => APR_INCOMPLETE;
+        /// Whether the operation is not implemented (`APR_STATUS_IS_ENOTIMPL`)
+        is_enotimpl => APR_ENOTIMPL;
+        /// Whether the resource is busy (`APR_STATUS_IS_EBUSY`)
+        is_ebusy => APR_EBUSY;
+        /// Whether the requested item was not found (`APR_STATUS_IS_NOTFOUND`)
+        is_notfound => APR_NOTFOUND;
     }
 }
 
@@ -187,9 +402,12 @@ impl From<u32> for Status {
             apr_sys::APR_EBUSY => Status::Busy,
             apr_sys::APR_EPROC_UNKNOWN => Status::ProcessUnknown,
 
-            // For unknown or OS-specific error codes, return a General error
-            // APR maps OS errors into its status space
-            _ => Status::General,
+            // Anything else keeps its code, so it can still be passed back
+            // to APR or reported with the right message.
+            _ => match to_os_error(status) {
+                Some(code) => Status::Os(code),
+                None => Status::Other(status),
+            },
         }
     }
 }
@@ -251,6 +469,8 @@ impl From<Status> for u32 {
             Status::AboveRoot => apr_sys::APR_EABOVEROOT,
             Status::Busy => apr_sys::APR_EBUSY,
             Status::ProcessUnknown => apr_sys::APR_EPROC_UNKNOWN,
+            Status::Os(code) => from_os_error(code),
+            Status::Other(code) => code,
         }
     }
 }
@@ -264,14 +484,45 @@ impl From<i32> for Status {
 #[cfg(feature = "std")]
 impl From<std::io::ErrorKind> for Status {
     fn from(kind: std::io::ErrorKind) -> Self {
-        (kind as u32).into()
+        use std::io::ErrorKind;
+        let code = match kind {
+            ErrorKind::NotFound => apr_sys::APR_ENOENT,
+            ErrorKind::PermissionDenied => apr_sys::APR_EACCES,
+            ErrorKind::AlreadyExists => apr_sys::APR_EEXIST,
+            ErrorKind::WouldBlock => apr_sys::APR_EAGAIN,
+            ErrorKind::Interrupted => apr_sys::APR_EINTR,
+            ErrorKind::InvalidInput => apr_sys::APR_EINVAL,
+            ErrorKind::BrokenPipe => apr_sys::APR_EPIPE,
+            ErrorKind::ConnectionRefused => apr_sys::APR_ECONNREFUSED,
+            ErrorKind::ConnectionReset => apr_sys::APR_ECONNRESET,
+            ErrorKind::ConnectionAborted => apr_sys::APR_ECONNABORTED,
+            ErrorKind::TimedOut => apr_sys::APR_ETIMEDOUT,
+            ErrorKind::OutOfMemory => apr_sys::APR_ENOMEM,
+            ErrorKind::UnexpectedEof => apr_sys::APR_EOF,
+            ErrorKind::Unsupported => apr_sys::APR_ENOTIMPL,
+            _ => apr_sys::APR_EGENERAL,
+        };
+        code.into()
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<&std::io::Error> for Status {
+    fn from(error: &std::io::Error) -> Self {
+        if let Some(code) = error.raw_os_error() {
+            return Status::Os(code);
+        }
+        if let Some(status) = error.get_ref().and_then(|e| e.downcast_ref::<Status>()) {
+            return *status;
+        }
+        error.kind().into()
     }
 }
 
 #[cfg(feature = "std")]
 impl From<std::io::Error> for Status {
     fn from(error: std::io::Error) -> Self {
-        error.kind().into()
+        (&error).into()
     }
 }
 
@@ -279,11 +530,15 @@ impl From<std::io::Error> for Status {
 impl From<Status> for std::io::Error {
     fn from(status: Status) -> Self {
         let kind = match status {
+            // std knows how to classify native error codes, and keeps the
+            // code available through raw_os_error().
+            Status::Os(code) => return std::io::Error::from_raw_os_error(code),
             Status::NotFound | Status::NoDirectory => std::io::ErrorKind::NotFound,
             Status::BadArgument | Status::InvalidSocket => std::io::ErrorKind::InvalidInput,
             Status::Eof => std::io::ErrorKind::UnexpectedEof,
             Status::Busy => std::io::ErrorKind::ResourceBusy,
             Status::TimeUp => std::io::ErrorKind::TimedOut,
+            Status::NotImplemented => std::io::ErrorKind::Unsupported,
             _ => return std::io::Error::other(status),
         };
 
@@ -347,4 +602,68 @@ mod tests {
         // Should contain the APR_NOTFOUND value, not the discriminant
         assert!(display.contains(&format!("({})", apr_sys::APR_NOTFOUND)));
     }
+
+    #[test]
+    fn test_os_error_roundtrip() {
+        let status = Status::from(apr_sys::APR_ENOENT);
+        assert!(matches!(status, Status::Os(_)));
+        assert_eq!(status.code(), apr_sys::APR_ENOENT);
+        assert!(status.is_enoent());
+        assert!(!status.is_eacces());
+        #[cfg(unix)]
+        assert_eq!(status.raw_os_error(), Some(apr_sys::APR_ENOENT as i32));
+        assert!(status.strerror().contains("No such file or directory"));
+        assert!(!status.strerror().contains('\0'));
+    }
+
+    #[test]
+    fn test_unknown_status_preserved() {
+        let code = apr_sys::APR_UTIL_START_STATUS + 1;
+        let status = Status::from(code);
+        assert_eq!(status, Status::Other(code));
+        assert_eq!(status.code(), code);
+        assert_eq!(status.raw_os_error(), None);
+    }
+
+    #[test]
+    fn test_status_predicates() {
+        assert!(Status::TimeUp.is_timeup());
+        assert!(Status::Eof.is_eof());
+        assert!(!Status::Eof.is_timeup());
+        assert!(Status::from(apr_sys::APR_EAGAIN).is_eagain());
+        assert!(Status::from(apr_sys::APR_EINTR as i32).is_eintr());
+        assert!(!Status::Success.is_enoent());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_io_error_kind() {
+        use std::io::ErrorKind;
+
+        let err = std::io::Error::from(Status::from(apr_sys::APR_ENOENT));
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        let err = std::io::Error::from(Status::from(apr_sys::APR_EACCES));
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert_eq!(Status::from(err), Status::from(apr_sys::APR_EACCES));
+
+        let err = std::io::Error::from(Status::TimeUp);
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert_eq!(Status::from(err), Status::TimeUp);
+
+        assert!(Status::from(ErrorKind::NotFound).is_enoent());
+        assert!(Status::from(ErrorKind::WouldBlock).is_eagain());
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_status_predicates_windows() {
+        let os = |code: u32| Status::from(apr_sys::APR_OS_START_SYSERR + code);
+        assert!(os(win::ERROR_FILE_NOT_FOUND).is_enoent());
+        assert!(os(win::ERROR_PATH_NOT_FOUND).is_enoent());
+        assert!(os(win::ERROR_PATH_NOT_FOUND).is_enotdir());
+        assert!(os(win::ERROR_ACCESS_DENIED).is_eacces());
+        assert!(os(win::ERROR_ALREADY_EXISTS).is_eexist());
+        assert!(os(win::WSAEWOULDBLOCK).is_eagain());
+        assert!(!os(win::ERROR_FILE_NOT_FOUND).is_eacces());
+    }
 }
@@ -4,8 +4,10 @@
 //! (dynamic arrays) data structures.
 
 use crate::pool::Pool;
+use crate::strings::BStr;
 use alloc::ffi::CString;
 use alloc::string::String;
+use alloc::vec::Vec;
 pub use apr_sys::{apr_array_header_t, apr_table_t};
 use core::ffi::{c_char, c_void, CStr};
 use core::marker::PhantomData;
@@ -67,6 +69,74 @@ impl<'pool> Array<'pool> {
         elts.add(index * header.elt_size as usize) as *mut c_void
     }
 
+    /// Remove the last element, returning a pointer to it.
+    ///
+    /// Returns null if the array is empty. The pointed-to bytes stay valid
+    /// until the next push.
+    ///
+    /// # Safety
+    /// The caller must know the element type to make use of the pointer.
+    pub unsafe fn pop_raw(&mut self) -> *mut c_void {
+        apr_sys::apr_array_pop(self.ptr)
+    }
+
+    /// Get the size of each element in bytes.
+    pub fn elt_size(&self) -> usize {
+        unsafe { (*self.ptr).elt_size as usize }
+    }
+
+    /// Append the elements of `src` to this array.
+    ///
+    /// # Panics
+    /// Panics if the arrays have different element sizes.
+    pub fn cat(&mut self, src: &Array<'_>) {
+        assert_eq!(
+            self.elt_size(),
+            src.elt_size(),
+            "Array element size mismatch"
+        );
+        unsafe {
+            apr_sys::apr_array_cat(self.ptr, src.ptr);
+        }
+    }
+
+    /// Copy the array and its elements into `pool`.
+    pub fn copy<'a>(&self, pool: &'a Pool<'a>) -> Array<'a> {
+        unsafe { Array::from_ptr(apr_sys::apr_array_copy(pool.as_mut_ptr(), self.ptr)) }
+    }
+
+    /// Copy just the array header into `pool`.
+    ///
+    /// The copy shares its elements with this array until either is grown.
+    pub fn copy_hdr<'a>(&self, pool: &'a Pool<'a>) -> Array<'a>
+    where
+        'pool: 'a,
+    {
+        unsafe { Array::from_ptr(apr_sys::apr_array_copy_hdr(pool.as_mut_ptr(), self.ptr)) }
+    }
+
+    /// Create a new array in `pool` holding the elements of `first` followed
+    /// by those of `second`.
+    ///
+    /// If `second` is empty the result shares its elements with `first`.
+    ///
+    /// # Panics
+    /// Panics if the arrays have different element sizes.
+    pub fn append(pool: &'pool Pool<'pool>, first: &Array<'pool>, second: &Array<'_>) -> Self {
+        assert_eq!(
+            first.elt_size(),
+            second.elt_size(),
+            "Array element size mismatch"
+        );
+        unsafe {
+            Array::from_ptr(apr_sys::apr_array_append(
+                pool.as_mut_ptr(),
+                first.ptr,
+                second.ptr,
+            ))
+        }
+    }
+
     /// Get the number of elements in the array.
     pub fn len(&self) -> usize {
         unsafe { (*self.ptr).nelts as usize }
@@ -192,6 +262,62 @@ impl<'pool, T: Copy> TypedArray<'pool, T> {
     }
 }
 
+impl<'pool, T: Copy> TypedArray<'pool, T> {
+    /// Remove and return the last element.
+    pub fn pop(&mut self) -> Option<T> {
+        unsafe {
+            let ptr = self.inner.pop_raw() as *const T;
+            (!ptr.is_null()).then(|| ptr.read_unaligned())
+        }
+    }
+
+    /// View the elements as a slice.
+    pub fn as_slice(&self) -> &[T] {
+        if self.is_empty() {
+            return &[];
+        }
+        unsafe { core::slice::from_raw_parts((*self.inner.ptr).elts as *const T, self.len()) }
+    }
+
+    /// Append the elements of `src` to this array.
+    pub fn cat(&mut self, src: &TypedArray<'_, T>) {
+        self.inner.cat(&src.inner)
+    }
+
+    /// Copy the array and its elements into `pool`.
+    pub fn copy<'a>(&self, pool: &'a Pool<'a>) -> TypedArray<'a, T> {
+        TypedArray {
+            inner: self.inner.copy(pool),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Copy just the array header into `pool`, sharing the elements until
+    /// either array is grown.
+    pub fn copy_hdr<'a>(&self, pool: &'a Pool<'a>) -> TypedArray<'a, T>
+    where
+        'pool: 'a,
+    {
+        TypedArray {
+            inner: self.inner.copy_hdr(pool),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Create a new array in `pool` holding the elements of `first` followed
+    /// by those of `second`.
+    pub fn append(
+        pool: &'pool Pool<'pool>,
+        first: &TypedArray<'pool, T>,
+        second: &TypedArray<'_, T>,
+    ) -> Self {
+        TypedArray {
+            inner: Array::append(pool, &first.inner, &second.inner),
+            _phantom: PhantomData,
+        }
+    }
+}
+
 /// Iterator for TypedArray.
 pub struct TypedArrayIter<'a, 'pool, T: Copy> {
     array: &'a TypedArray<'pool, T>,
@@ -237,6 +363,393 @@ impl<'pool, T: Copy> Extend<T> for TypedArray<'pool, T> {
     }
 }
 
+/// An array of C strings, as used by many APR-based C APIs.
+///
+/// This is an `apr_array_header_t` whose elements are `const char *`.
+/// Strings pushed from Rust are copied into the array's pool.
+pub struct StrArray<'pool> {
+    inner: Array<'pool>,
+}
+
+impl<'pool> StrArray<'pool> {
+    /// Create a new string array.
+    pub fn new(pool: &'pool Pool<'pool>, initial_size: i32) -> Self {
+        Self {
+            inner: Array::new(
+                pool,
+                initial_size,
+                core::mem::size_of::<*const c_char>() as i32,
+            ),
+        }
+    }
+
+    /// Create a string array from an existing raw APR array pointer.
+    ///
+    /// # Safety
+    /// The caller must ensure:
+    /// - The pointer is valid and points to an APR array of `const char *`
+    /// - Each element is null or a valid C string that outlives 'pool
+    /// - The array outlives 'pool
+    pub unsafe fn from_ptr(ptr: *mut apr_array_header_t) -> Self {
+        debug_assert_eq!(
+            (*ptr).elt_size as usize,
+            core::mem::size_of::<*const c_char>()
+        );
+        Self {
+            inner: Array::from_ptr(ptr),
+        }
+    }
+
+    /// Create a string array holding copies of the given strings.
+    ///
+    /// # Panics
+    /// Panics if a string contains a NUL byte.
+    pub fn from_iter<I>(pool: &'pool Pool<'pool>, iter: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut array = Self::new(pool, 0);
+        array.extend(iter);
+        array
+    }
+
+    /// Push a copy of `s` onto the array.
+    ///
+    /// # Panics
+    /// Panics if `s` contains a NUL byte.
+    pub fn push(&mut self, s: &str) {
+        assert!(!s.contains('\0'), "Invalid string");
+        unsafe {
+            let copy = apr_sys::apr_pstrmemdup(
+                (*self.inner.ptr).pool,
+                s.as_ptr() as *const c_char,
+                s.len(),
+            );
+            self.push_ptr(copy);
+        }
+    }
+
+    /// Push a C string pointer onto the array without copying it.
+    ///
+    /// # Safety
+    /// `s` must be null or a valid C string that outlives 'pool.
+    pub unsafe fn push_ptr(&mut self, s: *const c_char) {
+        let dst = apr_sys::apr_array_push(self.inner.ptr) as *mut *const c_char;
+        dst.write_unaligned(s);
+    }
+
+    fn ptr_at(&self, index: usize) -> *const c_char {
+        unsafe { (self.inner.get_raw(index) as *const *const c_char).read_unaligned() }
+    }
+
+    /// Get the string at `index`.
+    ///
+    /// Null entries are returned as an empty string.
+    pub fn get(&self, index: usize) -> Option<BStr<'_>> {
+        if index >= self.len() {
+            return None;
+        }
+        Some(unsafe { BStr::from_ptr(self.ptr_at(index)) })
+    }
+
+    /// Remove and return the last string.
+    pub fn pop(&mut self) -> Option<BStr<'pool>> {
+        unsafe {
+            let ptr = self.inner.pop_raw() as *const *const c_char;
+            (!ptr.is_null()).then(|| BStr::from_ptr(ptr.read_unaligned()))
+        }
+    }
+
+    /// Get the number of strings.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Check if the array is empty.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Remove all strings from the array.
+    pub fn clear(&mut self) {
+        self.inner.clear()
+    }
+
+    /// Iterate over the strings as byte strings.
+    pub fn iter(&self) -> StrArrayIter<'_, 'pool> {
+        StrArrayIter {
+            array: self,
+            index: 0,
+        }
+    }
+
+    /// Iterate over the strings, checking that each is valid UTF-8.
+    pub fn iter_str(&self) -> impl Iterator<Item = Result<&str, core::str::Utf8Error>> + '_ {
+        (0..self.len()).map(move |i| {
+            let ptr = self.ptr_at(i);
+            if ptr.is_null() {
+                return Ok("");
+            }
+            unsafe { CStr::from_ptr(ptr) }.to_str()
+        })
+    }
+
+    /// Copy the strings into a `Vec`, replacing invalid UTF-8.
+    pub fn to_vec(&self) -> Vec<String> {
+        self.iter()
+            .map(|s| s.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// Concatenate the strings into a single string allocated in `pool`,
+    /// separated by `sep` if given.
+    pub fn join<'a>(&self, sep: Option<u8>, pool: &'a Pool<'a>) -> BStr<'a> {
+        unsafe {
+            let joined = apr_sys::apr_array_pstrcat(
+                pool.as_mut_ptr(),
+                self.inner.ptr,
+                sep.unwrap_or(0) as c_char,
+            );
+            BStr::from_ptr(joined)
+        }
+    }
+
+    /// Append the strings in `src` to this array.
+    ///
+    /// The strings themselves are not copied, so `src`'s pool must outlive
+    /// this array.
+    pub fn cat(&mut self, src: &StrArray<'pool>) {
+        self.inner.cat(&src.inner)
+    }
+
+    /// Copy the array into `pool`.
+    ///
+    /// The strings themselves are shared with this array.
+    pub fn copy<'a>(&self, pool: &'a Pool<'a>) -> StrArray<'a>
+    where
+        'pool: 'a,
+    {
+        StrArray {
+            inner: self.inner.copy(pool),
+        }
+    }
+
+    /// Copy just the array header into `pool`, sharing the elements until
+    /// either array is grown.
+    pub fn copy_hdr<'a>(&self, pool: &'a Pool<'a>) -> StrArray<'a>
+    where
+        'pool: 'a,
+    {
+        StrArray {
+            inner: self.inner.copy_hdr(pool),
+        }
+    }
+
+    /// Create a new array in `pool` holding the strings of `first` followed
+    /// by those of `second`.
+    pub fn append(
+        pool: &'pool Pool<'pool>,
+        first: &StrArray<'pool>,
+        second: &StrArray<'pool>,
+    ) -> Self {
+        StrArray {
+            inner: Array::append(pool, &first.inner, &second.inner),
+        }
+    }
+
+    /// Get the raw pointer to the array header.
+    ///
+    /// # Safety
+    /// The caller must ensure proper usage of the raw pointer.
+    pub unsafe fn as_ptr(&self) -> *const apr_array_header_t {
+        self.inner.as_ptr()
+    }
+
+    /// Get a mutable raw pointer to the array header.
+    ///
+    /// # Safety
+    /// The caller must ensure proper usage of the raw pointer.
+    pub unsafe fn as_mut_ptr(&mut self) -> *mut apr_array_header_t {
+        self.inner.as_mut_ptr()
+    }
+}
+
+impl<'pool, S: AsRef<str>> Extend<S> for StrArray<'pool> {
+    /// Extend the array with copies of the strings from an iterator.
+    fn extend<I: IntoIterator<Item = S>>(&mut self, iter: I) {
+        for s in iter {
+            self.push(s.as_ref());
+        }
+    }
+}
+
+/// Iterator for StrArray.
+pub struct StrArrayIter<'a, 'pool> {
+    array: &'a StrArray<'pool>,
+    index: usize,
+}
+
+impl<'a, 'pool> Iterator for StrArrayIter<'a, 'pool> {
+    type Item = BStr<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let s = self.array.get(self.index)?;
+        self.index += 1;
+        Some(s)
+    }
+}
+
+/// An array of pointers to structs allocated in a pool.
+///
+/// This is an `apr_array_header_t` whose elements are `T *`, as commonly
+/// returned by C APIs.
+pub struct PtrArray<'pool, T> {
+    inner: Array<'pool>,
+    _phantom: PhantomData<&'pool T>,
+}
+
+impl<'pool, T> PtrArray<'pool, T> {
+    /// Create a new pointer array.
+    pub fn new(pool: &'pool Pool<'pool>, initial_size: i32) -> Self {
+        Self {
+            inner: Array::new(pool, initial_size, core::mem::size_of::<*mut T>() as i32),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Create a pointer array from an existing raw APR array pointer.
+    ///
+    /// # Safety
+    /// The caller must ensure:
+    /// - The pointer is valid and points to an APR array of `T *`
+    /// - Each element is null or points to a valid `T` that outlives 'pool
+    /// - The array outlives 'pool
+    pub unsafe fn from_ptr(ptr: *mut apr_array_header_t) -> Self {
+        debug_assert_eq!((*ptr).elt_size as usize, core::mem::size_of::<*mut T>());
+        Self {
+            inner: Array::from_ptr(ptr),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Push a reference to a value that lives at least as long as the pool.
+    pub fn push(&mut self, value: &'pool T) {
+        unsafe { self.push_ptr(value as *const T as *mut T) }
+    }
+
+    /// Push a raw pointer onto the array.
+    ///
+    /// # Safety
+    /// `ptr` must be null or point to a valid `T` that outlives 'pool.
+    pub unsafe fn push_ptr(&mut self, ptr: *mut T) {
+        let dst = apr_sys::apr_array_push(self.inner.ptr) as *mut *mut T;
+        dst.write_unaligned(ptr);
+    }
+
+    /// Get the raw pointer at `index`.
+    pub fn get_ptr(&self, index: usize) -> Option<*mut T> {
+        if index >= self.len() {
+            return None;
+        }
+        Some(unsafe { (self.inner.get_raw(index) as *const *mut T).read_unaligned() })
+    }
+
+    /// Get a reference to the value at `index`.
+    ///
+    /// Returns `None` if the index is out of bounds or the entry is null.
+    pub fn get(&self, index: usize) -> Option<&'pool T> {
+        self.get_ptr(index).and_then(|ptr| unsafe { ptr.as_ref() })
+    }
+
+    /// Remove and return the last pointer.
+    pub fn pop(&mut self) -> Option<*mut T> {
+        unsafe {
+            let ptr = self.inner.pop_raw() as *const *mut T;
+            (!ptr.is_null()).then(|| ptr.read_unaligned())
+        }
+    }
+
+    /// Get the number of pointers.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Check if the array is empty.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Remove all pointers from the array.
+    pub fn clear(&mut self) {
+        self.inner.clear()
+    }
+
+    /// Iterate over the values, skipping null entries.
+    pub fn iter(&self) -> impl Iterator<Item = &'pool T> + '_ {
+        (0..self.len()).filter_map(move |i| self.get(i))
+    }
+
+    /// Append the pointers in `src` to this array.
+    pub fn cat(&mut self, src: &PtrArray<'pool, T>) {
+        self.inner.cat(&src.inner)
+    }
+
+    /// Copy the array into `pool`.
+    ///
+    /// The values themselves are shared with this array.
+    pub fn copy<'a>(&self, pool: &'a Pool<'a>) -> PtrArray<'a, T>
+    where
+        'pool: 'a,
+    {
+        PtrArray {
+            inner: self.inner.copy(pool),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Copy just the array header into `pool`, sharing the elements until
+    /// either array is grown.
+    pub fn copy_hdr<'a>(&self, pool: &'a Pool<'a>) -> PtrArray<'a, T>
+    where
+        'pool: 'a,
+    {
+        PtrArray {
+            inner: self.inner.copy_hdr(pool),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Create a new array in `pool` holding the pointers of `first` followed
+    /// by those of `second`.
+    pub fn append(
+        pool: &'pool Pool<'pool>,
+        first: &PtrArray<'pool, T>,
+        second: &PtrArray<'pool, T>,
+    ) -> Self {
+        PtrArray {
+            inner: Array::append(pool, &first.inner, &second.inner),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Get the raw pointer to the array header.
+    ///
+    /// # Safety
+    /// The caller must ensure proper usage of the raw pointer.
+    pub unsafe fn as_ptr(&self) -> *const apr_array_header_t {
+        self.inner.as_ptr()
+    }
+
+    /// Get a mutable raw pointer to the array header.
+    ///
+    /// # Safety
+    /// The caller must ensure proper usage of the raw pointer.
+    pub unsafe fn as_mut_ptr(&mut self) -> *mut apr_array_header_t {
+        self.inner.as_mut_ptr()
+    }
+}
+
 /// A table that maps C strings to C strings.
 ///
 /// This is a direct wrapper around APR's table implementation.
@@ -319,6 +832,146 @@ impl<'pool> Table<'pool> {
         }
     }
 
+    /// Merge a value into the existing value for `key`, separated by ", ".
+    ///
+    /// If the key is not present, this behaves like `set_raw`.
+    ///
+    /// # Safety
+    /// The key and value must be valid C strings.
+    pub unsafe fn merge_raw(&mut self, key: *const c_char, val: *const c_char) {
+        apr_sys::apr_table_merge(self.ptr, key, val);
+    }
+
+    /// Merge a value like `merge_raw`, without copying the key or value.
+    ///
+    /// # Safety
+    /// The key and value must be valid C strings that outlive the table.
+    pub unsafe fn mergen_raw(&mut self, key: *const c_char, val: *const c_char) {
+        apr_sys::apr_table_mergen(self.ptr, key, val);
+    }
+
+    /// Get all values for a key, joined with ", ".
+    ///
+    /// If there is more than one value, the joined string is allocated in `pool`.
+    ///
+    /// # Safety
+    /// The key must be a valid C string.
+    pub unsafe fn getm_raw(&self, key: *const c_char, pool: &Pool<'_>) -> *const c_char {
+        apr_sys::apr_table_getm(pool.as_mut_ptr(), self.ptr, key)
+    }
+
+    /// Get every value for a case-insensitive key, in insertion order.
+    pub fn get_all(&self, key: &CStr) -> Vec<&CStr> {
+        let mut values = Vec::new();
+        self.do_each(Some(key), |_, value| {
+            values.push(value);
+            true
+        });
+        values
+    }
+
+    /// Call `f` with each key and value, stopping early if it returns `false`.
+    ///
+    /// If `key` is given, only entries with that case-insensitive key are
+    /// visited. Returns `false` if iteration was stopped by `f`.
+    pub fn do_each<'a, F>(&'a self, key: Option<&CStr>, mut f: F) -> bool
+    where
+        F: FnMut(&'a CStr, &'a CStr) -> bool,
+    {
+        unsafe extern "C" fn trampoline<'a, F>(
+            rec: *mut c_void,
+            key: *const c_char,
+            value: *const c_char,
+        ) -> core::ffi::c_int
+        where
+            F: FnMut(&'a CStr, &'a CStr) -> bool,
+        {
+            let f = &mut *(rec as *mut F);
+            let value = if value.is_null() {
+                c""
+            } else {
+                CStr::from_ptr(value)
+            };
+            f(CStr::from_ptr(key), value) as core::ffi::c_int
+        }
+
+        let key = key.map_or(core::ptr::null(), |k| k.as_ptr());
+        let status = unsafe {
+            apr_sys::apr_table_do(
+                Some(trampoline::<F>),
+                &mut f as *mut F as *mut c_void,
+                self.ptr,
+                key,
+                core::ptr::null::<c_char>(),
+            )
+        };
+        status != 0
+    }
+
+    /// Create an iterator over the table's entries.
+    pub fn iter(&self) -> TableIter<'_> {
+        TableIter {
+            entries: self.entries(),
+            index: 0,
+        }
+    }
+
+    fn entries(&self) -> &[apr_sys::apr_table_entry_t] {
+        unsafe {
+            let header = &*apr_sys::apr_table_elts(self.ptr);
+            if header.nelts == 0 {
+                return &[];
+            }
+            core::slice::from_raw_parts(
+                header.elts as *const apr_sys::apr_table_entry_t,
+                header.nelts as usize,
+            )
+        }
+    }
+
+    /// Create a new table in `pool` with the entries of this table followed
+    /// by those of `base`.
+    ///
+    /// The keys and values are not copied, so both tables' pools must outlive
+    /// the result.
+    pub fn overlay(&self, base: &Table<'pool>, pool: &'pool Pool<'pool>) -> Table<'pool> {
+        unsafe {
+            Table::from_ptr(apr_sys::apr_table_overlay(
+                pool.as_mut_ptr(),
+                self.ptr,
+                base.ptr,
+            ))
+        }
+    }
+
+    /// Add the entries of `other` to this table, resolving keys present in
+    /// both according to `mode`.
+    pub fn overlap(&mut self, other: &Table<'pool>, mode: OverlapMode) {
+        unsafe {
+            apr_sys::apr_table_overlap(self.ptr, other.ptr, mode.into());
+        }
+    }
+
+    /// Collapse entries with the same key into one, according to `mode`.
+    pub fn compress(&mut self, mode: OverlapMode) {
+        unsafe {
+            apr_sys::apr_table_compress(self.ptr, mode.into());
+        }
+    }
+
+    /// Copy the table into `pool`, sharing the keys and values.
+    pub fn copy<'a>(&self, pool: &'a Pool<'a>) -> Table<'a>
+    where
+        'pool: 'a,
+    {
+        unsafe { Table::from_ptr(apr_sys::apr_table_copy(pool.as_mut_ptr(), self.ptr)) }
+    }
+
+    /// Copy the table and all of its keys and values into `pool`.
+    pub fn deep_copy<'a>(&self, pool: &'a Pool<'a>) -> Table<'a> {
+        unsafe { Table::from_ptr(apr_sys::apr_table_clone(pool.as_mut_ptr(), self.ptr)) }
+    }
+
     /// Get the raw pointer to the table.
     ///
     /// # Safety
@@ -336,6 +989,55 @@ impl<'pool> Table<'pool> {
     }
 }
 
+/// How to resolve keys present more than once when combining tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlapMode {
+    /// Keep only the last value (`APR_OVERLAP_TABLES_SET`).
+    Set,
+    /// Join all values with ", " (`APR_OVERLAP_TABLES_MERGE`).
+    Merge,
+    /// Keep every value as a separate entry (`APR_OVERLAP_TABLES_ADD`).
+    Add,
+}
+
+impl From<OverlapMode> for core::ffi::c_uint {
+    fn from(mode: OverlapMode) -> Self {
+        match mode {
+            OverlapMode::Set => apr_sys::APR_OVERLAP_TABLES_SET,
+            OverlapMode::Merge => apr_sys::APR_OVERLAP_TABLES_MERGE,
+            OverlapMode::Add => apr_sys::APR_OVERLAP_TABLES_ADD,
+        }
+    }
+}
+
+/// Iterator over the entries of a Table.
+pub struct TableIter<'a> {
+    entries: &'a [apr_sys::apr_table_entry_t],
+    index: usize,
+}
+
+impl<'a> Iterator for TableIter<'a> {
+    type Item = (&'a CStr, &'a CStr);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(entry) = self.entries.get(self.index) {
+            self.index += 1;
+            if entry.key.is_null() {
+                continue;
+            }
+            unsafe {
+                let value = if entry.val.is_null() {
+                    c""
+                } else {
+                    CStr::from_ptr(entry.val)
+                };
+                return Some((CStr::from_ptr(entry.key), value));
+            }
+        }
+        None
+    }
+}
+
 /// A type-safe wrapper for tables with string values.
 pub struct StringTable<'pool> {
     inner: Table<'pool>,
@@ -422,6 +1124,120 @@ impl<'pool> StringTable<'pool> {
         self.inner.clear()
     }
 
+    /// Merge a value into the existing value for `key`, separated by ", ".
+    pub fn merge(&mut self, key: &str, value: &str) {
+        let key_cstr = CString::new(key).expect("Invalid key");
+        let val_cstr = CString::new(value).expect("Invalid value");
+
+        unsafe {
+            self.inner.merge_raw(key_cstr.as_ptr(), val_cstr.as_ptr());
+        }
+    }
+
+    /// Merge a value like `merge`, without copying the key or value.
+    pub fn mergen(&mut self, key: &'pool CStr, value: &'pool CStr) {
+        unsafe {
+            self.inner.mergen_raw(key.as_ptr(), value.as_ptr());
+        }
+    }
+
+    /// Get all values for a key, joined with ", ".
+    ///
+    /// If there is more than one value, the joined string is allocated in `pool`.
+    pub fn getm<'a>(&'a self, key: &str, pool: &'a Pool<'a>) -> Option<&'a str> {
+        let key_cstr = CString::new(key).ok()?;
+
+        unsafe {
+            let val_ptr = self.inner.getm_raw(key_cstr.as_ptr(), pool);
+            if val_ptr.is_null() {
+                None
+            } else {
+                CStr::from_ptr(val_ptr).to_str().ok()
+            }
+        }
+    }
+
+    /// Get every value for a case-insensitive key, in insertion order.
+    ///
+    /// Values that are not valid UTF-8 are skipped.
+    pub fn get_all(&self, key: &str) -> Vec<&str> {
+        let Ok(key_cstr) = CString::new(key) else {
+            return Vec::new();
+        };
+        self.inner
+            .get_all(&key_cstr)
+            .into_iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect()
+    }
+
+    /// Call `f` with each key and value, stopping early if it returns `false`.
+    ///
+    /// If `key` is given, only entries with that case-insensitive key are
+    /// visited. Entries that are not valid UTF-8 are skipped. Returns `false`
+    /// if iteration was stopped by `f`.
+    pub fn do_each<'a, F>(&'a self, key: Option<&str>, mut f: F) -> bool
+    where
+        F: FnMut(&'a str, &'a str) -> bool,
+    {
+        let key_cstr = match key.map(CString::new) {
+            Some(Ok(key)) => Some(key),
+            Some(Err(_)) => return true,
+            None => None,
+        };
+        self.inner.do_each(key_cstr.as_deref(), |key, value| {
+            match (key.to_str(), value.to_str()) {
+                (Ok(key), Ok(value)) => f(key, value),
+                _ => true,
+            }
+        })
+    }
+
+    /// Create a new table in `pool` with the entries of this table followed
+    /// by those of `base`.
+    pub fn overlay(
+        &self,
+        base: &StringTable<'pool>,
+        pool: &'pool Pool<'pool>,
+    ) -> StringTable<'pool> {
+        StringTable {
+            inner: self.inner.overlay(&base.inner, pool),
+        }
+    }
+
+    /// Add the entries of `other` to this table, resolving keys present in
+    /// both according to `mode`.
+    pub fn overlap(&mut self, other: &StringTable<'pool>, mode: OverlapMode) {
+        self.inner.overlap(&other.inner, mode)
+    }
+
+    /// Collapse entries with the same key into one, according to `mode`.
+    pub fn compress(&mut self, mode: OverlapMode) {
+        self.inner.compress(mode)
+    }
+
+    /// Copy the table into `pool`, sharing the keys and values.
+    pub fn copy<'a>(&self, pool: &'a Pool<'a>) -> StringTable<'a>
+    where
+        'pool: 'a,
+    {
+        StringTable {
+            inner: self.inner.copy(pool),
+        }
+    }
+
+    /// Copy the table and all of its keys and values into `pool`.
+    pub fn deep_copy<'a>(&self, pool: &'a Pool<'a>) -> StringTable<'a> {
+        StringTable {
+            inner: self.inner.deep_copy(pool),
+        }
+    }
+
+    /// Get the underlying Table.
+    pub fn as_table(&self) -> &Table<'pool> {
+        &self.inner
+    }
+
     /// Create an iterator over table entries.
     pub fn iter(&self) -> StringTableIter<'_, 'pool> {
         StringTableIter {
@@ -567,6 +1383,81 @@ mod tests {
         assert_eq!(values, vec![42, 84, 126]);
     }
 
+    #[test]
+    fn test_typed_array_ops() {
+        let pool = Pool::new();
+        let mut first = TypedArray::from_iter(&pool, [1, 2, 3]);
+        let second = TypedArray::from_iter(&pool, [4, 5]);
+
+        assert_eq!(first.pop(), Some(3));
+        first.cat(&second);
+        assert_eq!(first.as_slice(), &[1, 2, 4, 5]);
+
+        let copy = first.copy(&pool);
+        first.clear();
+        assert!(first.is_empty());
+        assert_eq!(copy.as_slice(), &[1, 2, 4, 5]);
+
+        let joined = TypedArray::append(&pool, &copy, &second);
+        assert_eq!(joined.iter().collect::<Vec<_>>(), vec![1, 2, 4, 5, 4, 5]);
+        let hdr = joined.copy_hdr(&pool);
+        assert_eq!(hdr.len(), 6);
+    }
+
+    #[test]
+    fn test_str_array() {
+        let pool = Pool::new();
+        let names = vec!["alpha".to_string(), "beta".to_string()];
+        let mut array = StrArray::from_iter(&pool, &names);
+        array.push("gamma");
+        assert_eq!(array.len(), 3);
+        assert_eq!(array.get(1).unwrap(), "beta");
+        assert!(array.get(3).is_none());
+        assert_eq!(
+            array.iter_str().collect::<Result<Vec<_>, _>>().unwrap(),
+            vec!["alpha", "beta", "gamma"]
+        );
+        assert_eq!(array.join(Some(b','), &pool), "alpha,beta,gamma");
+        assert_eq!(array.join(None, &pool), "alphabetagamma");
+
+        assert_eq!(array.pop().unwrap(), "gamma");
+        let other = StrArray::from_iter(&pool, ["delta"]);
+        let appended = StrArray::append(&pool, &array, &other);
+        assert_eq!(appended.to_vec(), vec!["alpha", "beta", "delta"]);
+        array.cat(&other);
+        assert_eq!(array.copy(&pool).to_vec(), appended.to_vec());
+
+        // Round-trip through the raw pointer, as when C hands an array back
+        let raw = unsafe { array.as_mut_ptr() };
+        let from_c = unsafe { StrArray::from_ptr(raw) };
+        assert_eq!(from_c.len(), 3);
+    }
+
+    #[test]
+    fn test_ptr_array() {
+        #[derive(Debug, PartialEq)]
+        struct Entry {
+            id: u32,
+        }
+
+        let pool = Pool::new();
+        let entries = [Entry { id: 1 }, Entry { id: 2 }];
+        let mut array = PtrArray::new(&pool, 2);
+        array.push(&entries[0]);
+        array.push(&entries[1]);
+        unsafe { array.push_ptr(core::ptr::null_mut()) };
+
+        assert_eq!(array.len(), 3);
+        assert_eq!(array.get(1), Some(&Entry { id: 2 }));
+        assert_eq!(array.get(2), None);
+        assert_eq!(array.iter().map(|e| e.id).collect::<Vec<_>>(), vec![1, 2]);
+
+        assert_eq!(array.pop(), Some(core::ptr::null_mut()));
+        let copy = array.copy(&pool);
+        array.cat(&copy);
+        assert_eq!(array.len(), 4);
+    }
+
     #[test]
     fn test_table_basic() {
         let pool = Pool::new();
@@ -620,6 +1511,78 @@ mod tests {
         assert_eq!(table.get("key1"), None);
     }
 
+    #[test]
+    fn test_table_iter_and_get_all() {
+        let pool = Pool::new();
+        let mut table = StringTable::new(&pool, 4);
+        table.add("Accept", "text/html");
+        table.add("Host", "example.com");
+        table.add("accept", "text/plain");
+
+        let table_ref = table.as_table();
+        let entries: Vec<_> = table_ref
+            .iter()
+            .map(|(k, v)| (k.to_str().unwrap(), v.to_str().unwrap()))
+            .collect();
+        assert_eq!(entries[1], ("Host", "example.com"));
+        assert_eq!(table_ref.get_all(c"ACCEPT").len(), 2);
+
+        assert_eq!(table.get_all("ACCEPT"), vec!["text/html", "text/plain"]);
+        assert!(table.get_all("missing").is_empty());
+        assert_eq!(table.getm("accept", &pool), Some("text/html, text/plain"));
+        assert_eq!(table.getm("host", &pool), Some("example.com"));
+
+        let mut seen = Vec::new();
+        assert!(!table.do_each(None, |key, _| {
+            seen.push(key);
+            seen.len() < 2
+        }));
+        assert_eq!(seen, vec!["Accept", "Host"]);
+        assert!(table.do_each(Some("host"), |_, value| value == "example.com"));
+    }
+
+    #[test]
+    fn test_table_merge_and_compress() {
+        let pool = Pool::new();
+        let mut table = StringTable::new(&pool, 4);
+        table.merge("Vary", "Accept");
+        table.merge("vary", "Cookie");
+        assert_eq!(table.get("Vary"), Some("Accept, Cookie"));
+        table.mergen(c"Vary", c"Origin");
+        assert_eq!(table.get("Vary"), Some("Accept, Cookie, Origin"));
+
+        let mut table = StringTable::new(&pool, 4);
+        table.add("Cache-Control", "no-cache");
+        table.add("Cache-Control", "no-store");
+        let mut set = table.copy(&pool);
+        table.compress(OverlapMode::Merge);
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.get("Cache-Control"), Some("no-cache, no-store"));
+        set.compress(OverlapMode::Set);
+        assert_eq!(set.get_all("Cache-Control"), vec!["no-store"]);
+    }
+
+    #[test]
+    fn test_table_overlay_and_overlap() {
+        let pool = Pool::new();
+        let mut base = StringTable::new(&pool, 4);
+        base.set("Server", "apr");
+        base.set("Connection", "close");
+        let mut overrides = StringTable::new(&pool, 4);
+        overrides.set("Connection", "keep-alive");
+
+        let combined = overrides.overlay(&base, &pool);
+        assert_eq!(combined.len(), 3);
+        assert_eq!(combined.get_all("Connection"), vec!["keep-alive", "close"]);
+
+        let mut merged = base.deep_copy(&pool);
+        merged.overlap(&overrides, OverlapMode::Merge);
+        assert_eq!(merged.get("Connection"), Some("close, keep-alive"));
+        base.overlap(&overrides, OverlapMode::Set);
+        assert_eq!(base.get("Connection"), Some("keep-alive"));
+        assert_eq!(base.get("Server"), Some("apr"));
+    }
+
     #[test]
     fn test_typed_array_from_iter() {
         let pool = Pool::new();
@@ -0,0 +1,661 @@
+//! Threads
+//!
+//! Threads started here are real APR threads created with `apr_thread_create`,
+//! so C code running on them can inspect the current thread or use its pool.
+//! Each thread gets its own pool, a child of the pool it was spawned from,
+//! which is passed to the thread's closure.
+
+use crate::pool::Pool;
+use crate::{Error, Result, Status};
+use core::cell::{Cell, RefCell};
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::ptr;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+type Slot<T> = Arc<Mutex<Option<std::thread::Result<T>>>>;
+
+struct Packet<F, T> {
+    f: F,
+    result: Slot<T>,
+}
+
+extern "C" fn thread_start<F, T>(
+    thread: *mut apr_sys::apr_thread_t,
+    data: *mut c_void,
+) -> *mut c_void
+where
+    F: FnOnce(&Pool<'_>) -> T,
+{
+    let packet = unsafe { Box::from_raw(data as *mut Packet<F, T>) };
+    let Packet { f, result } = *packet;
+    // The thread's pool is owned by APR. Depending on the APR version it is
+    // destroyed when the thread is joined or exits, or only along with the
+    // pool the thread was spawned from.
+    let pool = ManuallyDrop::new(unsafe { Pool::from_raw(apr_sys::apr_thread_pool_get(thread)) });
+    let outcome = catch_unwind(AssertUnwindSafe(|| f(&pool)));
+    *result.lock().unwrap_or_else(|e| e.into_inner()) = Some(outcome);
+    ptr::null_mut()
+}
+
+/// Options for spawning a thread
+#[derive(Debug, Clone, Default)]
+pub struct ThreadBuilder {
+    stack_size: Option<usize>,
+    guard_size: Option<usize>,
+}
+
+impl ThreadBuilder {
+    /// Create a builder with APR's default thread attributes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the stack size of the new thread
+    pub fn stack_size(mut self, size: usize) -> Self {
+        self.stack_size = Some(size);
+        self
+    }
+
+    /// Set the size of the guard area at the end of the thread's stack
+    pub fn guard_size(mut self, size: usize) -> Self {
+        self.guard_size = Some(size);
+        self
+    }
+
+    fn create<F, T>(
+        &self,
+        detach: bool,
+        f: F,
+        pool: &Pool<'_>,
+    ) -> Result<(*mut apr_sys::apr_thread_t, Slot<T>)>
+    where
+        F: FnOnce(&Pool<'_>) -> T + Send,
+        T: Send,
+    {
+        let check = |status: apr_sys::apr_status_t| -> Result<()> {
+            if status != apr_sys::APR_SUCCESS as i32 {
+                return Err(Error::from_status(Status::from(status)));
+            }
+            Ok(())
+        };
+
+        let mut attr: *mut apr_sys::apr_threadattr_t = ptr::null_mut();
+        unsafe {
+            check(apr_sys::apr_threadattr_create(&mut attr, pool.as_mut_ptr()))?;
+            if detach {
+                check(apr_sys::apr_threadattr_detach_set(attr, 1))?;
+            }
+            if let Some(size) = self.stack_size {
+                check(apr_sys::apr_threadattr_stacksize_set(attr, size))?;
+            }
+            if let Some(size) = self.guard_size {
+                check(apr_sys::apr_threadattr_guardsize_set(attr, size))?;
+            }
+        }
+
+        let result: Slot<T> = Arc::new(Mutex::new(None));
+        let packet = Box::into_raw(Box::new(Packet {
+            f,
+            result: result.clone(),
+        }));
+
+        let mut raw: *mut apr_sys::apr_thread_t = ptr::null_mut();
+        let status = unsafe {
+            apr_sys::apr_thread_create(
+                &mut raw,
+                attr,
+                Some(thread_start::<F, T>),
+                packet as *mut c_void,
+                pool.as_mut_ptr(),
+            )
+        };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            // The thread never started, so the packet is still ours.
+            drop(unsafe { Box::from_raw(packet) });
+            return Err(Error::from_status(Status::from(status)));
+        }
+
+        Ok((raw, result))
+    }
+
+    /// Spawn a thread that can be joined
+    ///
+    /// The pool must live for the rest of the program, as the handle may be
+    /// leaked without joining the thread. Use [`scope`] to spawn threads from
+    /// a shorter-lived pool.
+    pub fn spawn<F, T>(self, pool: &'static Pool<'static>, f: F) -> Result<Thread<T>>
+    where
+        F: FnOnce(&Pool<'_>) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (raw, result) = self.create(false, f, pool)?;
+        Ok(Thread { raw, result })
+    }
+
+    /// Spawn a detached thread, which cleans up after itself when it exits
+    ///
+    /// APR allocates the thread from `pool`, and nothing waits for a detached
+    /// thread to finish, so the pool must live for the rest of the program.
+    pub fn spawn_detached<F>(self, pool: &'static Pool<'static>, f: F) -> Result<()>
+    where
+        F: FnOnce(&Pool<'_>) + Send + 'static,
+    {
+        self.create(true, f, pool).map(|_| ())
+    }
+}
+
+fn join_raw(raw: *mut apr_sys::apr_thread_t) {
+    let mut retval: apr_sys::apr_status_t = 0;
+    let status = unsafe { apr_sys::apr_thread_join(&mut retval, raw) };
+    if status != apr_sys::APR_SUCCESS as i32 {
+        panic!("failed to join thread: {}", Status::from(status));
+    }
+}
+
+fn take_result<T>(result: &Slot<T>) -> std::thread::Result<T> {
+    result
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take()
+        .expect("thread exited without producing a result")
+}
+
+/// Handle to a joinable APR thread
+///
+/// If the handle is dropped without being joined or detached, the thread is
+/// joined.
+pub struct Thread<T> {
+    raw: *mut apr_sys::apr_thread_t,
+    result: Slot<T>,
+}
+
+impl<T> Thread<T> {
+    /// Spawn a thread with default attributes
+    ///
+    /// See [`ThreadBuilder::spawn`] for why the pool must be `'static`.
+    pub fn spawn<F>(pool: &'static Pool<'static>, f: F) -> Result<Self>
+    where
+        F: FnOnce(&Pool<'_>) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        ThreadBuilder::new().spawn(pool, f)
+    }
+
+    /// Wait for the thread to finish
+    ///
+    /// Returns the closure's return value, or the panic payload if it panicked.
+    pub fn join(mut self) -> std::thread::Result<T> {
+        let raw = core::mem::replace(&mut self.raw, ptr::null_mut());
+        join_raw(raw);
+        take_result(&self.result)
+    }
+
+    /// Whether the thread has finished running its closure
+    pub fn is_finished(&self) -> bool {
+        self.result
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some()
+    }
+
+    /// Get a raw pointer to the underlying thread
+    pub fn as_ptr(&self) -> *const apr_sys::apr_thread_t {
+        self.raw
+    }
+
+    /// Get a mutable raw pointer to the underlying thread
+    pub fn as_mut_ptr(&mut self) -> *mut apr_sys::apr_thread_t {
+        self.raw
+    }
+
+    /// Detach the thread, letting it run to completion on its own
+    pub fn detach(mut self) -> Result<()> {
+        let raw = core::mem::replace(&mut self.raw, ptr::null_mut());
+        let status = unsafe { apr_sys::apr_thread_detach(raw) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(Error::from_status(Status::from(status)));
+        }
+        Ok(())
+    }
+}
+
+impl<T> Drop for Thread<T> {
+    fn drop(&mut self) {
+        if !self.raw.is_null() {
+            join_raw(self.raw);
+        }
+    }
+}
+
+/// A scoped thread together with the flag recording whether it was joined.
+type ScopedEntry = (*mut apr_sys::apr_thread_t, Rc<Cell<bool>>);
+
+/// Scope for spawning threads that borrow from the enclosing stack frame
+///
+/// Created by [`scope`]; all threads spawned in it are joined before
+/// [`scope`] returns.
+pub struct Scope<'scope, 'env: 'scope> {
+    pool: &'env Pool<'env>,
+    threads: RefCell<Vec<ScopedEntry>>,
+    _scope: PhantomData<&'scope mut &'scope ()>,
+    _env: PhantomData<&'env mut &'env ()>,
+}
+
+/// Handle to a thread spawned in a [`Scope`]
+pub struct ScopedThread<'scope, T> {
+    raw: *mut apr_sys::apr_thread_t,
+    joined: Rc<Cell<bool>>,
+    result: Slot<T>,
+    _phantom: PhantomData<&'scope ()>,
+}
+
+impl<'scope, 'env> Scope<'scope, 'env> {
+    /// Spawn a thread in this scope
+    pub fn spawn<F, T>(&'scope self, f: F) -> Result<ScopedThread<'scope, T>>
+    where
+        F: FnOnce(&Pool<'_>) -> T + Send + 'scope,
+        T: Send + 'scope,
+    {
+        self.spawn_with(ThreadBuilder::new(), f)
+    }
+
+    /// Spawn a thread in this scope with the given attributes
+    pub fn spawn_with<F, T>(
+        &'scope self,
+        builder: ThreadBuilder,
+        f: F,
+    ) -> Result<ScopedThread<'scope, T>>
+    where
+        F: FnOnce(&Pool<'_>) -> T + Send + 'scope,
+        T: Send + 'scope,
+    {
+        let (raw, result) = builder.create(false, f, self.pool)?;
+        let joined = Rc::new(Cell::new(false));
+        self.threads.borrow_mut().push((raw, joined.clone()));
+        Ok(ScopedThread {
+            raw,
+            joined,
+            result,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl<'scope, T> ScopedThread<'scope, T> {
+    /// Wait for the thread to finish
+    ///
+    /// Returns the closure's return value, or the panic payload if it panicked.
+    pub fn join(self) -> std::thread::Result<T> {
+        join_raw(self.raw);
+        self.joined.set(true);
+        take_result(&self.result)
+    }
+
+    /// Whether the thread has finished running its closure
+    pub fn is_finished(&self) -> bool {
+        self.result
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some()
+    }
+
+    /// Get a raw pointer to the underlying thread
+    pub fn as_ptr(&self) -> *const apr_sys::apr_thread_t {
+        self.raw
+    }
+}
+
+/// Run `f` with a [`Scope`] for spawning threads that may borrow local data
+///
+/// Threads are created from `pool`, and any that have not been joined are
+/// joined when `f` returns.
+pub fn scope<'env, F, R>(pool: &'env Pool<'env>, f: F) -> R
+where
+    F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> R,
+{
+    let scope = Scope {
+        pool,
+        threads: RefCell::new(Vec::new()),
+        _scope: PhantomData,
+        _env: PhantomData,
+    };
+    let outcome = catch_unwind(AssertUnwindSafe(|| f(&scope)));
+    for (raw, joined) in scope.threads.borrow_mut().drain(..) {
+        if !joined.get() {
+            join_raw(raw);
+        }
+    }
+    match outcome {
+        Ok(value) => value,
+        Err(payload) => resume_unwind(payload),
+    }
+}
+
+/// Yield the rest of the current thread's time slice
+pub fn yield_now() {
+    unsafe { apr_sys::apr_thread_yield() }
+}
+
+type PanicPayload = Box<dyn core::any::Any + Send>;
+
+thread_local! {
+    static ONCE_FN: Cell<Option<Box<dyn FnOnce()>>> = const { Cell::new(None) };
+    static ONCE_PANIC: Cell<Option<PanicPayload>> = const { Cell::new(None) };
+}
+
+extern "C" fn once_trampoline() {
+    if let Some(f) = ONCE_FN.with(|slot| slot.take()) {
+        // Panics must not unwind into APR.
+        if let Err(payload) = catch_unwind(AssertUnwindSafe(f)) {
+            ONCE_PANIC.with(|slot| slot.set(Some(payload)));
+        }
+    }
+}
+
+/// One-time initialization, wrapping `apr_thread_once`
+pub struct Once {
+    raw: *mut apr_sys::apr_thread_once_t,
+    _pool: Pool<'static>,
+}
+
+unsafe impl Send for Once {}
+unsafe impl Sync for Once {}
+
+impl Once {
+    /// Create a new once-control
+    pub fn new() -> Result<Self> {
+        let pool = Pool::new();
+        let mut raw: *mut apr_sys::apr_thread_once_t = ptr::null_mut();
+        let status = unsafe { apr_sys::apr_thread_once_init(&mut raw, pool.as_mut_ptr()) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(Error::from_status(Status::from(status)));
+        }
+        Ok(Once { raw, _pool: pool })
+    }
+
+    /// Run `f` if no call on this control has run yet
+    ///
+    /// Concurrent callers block until the first call has finished.
+    pub fn call_once<F: FnOnce()>(&self, f: F) -> Result<()> {
+        // apr_thread_once takes a function without arguments, but runs it on
+        // the calling thread, so the closure is handed over in a thread local.
+        let f: Box<dyn FnOnce() + '_> = Box::new(f);
+        let f: Box<dyn FnOnce()> = unsafe { core::mem::transmute(f) };
+        ONCE_FN.with(|slot| slot.set(Some(f)));
+        let status = unsafe { apr_sys::apr_thread_once(self.raw, Some(once_trampoline)) };
+        // Drop the closure if this call was not the one that ran it.
+        ONCE_FN.with(|slot| slot.take());
+        if let Some(payload) = ONCE_PANIC.with(|slot| slot.take()) {
+            resume_unwind(payload);
+        }
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(Error::from_status(Status::from(status)));
+        }
+        Ok(())
+    }
+}
+
+extern "C" fn threadkey_destructor<T>(data: *mut c_void) {
+    if !data.is_null() {
+        drop(unsafe { Box::from_raw(data as *mut T) });
+    }
+}
+
+thread_local! {
+    /// Keys whose value the current thread is lending out through [`ThreadKey::with`]
+    static KEYS_IN_USE: RefCell<Vec<*mut apr_sys::apr_threadkey_t>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+/// Marks a key as in use by [`ThreadKey::with`] until dropped
+struct KeyBorrow(*mut apr_sys::apr_threadkey_t);
+
+impl KeyBorrow {
+    fn new(key: *mut apr_sys::apr_threadkey_t) -> Self {
+        KEYS_IN_USE.with(|keys| keys.borrow_mut().push(key));
+        KeyBorrow(key)
+    }
+
+    fn is_active(key: *mut apr_sys::apr_threadkey_t) -> bool {
+        KEYS_IN_USE.with(|keys| keys.borrow().contains(&key))
+    }
+}
+
+impl Drop for KeyBorrow {
+    fn drop(&mut self) {
+        KEYS_IN_USE.with(|keys| {
+            let mut keys = keys.borrow_mut();
+            if let Some(i) = keys.iter().rposition(|&key| key == self.0) {
+                keys.remove(i);
+            }
+        });
+    }
+}
+
+/// Per-thread storage, wrapping `apr_threadkey_private_*`
+///
+/// Each thread sees its own value; values are dropped when their thread exits.
+pub struct ThreadKey<T> {
+    raw: *mut apr_sys::apr_threadkey_t,
+    _pool: Pool<'static>,
+    _phantom: PhantomData<T>,
+}
+
+unsafe impl<T> Send for ThreadKey<T> {}
+unsafe impl<T> Sync for ThreadKey<T> {}
+
+impl<T> ThreadKey<T> {
+    /// Create a new key
+    pub fn new() -> Result<Self> {
+        let pool = Pool::new();
+        let mut raw: *mut apr_sys::apr_threadkey_t = ptr::null_mut();
+        let status = unsafe {
+            apr_sys::apr_threadkey_private_create(
+                &mut raw,
+                Some(threadkey_destructor::<T>),
+                pool.as_mut_ptr(),
+            )
+        };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(Error::from_status(Status::from(status)));
+        }
+        Ok(ThreadKey {
+            raw,
+            _pool: pool,
+            _phantom: PhantomData,
+        })
+    }
+
+    fn get_raw(&self) -> *mut T {
+        let mut data: *mut c_void = ptr::null_mut();
+        unsafe { apr_sys::apr_threadkey_private_get(&mut data, self.raw) };
+        data as *mut T
+    }
+
+    fn set_raw(&self, data: *mut T) -> Result<()> {
+        let status = unsafe { apr_sys::apr_threadkey_private_set(data as *mut c_void, self.raw) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(Error::from_status(Status::from(status)));
+        }
+        Ok(())
+    }
+
+    fn check_not_borrowed(&self) {
+        assert!(
+            !KeyBorrow::is_active(self.raw),
+            "ThreadKey value replaced while borrowed by ThreadKey::with"
+        );
+    }
+
+    /// Set the current thread's value, returning the previous one
+    ///
+    /// # Panics
+    ///
+    /// If called from inside [`ThreadKey::with`] on the same key.
+    pub fn set(&self, value: T) -> Result<Option<T>> {
+        self.check_not_borrowed();
+        let old = self.get_raw();
+        let new = Box::into_raw(Box::new(value));
+        if let Err(e) = self.set_raw(new) {
+            drop(unsafe { Box::from_raw(new) });
+            return Err(e);
+        }
+        Ok((!old.is_null()).then(|| *unsafe { Box::from_raw(old) }))
+    }
+
+    /// Remove and return the current thread's value
+    ///
+    /// # Panics
+    ///
+    /// If called from inside [`ThreadKey::with`] on the same key.
+    pub fn take(&self) -> Result<Option<T>> {
+        self.check_not_borrowed();
+        let old = self.get_raw();
+        if old.is_null() {
+            return Ok(None);
+        }
+        self.set_raw(ptr::null_mut())?;
+        Ok(Some(*unsafe { Box::from_raw(old) }))
+    }
+
+    /// Run `f` with a reference to the current thread's value
+    ///
+    /// The value can't be replaced while `f` runs: [`ThreadKey::set`] and
+    /// [`ThreadKey::take`] panic if `f` calls them on this key.
+    pub fn with<R>(&self, f: impl FnOnce(Option<&T>) -> R) -> R {
+        let _borrow = KeyBorrow::new(self.raw);
+        let data = self.get_raw();
+        f(unsafe { data.as_ref() })
+    }
+
+    /// Get a raw pointer to the underlying key
+    pub fn as_ptr(&self) -> *const apr_sys::apr_threadkey_t {
+        self.raw
+    }
+}
+
+impl<T> Drop for ThreadKey<T> {
+    fn drop(&mut self) {
+        // Values still set on other threads are leaked, as with pthread keys.
+        drop(self.take());
+        unsafe {
+            apr_sys::apr_threadkey_private_delete(self.raw);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_spawn_join() {
+        let pool: &'static Pool<'static> = Box::leak(Box::new(Pool::new()));
+        let thread = Thread::spawn(pool, |thread_pool| {
+            assert!(!thread_pool.as_ptr().is_null());
+            21 * 2
+        })
+        .unwrap();
+        assert_eq!(thread.join().unwrap(), 42);
+    }
+
+    #[test]
+    fn test_join_panic() {
+        let pool: &'static Pool<'static> = Box::leak(Box::new(Pool::new()));
+        let thread = Thread::<()>::spawn(pool, |_| panic!("boom")).unwrap();
+        let payload = thread.join().unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"boom"));
+    }
+
+    #[test]
+    fn test_builder() {
+        let pool: &'static Pool<'static> = Box::leak(Box::new(Pool::new()));
+        let thread = ThreadBuilder::new()
+            .stack_size(256 * 1024)
+            .guard_size(4096)
+            .spawn(pool, |_| "done")
+            .unwrap();
+        assert_eq!(thread.join().unwrap(), "done");
+    }
+
+    #[test]
+    fn test_detached() {
+        let pool: &'static Pool<'static> = Box::leak(Box::new(Pool::new()));
+        let (tx, rx) = std::sync::mpsc::channel();
+        ThreadBuilder::new()
+            .spawn_detached(pool, move |_| tx.send(7).unwrap())
+            .unwrap();
+        assert_eq!(rx.recv().unwrap(), 7);
+    }
+
+    #[test]
+    fn test_scope_borrows() {
+        let pool = Pool::new();
+        let counter = AtomicUsize::new(0);
+        let mut data = vec![1, 2, 3];
+        scope(&pool, |s| {
+            for _ in 0..4 {
+                s.spawn(|_| counter.fetch_add(1, Ordering::SeqCst)).unwrap();
+            }
+            let sum = s.spawn(|_| data.iter().sum::<i32>()).unwrap();
+            assert_eq!(sum.join().unwrap(), 6);
+        });
+        assert_eq!(counter.load(Ordering::SeqCst), 4);
+        data.push(4);
+    }
+
+    #[test]
+    fn test_once() {
+        let once = Once::new().unwrap();
+        let counter = AtomicUsize::new(0);
+        once.call_once(|| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+        once.call_once(|| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_thread_key() {
+        let pool = Pool::new();
+        let key = ThreadKey::<String>::new().unwrap();
+        assert_eq!(key.set("main".to_string()).unwrap(), None);
+
+        scope(&pool, |s| {
+            s.spawn(|_| {
+                key.with(|v| assert!(v.is_none()));
+                key.set("worker".to_string()).unwrap();
+                key.with(|v| assert_eq!(v.map(|s| s.as_str()), Some("worker")));
+            })
+            .unwrap();
+        });
+
+        key.with(|v| assert_eq!(v.map(|s| s.as_str()), Some("main")));
+        assert_eq!(key.take().unwrap(), Some("main".to_string()));
+        assert_eq!(key.take().unwrap(), None);
+    }
+
+    #[test]
+    fn test_thread_key_set_inside_with() {
+        let key = ThreadKey::<String>::new().unwrap();
+        key.set("outer".to_string()).unwrap();
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            key.with(|v| {
+                key.set("inner".to_string()).unwrap();
+                v.map(|s| s.len())
+            })
+        }));
+        assert!(result.is_err());
+        key.with(|v| assert_eq!(v.map(|s| s.as_str()), Some("outer")));
+        assert_eq!(key.take().unwrap(), Some("outer".to_string()));
+    }
+}
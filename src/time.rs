@@ -1,4 +1,5 @@
 //! Time handling.
+use crate::Result;
 use alloc::string::{String, ToString};
 pub use apr_sys::{apr_interval_time_t, apr_time_t};
 
@@ -23,6 +24,26 @@ impl Time {
         self.0
     }
 
+    /// Create a Time from seconds since the Unix epoch, as returned by `time(2)`.
+    pub fn from_ansi(secs: i64) -> Result<Self> {
+        let mut time: apr_time_t = 0;
+        let status = unsafe { apr_sys::apr_time_ansi_put(&mut time, secs as _) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+        Ok(Self(time))
+    }
+
+    /// Break the time down into calendar fields in UTC.
+    pub fn to_gmt(&self) -> Result<ExplodedTime> {
+        ExplodedTime::gmt(*self)
+    }
+
+    /// Break the time down into calendar fields in the local timezone.
+    pub fn to_local(&self) -> Result<ExplodedTime> {
+        ExplodedTime::local(*self)
+    }
+
     /// Returns the time as a string in the format `Sun Nov 06 08:49:37 1994`.
     pub fn ctime(&self) -> String {
         let mut buf: [u8; apr_sys::APR_CTIME_LEN as usize] = [0; apr_sys::APR_CTIME_LEN as usize];
@@ -47,6 +68,231 @@ impl Time {
     }
 }
 
+/// A time broken down into calendar fields, wrapping `apr_time_exp_t`.
+///
+/// Fields are stored relative to a timezone, whose offset from UTC is
+/// [`ExplodedTime::gmt_offset`].
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(transparent)]
+pub struct ExplodedTime(apr_sys::apr_time_exp_t);
+
+impl ExplodedTime {
+    /// Create an exploded time in UTC.
+    ///
+    /// `month` runs from 1 to 12. The weekday and day of the year are left
+    /// at zero; they are filled in by [`ExplodedTime::gmt`] and friends, and
+    /// ignored when imploding.
+    pub fn new(
+        year: i32,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+        microsecond: u32,
+    ) -> Self {
+        Self(apr_sys::apr_time_exp_t {
+            tm_usec: microsecond as i32,
+            tm_sec: second as i32,
+            tm_min: minute as i32,
+            tm_hour: hour as i32,
+            tm_mday: day as i32,
+            tm_mon: month as i32 - 1,
+            tm_year: year - 1900,
+            ..Default::default()
+        })
+    }
+
+    fn explode(
+        time: Time,
+        f: impl FnOnce(*mut apr_sys::apr_time_exp_t, apr_time_t) -> apr_sys::apr_status_t,
+    ) -> Result<Self> {
+        // APR truncates towards zero, which gives times before the epoch a
+        // negative tm_usec; explode the whole second below instead.
+        let usec = time.0.rem_euclid(1_000_000);
+        let mut exploded = Self::default();
+        let status = f(&mut exploded.0, time.0 - usec);
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+        exploded.0.tm_usec = usec as i32;
+        Ok(exploded)
+    }
+
+    /// Explode `time` in UTC.
+    pub fn gmt(time: Time) -> Result<Self> {
+        Self::explode(time, |result, t| unsafe {
+            apr_sys::apr_time_exp_gmt(result, t)
+        })
+    }
+
+    /// Explode `time` in the local timezone.
+    pub fn local(time: Time) -> Result<Self> {
+        Self::explode(time, |result, t| unsafe {
+            apr_sys::apr_time_exp_lt(result, t)
+        })
+    }
+
+    /// Explode `time` in a timezone `offset` seconds east of UTC.
+    pub fn with_offset(time: Time, offset: i32) -> Result<Self> {
+        Self::explode(time, |result, t| unsafe {
+            apr_sys::apr_time_exp_tz(result, t, offset)
+        })
+    }
+
+    fn implode(
+        &self,
+        f: unsafe extern "C" fn(
+            *mut apr_time_t,
+            *mut apr_sys::apr_time_exp_t,
+        ) -> apr_sys::apr_status_t,
+    ) -> Result<Time> {
+        let mut exploded = self.0;
+        let mut time: apr_time_t = 0;
+        let status = unsafe { f(&mut time, &mut exploded) };
+        if status != apr_sys::APR_SUCCESS as i32 {
+            return Err(crate::Error::from_status(status.into()));
+        }
+        Ok(Time(time))
+    }
+
+    /// Convert back to a [`Time`], taking the timezone offset into account.
+    pub fn to_time(&self) -> Result<Time> {
+        self.implode(apr_sys::apr_time_exp_gmt_get)
+    }
+
+    /// Convert back to a [`Time`], treating the fields as if they were UTC.
+    ///
+    /// This is `apr_time_exp_get`, which ignores [`ExplodedTime::gmt_offset`].
+    pub fn to_time_ignoring_offset(&self) -> Result<Time> {
+        self.implode(apr_sys::apr_time_exp_get)
+    }
+
+    /// Format the time with `apr_strftime`, using `strftime(3)` conversions.
+    pub fn strftime(&self, format: &str) -> Result<String> {
+        let format = alloc::ffi::CString::new(format)
+            .map_err(|_| crate::Error::from_status((apr_sys::APR_EINVAL as i32).into()))?;
+        let mut exploded = self.0;
+        let mut buf = alloc::vec![0u8; 256];
+        loop {
+            let mut len: apr_sys::apr_size_t = 0;
+            let status = unsafe {
+                apr_sys::apr_strftime(
+                    buf.as_mut_ptr() as *mut core::ffi::c_char,
+                    &mut len,
+                    buf.len(),
+                    format.as_ptr(),
+                    &mut exploded,
+                )
+            };
+            if status != apr_sys::APR_SUCCESS as i32 {
+                return Err(crate::Error::from_status(status.into()));
+            }
+            // strftime reports an empty result when the buffer is too small.
+            if len > 0 || format.is_empty() || buf.len() >= 64 * 1024 {
+                buf.truncate(len);
+                return Ok(String::from_utf8_lossy(&buf).into_owned());
+            }
+            buf.resize(buf.len() * 4, 0);
+        }
+    }
+
+    /// The year, e.g. 1994.
+    pub fn year(&self) -> i32 {
+        self.0.tm_year + 1900
+    }
+
+    /// The month, from 1 to 12.
+    pub fn month(&self) -> u32 {
+        (self.0.tm_mon + 1) as u32
+    }
+
+    /// The day of the month, from 1 to 31.
+    pub fn day(&self) -> u32 {
+        self.0.tm_mday as u32
+    }
+
+    /// The hour, from 0 to 23.
+    pub fn hour(&self) -> u32 {
+        self.0.tm_hour as u32
+    }
+
+    /// The minute, from 0 to 59.
+    pub fn minute(&self) -> u32 {
+        self.0.tm_min as u32
+    }
+
+    /// The second, from 0 to 61 to allow for leap seconds.
+    pub fn second(&self) -> u32 {
+        self.0.tm_sec as u32
+    }
+
+    /// The microseconds past the second.
+    pub fn microsecond(&self) -> u32 {
+        self.0.tm_usec as u32
+    }
+
+    /// The day of the week, from 0 (Sunday) to 6.
+    pub fn weekday(&self) -> u32 {
+        self.0.tm_wday as u32
+    }
+
+    /// The day of the year, from 0 to 365.
+    pub fn yearday(&self) -> u32 {
+        self.0.tm_yday as u32
+    }
+
+    /// Whether daylight saving time is in effect.
+    pub fn is_dst(&self) -> bool {
+        self.0.tm_isdst != 0
+    }
+
+    /// Seconds east of UTC of the timezone the fields are in.
+    pub fn gmt_offset(&self) -> i32 {
+        self.0.tm_gmtoff
+    }
+
+    /// Set the timezone the fields are in, as seconds east of UTC.
+    pub fn set_gmt_offset(&mut self, offset: i32) {
+        self.0.tm_gmtoff = offset;
+    }
+
+    /// Returns a pointer to the underlying `apr_time_exp_t`.
+    pub fn as_ptr(&self) -> *const apr_sys::apr_time_exp_t {
+        &self.0
+    }
+
+    /// Returns a mutable pointer to the underlying `apr_time_exp_t`.
+    pub fn as_mut_ptr(&mut self) -> *mut apr_sys::apr_time_exp_t {
+        &mut self.0
+    }
+}
+
+impl From<apr_sys::apr_time_exp_t> for ExplodedTime {
+    fn from(exploded: apr_sys::apr_time_exp_t) -> Self {
+        Self(exploded)
+    }
+}
+
+impl From<ExplodedTime> for apr_sys::apr_time_exp_t {
+    fn from(exploded: ExplodedTime) -> Self {
+        exploded.0
+    }
+}
+
+impl PartialEq for ExplodedTime {
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b) = (&self.0, &other.0);
+        (
+            a.tm_year, a.tm_mon, a.tm_mday, a.tm_hour, a.tm_min, a.tm_sec, a.tm_usec,
+        ) == (
+            b.tm_year, b.tm_mon, b.tm_mday, b.tm_hour, b.tm_min, b.tm_sec, b.tm_usec,
+        ) && a.tm_gmtoff == b.tm_gmtoff
+    }
+}
+
+impl Eq for ExplodedTime {}
+
 /// Convert SystemTime to apr_time_t (microseconds since Unix epoch)
 #[cfg(feature = "std")]
 pub fn to_apr_time(system_time: std::time::SystemTime) -> apr_time_t {
@@ -172,6 +418,81 @@ mod tests {
         assert_eq!(t.rfc822_date(), "Sun, 06 Nov 1994 08:49:37 GMT");
     }
 
+    #[test]
+    fn test_exploded_gmt() {
+        let t = Time::from(784111777000123);
+        let exploded = t.to_gmt().unwrap();
+        assert_eq!(exploded.year(), 1994);
+        assert_eq!(exploded.month(), 11);
+        assert_eq!(exploded.day(), 6);
+        assert_eq!(
+            (exploded.hour(), exploded.minute(), exploded.second()),
+            (8, 49, 37)
+        );
+        assert_eq!(exploded.microsecond(), 123);
+        assert_eq!(exploded.weekday(), 0);
+        assert_eq!(exploded.yearday(), 309);
+        assert_eq!(exploded.gmt_offset(), 0);
+        assert_eq!(exploded, ExplodedTime::new(1994, 11, 6, 8, 49, 37, 123));
+        assert_eq!(exploded.to_time().unwrap(), t);
+        assert_eq!(
+            ExplodedTime::new(1994, 11, 6, 8, 49, 37, 123)
+                .to_time()
+                .unwrap(),
+            t
+        );
+    }
+
+    #[test]
+    fn test_exploded_before_epoch() {
+        let exploded = Time::from(-500_000).to_gmt().unwrap();
+        assert_eq!(
+            (exploded.year(), exploded.month(), exploded.day()),
+            (1969, 12, 31)
+        );
+        assert_eq!(
+            (exploded.hour(), exploded.minute(), exploded.second()),
+            (23, 59, 59)
+        );
+        assert_eq!(exploded.microsecond(), 500_000);
+    }
+
+    #[test]
+    fn test_exploded_offset() {
+        let t = Time::from(784111777000000);
+        let exploded = ExplodedTime::with_offset(t, -5 * 3600).unwrap();
+        assert_eq!((exploded.day(), exploded.hour()), (6, 3));
+        assert_eq!(exploded.gmt_offset(), -5 * 3600);
+        assert_eq!(exploded.to_time().unwrap(), t);
+        assert_eq!(
+            exploded.to_time_ignoring_offset().unwrap(),
+            Time::from(784111777000000 - 5 * 3600 * 1_000_000)
+        );
+
+        let local = t.to_local().unwrap();
+        assert_eq!(local.to_time().unwrap(), t);
+    }
+
+    #[test]
+    fn test_strftime() {
+        let exploded = Time::from(784111777000000).to_gmt().unwrap();
+        assert_eq!(
+            exploded.strftime("%Y-%m-%d %H:%M:%S").unwrap(),
+            "1994-11-06 08:49:37"
+        );
+        assert_eq!(exploded.strftime("%a, %d %b").unwrap(), "Sun, 06 Nov");
+        assert_eq!(exploded.strftime("").unwrap(), "");
+        assert_eq!(exploded.strftime(&"%Y".repeat(200)).unwrap().len(), 800);
+    }
+
+    #[test]
+    fn test_from_ansi() {
+        assert_eq!(
+            Time::from_ansi(784111777).unwrap(),
+            Time::from(784111777000000)
+        );
+    }
+
     #[test]
     #[cfg(feature = "std")]
     fn test_system_time_conversion() {